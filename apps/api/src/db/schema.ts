@@ -1,11 +1,13 @@
 import { createId } from "@paralleldrive/cuid2";
 import {
 	bigint,
+	customType,
 	json,
 	mysqlEnum,
 	mysqlTable,
 	primaryKey,
 	serial,
+	text,
 	timestamp,
 	unique,
 	varchar,
@@ -24,6 +26,10 @@ const serialRelation = (name: string) =>
 const cuid = (name: string) =>
 	varchar(name, { length: 24 }).$default(() => createId());
 
+const mediumblob = customType<{ data: Buffer }>({
+	dataType: () => "mediumblob",
+});
+
 export type TableID<Table extends string> = number & { __table: Table };
 
 export const waitlistInterestReasons = [
@@ -153,6 +159,7 @@ export const possibleOSes = [
 	"tvOS",
 	"Android",
 	"ChromeOS",
+	"Linux",
 ] as const;
 
 export const devices = mysqlTable("devices", {
@@ -180,6 +187,9 @@ export const devices = mysqlTable("devices", {
 	freeStorageSpaceInBytes: bigint("free_storage", { mode: "number" }),
 	totalStorageSpaceInBytes: bigint("total_storage", { mode: "number" }),
 
+	// The latest inventory reported by the Mattrax agent (`mattraxd`)
+	inventory: json("inventory"),
+
 	// owner: serialRelation("owner").references(() => users.pk),
 
 	azureADDeviceId: varchar("azure_ad_did", { length: 256 }).unique(),
@@ -196,18 +206,35 @@ export const devices = mysqlTable("devices", {
 		.notNull(),
 });
 
+// Enrollment tokens are used by the Mattrax agent (`mattraxd`) to enroll a device into a tenant.
+export const enrollmentTokens = mysqlTable("enrollment_tokens", {
+	token: varchar("token", { length: 64 }).primaryKey(),
+	tenantPk: serialRelation("tenant")
+		.references(() => tenants.pk)
+		.notNull(),
+	createdBy: serialRelation("created_by").references(() => accounts.pk),
+	createdAt: timestamp("created_at").notNull().defaultNow(),
+	// If null the token never expires
+	expiresAt: timestamp("expires_at"),
+});
+
 export const possibleDeviceActions = [
 	"restart",
 	"shutdown",
 	"lost",
 	"wipe",
 	"retire",
+	"sync",
+	"script",
+	"logs",
 ] as const;
 
-// Device actions are ephemeral. They will be deleted after they are completed.
+// Device actions are pushed to `mattraxd` over its WebSocket, or delivered on its next check-in.
+// Only the latest action of each type is kept for a device, along with its result.
 export const deviceActions = mysqlTable(
 	"device_actions",
 	{
+		id: cuid("id").notNull().unique(),
 		action: mysqlEnum("action", possibleDeviceActions).notNull(),
 		devicePk: serialRelation("device")
 			.notNull()
@@ -216,6 +243,11 @@ export const deviceActions = mysqlTable(
 			.notNull()
 			.references(() => accounts.pk),
 		createdAt: timestamp("created_at").notNull().defaultNow(),
+		// The script for `script` actions
+		payload: json("payload"),
+		ackedAt: timestamp("acked_at"),
+		completedAt: timestamp("completed_at"),
+		result: json("result"),
 	},
 	(table) => ({
 		pk: primaryKey({ columns: [table.action, table.devicePk] }),
@@ -230,3 +262,59 @@ export const deviceAuthorities = mysqlTable("device_authority", {
 	createdAt: timestamp("created_at").notNull(),
 	expiresAt: timestamp("expires_at").notNull(),
 });
+
+// Signed release manifests for the Mattrax agent (`mattraxd`), published by the release pipeline.
+export const agentReleases = mysqlTable("agent_releases", {
+	channel: varchar("channel", { length: 32 }).primaryKey(),
+	// The signature is over the exact bytes of the manifest so it's stored as text
+	manifest: text("manifest").notNull(),
+	signature: varchar("signature", { length: 128 }).notNull(),
+	updatedAt: timestamp("updated_at").notNull().defaultNow(),
+});
+
+// Diagnostics bundles uploaded by `mattraxd`. The bundle is stored in chunks so an interrupted upload can be resumed.
+export const deviceDiagnostics = mysqlTable("device_diagnostics", {
+	pk: serial("pk").primaryKey(),
+	id: cuid("id").notNull().unique(),
+	devicePk: serialRelation("device")
+		.notNull()
+		.references(() => devices.pk),
+	// The `device_actions` id which requested the bundle
+	action: varchar("action", { length: 24 }),
+	size: bigint("size", { mode: "number", unsigned: true }).notNull(),
+	sha256: varchar("sha256", { length: 64 }).notNull(),
+	received: bigint("received", { mode: "number", unsigned: true })
+		.notNull()
+		.default(0),
+	createdAt: timestamp("created_at").notNull().defaultNow(),
+	completedAt: timestamp("completed_at"),
+});
+
+export const deviceDiagnosticsChunks = mysqlTable(
+	"device_diagnostics_chunks",
+	{
+		diagnosticsPk: serialRelation("diagnostics")
+			.notNull()
+			.references(() => deviceDiagnostics.pk),
+		offset: bigint("offset", { mode: "number", unsigned: true }).notNull(),
+		data: mediumblob("data").notNull(),
+	},
+	(table) => ({
+		pk: primaryKey({ columns: [table.diagnosticsPk, table.offset] }),
+	}),
+);
+
+// Idempotency keys of the requests `mattraxd` sent from its outbox, so a retried request isn't applied twice.
+export const deviceRequests = mysqlTable(
+	"device_requests",
+	{
+		devicePk: serialRelation("device")
+			.notNull()
+			.references(() => devices.pk),
+		idempotencyKey: varchar("idempotency_key", { length: 64 }).notNull(),
+		createdAt: timestamp("created_at").notNull().defaultNow(),
+	},
+	(table) => ({
+		pk: primaryKey({ columns: [table.devicePk, table.idempotencyKey] }),
+	}),
+);
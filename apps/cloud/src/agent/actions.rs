@@ -0,0 +1,218 @@
+//! Delivery of `device_actions` to the agent.
+//!
+//! Agents hold a WebSocket open to receive actions as soon as they are created.
+//! Actions which aren't acknowledged over the socket are also returned from the check-in, so they are still delivered when the socket is down.
+//! Results are always reported over HTTP, as the agent queues them in its outbox until they are delivered.
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::StatusCode,
+    response::Response,
+    Extension, Json,
+};
+use mx_agent_api::{Action, ActionKind, ActionResult, AgentMessage, ServerMessage};
+use mysql_async::{prelude::*, Conn};
+use serde_json::Value;
+use tracing::{debug, error, warn};
+
+use super::{internal_error, Device};
+use crate::Context;
+
+// Sessions are notified when actions are written through the SQL adapter, this catches any written to the database directly.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+// An acknowledged action which hasn't completed by now is redelivered on check-in, in case the agent restarted while performing it.
+const REDELIVER_AFTER_MINUTES: u32 = 60;
+
+/// Get the actions which haven't been completed by the device.
+///
+/// If `redeliver` is set, actions which were acknowledged long enough ago are included.
+pub async fn pending(
+    conn: &mut Conn,
+    device_pk: u64,
+    redeliver: bool,
+) -> Result<Vec<Action>, mysql_async::Error> {
+    let actions: Vec<(String, String, Option<String>)> = conn
+        .exec(
+            "SELECT `id`, `action`, `payload` FROM `device_actions` WHERE `device` = ? AND `completed_at` IS NULL AND (`acked_at` IS NULL OR (? AND `acked_at` < NOW() - INTERVAL ? MINUTE)) ORDER BY `created_at`",
+            (device_pk, redeliver, REDELIVER_AFTER_MINUTES),
+        )
+        .await?;
+
+    Ok(actions
+        .into_iter()
+        .filter_map(|(id, action, payload)| {
+            let Ok(kind) = serde_json::from_value::<ActionKind>(Value::String(action.clone()))
+            else {
+                warn!("Ignoring device action {id:?} with unknown type {action:?}");
+                return None;
+            };
+
+            // The agent reports a `script` action without a script as failed
+            let script = match (kind, payload) {
+                (ActionKind::Script, Some(payload)) => serde_json::from_str(&payload)
+                    .map_err(|err| warn!("Error decoding script of device action {id:?}: {err}"))
+                    .ok(),
+                _ => None,
+            };
+
+            Some(Action { id, kind, script })
+        })
+        .collect())
+}
+
+/// Determine if a query run through the SQL adapter writes to `device_actions`.
+pub fn modifies_actions(query: &str) -> bool {
+    let query = query.trim_start().to_ascii_lowercase();
+    ["insert", "replace", "update"]
+        .iter()
+        .any(|verb| query.starts_with(verb))
+        && query.contains("device_actions")
+}
+
+pub async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<Context>>,
+    Extension(device): Extension<Device>,
+) -> Response {
+    ws.on_upgrade(move |socket| session(socket, state, device))
+}
+
+async fn session(mut socket: WebSocket, state: Arc<Context>, device: Device) {
+    debug!("Device {:?} connected to the actions socket", device.id);
+
+    // Actions already sent during this session. They are resent by the check-in if the agent never acknowledges them.
+    let mut sent = HashSet::new();
+    let mut created = state.action_created.subscribe();
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            // Lagging behind means actions were created, so it's handled the same as a notification
+            _ = created.recv() => {}
+            _ = poll.tick() => {}
+            msg = socket.recv() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str::<AgentMessage>(&text) {
+                        Ok(AgentMessage::Ack { id }) => {
+                            if let Err(err) = ack(&state, device.pk, &id).await {
+                                error!("Error acknowledging action {id:?} for device {:?}: {err}", device.id);
+                            }
+                        }
+                        Err(err) => debug!("Ignoring invalid message from device {:?}: {err}", device.id),
+                    },
+                    Some(Ok(Message::Close(_))) | None => break,
+                    // Pings are answered by Axum
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => {
+                        debug!("Error reading from device {:?}: {err}", device.id);
+                        break;
+                    }
+                }
+                continue;
+            }
+        }
+
+        let actions = match state.db.get_conn().await {
+            Ok(mut conn) => pending(&mut conn, device.pk, false).await,
+            Err(err) => Err(err),
+        };
+        let actions = match actions {
+            Ok(actions) => actions,
+            Err(err) => {
+                error!("Error getting actions for device {:?}: {err}", device.id);
+                continue;
+            }
+        };
+
+        for action in actions {
+            if !sent.insert(action.id.clone()) {
+                continue;
+            }
+
+            debug!("Sending action {:?} to device {:?}", action.id, device.id);
+            let msg = serde_json::to_string(&ServerMessage::Action(action)).unwrap();
+            if socket.send(Message::Text(msg)).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    debug!(
+        "Device {:?} disconnected from the actions socket",
+        device.id
+    );
+}
+
+async fn ack(state: &Context, device_pk: u64, id: &str) -> Result<(), mysql_async::Error> {
+    state
+        .db
+        .get_conn()
+        .await?
+        .exec_drop(
+            "UPDATE `device_actions` SET `acked_at` = NOW() WHERE `id` = ? AND `device` = ? AND `acked_at` IS NULL",
+            (id, device_pk),
+        )
+        .await
+}
+
+pub async fn result_handler(
+    State(state): State<Arc<Context>>,
+    Extension(device): Extension<Device>,
+    Json(result): Json<ActionResult>,
+) -> Result<StatusCode, Response> {
+    let mut conn = state.db.get_conn().await.map_err(|err| {
+        error!("Error getting DB connection: {err}");
+        internal_error()
+    })?;
+
+    // The action may have been replaced by a newer one of the same type, in which case the result is dropped
+    conn.exec_drop(
+        "UPDATE `device_actions` SET `acked_at` = COALESCE(`acked_at`, NOW()), `completed_at` = NOW(), `result` = ? WHERE `id` = ? AND `device` = ?",
+        (
+            serde_json::json!({
+                "success": result.success,
+                "output": result.output,
+                "script": result.script,
+            })
+            .to_string(),
+            &result.id,
+            device.pk,
+        ),
+    )
+    .await
+    .map_err(|err| {
+        error!(
+            "Error saving result of action {:?} for device {:?}: {err}",
+            result.id, device.id
+        );
+        internal_error()
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_modifies_actions() {
+        assert!(modifies_actions(
+            "insert into `device_actions` (`id`, `action`, `device`) values ('a', 'restart', 1)"
+        ));
+        assert!(modifies_actions(
+            "  UPDATE device_actions SET `completed_at` = NULL WHERE `id` = 'a'"
+        ));
+        assert!(!modifies_actions(
+            "select `id` from `device_actions` where `device` = 1"
+        ));
+        assert!(!modifies_actions(
+            "insert into `devices` (`id`) values ('a')"
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+//! Diagnostics bundles collected by the agent.
+//!
+//! Bundles are uploaded in chunks of [`DIAGNOSTICS_CHUNK_SIZE`] which are appended in order, so an interrupted upload resumes from [`DiagnosticsStatus::received`].
+
+use std::sync::Arc;
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use mx_agent_api::{
+    DiagnosticsStatus, DiagnosticsUpload, DIAGNOSTICS_CHUNK_SIZE, MAX_DIAGNOSTICS_SIZE,
+};
+use mysql_async::{prelude::*, Conn, TxOpts};
+use openssl::sha::Sha256;
+use tracing::{error, info, warn};
+
+use super::{internal_error, is_duplicate_key, Device};
+use crate::Context;
+
+pub async fn start_handler(
+    State(state): State<Arc<Context>>,
+    Extension(device): Extension<Device>,
+    Json(upload): Json<DiagnosticsUpload>,
+) -> Result<Json<DiagnosticsStatus>, Response> {
+    if upload.size == 0 || upload.size > MAX_DIAGNOSTICS_SIZE {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, "Invalid bundle size").into_response());
+    }
+    if upload.sha256.len() != 64 || !upload.sha256.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err((StatusCode::BAD_REQUEST, "Invalid checksum").into_response());
+    }
+
+    let mut conn = state.db.get_conn().await.map_err(|err| {
+        error!("Error getting DB connection: {err}");
+        internal_error()
+    })?;
+
+    let id = cuid2::create_id();
+    conn.exec_drop(
+        "INSERT INTO `device_diagnostics` (`id`, `device`, `action`, `size`, `sha256`) VALUES (?, ?, ?, ?, ?)",
+        (
+            &id,
+            device.pk,
+            &upload.action,
+            upload.size,
+            upload.sha256.to_lowercase(),
+        ),
+    )
+    .await
+    .map_err(|err| {
+        error!(
+            "Error creating diagnostics upload for device {:?}: {err}",
+            device.id
+        );
+        internal_error()
+    })?;
+
+    Ok(Json(DiagnosticsStatus {
+        id,
+        received: 0,
+        complete: false,
+    }))
+}
+
+pub async fn status_handler(
+    State(state): State<Arc<Context>>,
+    Extension(device): Extension<Device>,
+    Path(id): Path<String>,
+) -> Result<Json<DiagnosticsStatus>, Response> {
+    let mut conn = state.db.get_conn().await.map_err(|err| {
+        error!("Error getting DB connection: {err}");
+        internal_error()
+    })?;
+
+    let upload = find(&mut conn, &device, &id).await?;
+    Ok(Json(upload.status(id)))
+}
+
+pub async fn chunk_handler(
+    State(state): State<Arc<Context>>,
+    Extension(device): Extension<Device>,
+    Path((id, offset)): Path<(String, u64)>,
+    chunk: Bytes,
+) -> Result<Json<DiagnosticsStatus>, Response> {
+    let mut conn = state.db.get_conn().await.map_err(|err| {
+        error!("Error getting DB connection: {err}");
+        internal_error()
+    })?;
+
+    let upload = find(&mut conn, &device, &id).await?;
+    // The previous attempt may have succeeded without the agent receiving the response
+    if upload.complete || offset != upload.received {
+        return Err((StatusCode::CONFLICT, Json(upload.status(id))).into_response());
+    }
+    if chunk.is_empty()
+        || chunk.len() > DIAGNOSTICS_CHUNK_SIZE
+        || offset + chunk.len() as u64 > upload.size
+    {
+        return Err((StatusCode::BAD_REQUEST, "Invalid chunk").into_response());
+    }
+
+    let received = offset + chunk.len() as u64;
+    let db_error = |err: mysql_async::Error| {
+        error!("Error saving chunk of diagnostics {id:?}: {err}");
+        internal_error()
+    };
+    let mut tx = conn
+        .start_transaction(TxOpts::default())
+        .await
+        .map_err(db_error)?;
+    if let Err(err) = tx
+        .exec_drop(
+            "INSERT INTO `device_diagnostics_chunks` (`diagnostics`, `offset`, `data`) VALUES (?, ?, ?)",
+            (upload.pk, offset, chunk.as_ref()),
+        )
+        .await
+    {
+        if !is_duplicate_key(&err) {
+            return Err(db_error(err));
+        }
+
+        // A concurrent upload of the same chunk won the race, so the agent continues from wherever that left off
+        tx.rollback().await.map_err(db_error)?;
+        let upload = find(&mut conn, &device, &id).await?;
+        return Err((StatusCode::CONFLICT, Json(upload.status(id))).into_response());
+    }
+    tx.exec_drop(
+        "UPDATE `device_diagnostics` SET `received` = ? WHERE `pk` = ?",
+        (received, upload.pk),
+    )
+    .await
+    .map_err(db_error)?;
+    tx.commit().await.map_err(db_error)?;
+
+    if received < upload.size {
+        return Ok(Json(DiagnosticsStatus {
+            id,
+            received,
+            complete: false,
+        }));
+    }
+
+    let sha256 = checksum(&mut conn, upload.pk).await.map_err(|err| {
+        error!("Error reading diagnostics {id:?}: {err}");
+        internal_error()
+    })?;
+    if sha256 != upload.sha256 {
+        warn!(
+            "Diagnostics {id:?} from device {:?} failed the checksum",
+            device.id
+        );
+        // Start the upload over rather than keeping a corrupt bundle
+        conn.exec_drop(
+            "DELETE FROM `device_diagnostics_chunks` WHERE `diagnostics` = ?",
+            (upload.pk,),
+        )
+        .await
+        .map_err(db_error)?;
+        conn.exec_drop(
+            "UPDATE `device_diagnostics` SET `received` = 0 WHERE `pk` = ?",
+            (upload.pk,),
+        )
+        .await
+        .map_err(db_error)?;
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, "Checksum mismatch").into_response());
+    }
+
+    conn.exec_drop(
+        "UPDATE `device_diagnostics` SET `completed_at` = NOW() WHERE `pk` = ?",
+        (upload.pk,),
+    )
+    .await
+    .map_err(db_error)?;
+    info!("Received diagnostics {id:?} from device {:?}", device.id);
+
+    Ok(Json(DiagnosticsStatus {
+        id,
+        received,
+        complete: true,
+    }))
+}
+
+struct Upload {
+    pk: u64,
+    size: u64,
+    sha256: String,
+    received: u64,
+    complete: bool,
+}
+
+impl Upload {
+    fn status(&self, id: String) -> DiagnosticsStatus {
+        DiagnosticsStatus {
+            id,
+            received: self.received,
+            complete: self.complete,
+        }
+    }
+}
+
+async fn find(conn: &mut Conn, device: &Device, id: &str) -> Result<Upload, Response> {
+    let upload: Option<(u64, u64, String, u64, bool)> = conn
+        .exec_first(
+            "SELECT `pk`, `size`, `sha256`, `received`, `completed_at` IS NOT NULL FROM `device_diagnostics` WHERE `id` = ? AND `device` = ?",
+            (id, device.pk),
+        )
+        .await
+        .map_err(|err| {
+            error!("Error getting diagnostics {id:?}: {err}");
+            internal_error()
+        })?;
+
+    let Some((pk, size, sha256, received, complete)) = upload else {
+        return Err((StatusCode::NOT_FOUND, "Not Found").into_response());
+    };
+    Ok(Upload {
+        pk,
+        size,
+        sha256,
+        received,
+        complete,
+    })
+}
+
+// Chunks are hashed one at a time so the whole bundle is never held in memory.
+async fn checksum(conn: &mut Conn, pk: u64) -> Result<String, mysql_async::Error> {
+    let offsets: Vec<u64> = conn
+        .exec(
+            "SELECT `offset` FROM `device_diagnostics_chunks` WHERE `diagnostics` = ? ORDER BY `offset`",
+            (pk,),
+        )
+        .await?;
+
+    let mut hasher = Sha256::new();
+    for offset in offsets {
+        let data: Option<Vec<u8>> = conn
+            .exec_first(
+                "SELECT `data` FROM `device_diagnostics_chunks` WHERE `diagnostics` = ? AND `offset` = ?",
+                (pk, offset),
+            )
+            .await?;
+        hasher.update(&data.unwrap_or_default());
+    }
+
+    Ok(hasher.finish().iter().map(|b| format!("{b:02x}")).collect())
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use mx_agent_api::{EnrollRequest, EnrollResponse};
+use mysql_async::{prelude::*, TxOpts};
+use openssl::x509::X509Req;
+use tracing::{error, info};
+
+use super::{internal_error, is_duplicate_key};
+use crate::Context;
+
+pub async fn handler(
+    State(state): State<Arc<Context>>,
+    Json(req): Json<EnrollRequest>,
+) -> Result<Json<EnrollResponse>, Response> {
+    let mut conn = state.db.get_conn().await.map_err(|err| {
+        error!("Error getting DB connection: {err}");
+        internal_error()
+    })?;
+
+    let tenant_pk: u64 = conn
+        .exec_first(
+            "SELECT `tenant` FROM `enrollment_tokens` WHERE `token` = ? AND (`expires_at` IS NULL OR `expires_at` > NOW())",
+            (&req.token,),
+        )
+        .await
+        .map_err(|err| {
+            error!("Error looking up enrollment token: {err}");
+            internal_error()
+        })?
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Invalid enrollment token").into_response())?;
+
+    let csr = X509Req::from_pem(req.csr.as_bytes())
+        .ok()
+        .filter(|csr| {
+            csr.public_key()
+                .and_then(|key| csr.verify(&key))
+                .unwrap_or(false)
+        })
+        .ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                "Invalid certificate signing request",
+            )
+                .into_response()
+        })?;
+
+    // The MDM ID is used as the certificate's subject so it changes on every enrollment.
+    // This means certificates from a previous enrollment of the same device are no longer accepted.
+    let mdm_id = cuid2::create_id();
+
+    let mut tx = conn
+        .start_transaction(TxOpts::default())
+        .await
+        .map_err(|err| {
+            error!("Error starting transaction: {err}");
+            internal_error()
+        })?;
+
+    let existing: Option<(String, u64)> = tx
+        .exec_first(
+            "SELECT `id`, `tenant` FROM `devices` WHERE `serial_number` = ? FOR UPDATE",
+            (&req.serial_number,),
+        )
+        .await
+        .map_err(|err| {
+            error!("Error looking up existing device: {err}");
+            internal_error()
+        })?;
+
+    let device_id = match existing {
+        // Re-enrolling the same hardware into the same tenant reuses the existing device record
+        Some((device_id, tenant)) if tenant == tenant_pk => {
+            tx.exec_drop(
+                "UPDATE `devices` SET `mdm_id` = ?, `name` = ?, `manufacturer` = ?, `model` = ?, `os_version` = ?, `enrolled_at` = NOW() WHERE `id` = ?",
+                (
+                    &mdm_id,
+                    &req.name,
+                    &req.manufacturer,
+                    &req.model,
+                    &req.os_version,
+                    &device_id,
+                ),
+            )
+            .await
+            .map_err(|err| {
+                error!("Error updating enrolled device {device_id:?}: {err}");
+                internal_error()
+            })?;
+            device_id
+        }
+        // A device can't be moved to another tenant by enrolling it with that tenant's token.
+        // It must be removed from its current tenant first.
+        Some(_) => return Err(already_enrolled()),
+        None => {
+            let device_id = cuid2::create_id();
+            tx.exec_drop(
+                "INSERT INTO `devices` (`id`, `mdm_id`, `name`, `enrollment_type`, `os`, `serial_number`, `manufacturer`, `model`, `os_version`, `tenant`) VALUES (?, ?, ?, 'device', 'Linux', ?, ?, ?, ?, ?)",
+                (
+                    &device_id,
+                    &mdm_id,
+                    &req.name,
+                    &req.serial_number,
+                    &req.manufacturer,
+                    &req.model,
+                    &req.os_version,
+                    tenant_pk,
+                ),
+            )
+            .await
+            .map_err(|err| {
+                // Another enrollment of the same serial number won the race
+                if is_duplicate_key(&err) {
+                    return already_enrolled();
+                }
+                error!("Error saving enrolled device: {err}");
+                internal_error()
+            })?;
+            device_id
+        }
+    };
+
+    tx.commit().await.map_err(|err| {
+        error!("Error committing enrolled device: {err}");
+        internal_error()
+    })?;
+
+    let authority = state.authority.active(&state.db).await.map_err(|err| {
+        error!("Error getting device authority: {err}");
+        internal_error()
+    })?;
+
+    let certificate = authority
+        .sign_device_csr(&csr, &mdm_id)
+        .and_then(|cert| cert.to_pem())
+        .map_err(|err| {
+            error!("Error signing device certificate: {err}");
+            internal_error()
+        })?;
+
+    info!("Enrolled device {device_id:?} into tenant {tenant_pk}");
+
+    Ok(Json(EnrollResponse {
+        device_id,
+        certificate: String::from_utf8_lossy(&certificate).to_string(),
+    }))
+}
+
+fn already_enrolled() -> Response {
+    (StatusCode::CONFLICT, "Device is already enrolled").into_response()
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::Response, Extension, Json};
+use mx_agent_api::Inventory;
+use mysql_async::prelude::*;
+use tracing::error;
+
+use super::{internal_error, Device};
+use crate::Context;
+
+pub async fn handler(
+    State(state): State<Arc<Context>>,
+    Extension(device): Extension<Device>,
+    Json(inventory): Json<Inventory>,
+) -> Result<StatusCode, Response> {
+    let mut conn = state.db.get_conn().await.map_err(|err| {
+        error!("Error getting DB connection: {err}");
+        internal_error()
+    })?;
+
+    let root_disk = inventory.root_disk();
+
+    // We keep the full document but also copy the fields shared with other platforms onto the device
+    conn.exec_drop(
+        "UPDATE `devices` SET `inventory` = ?, `name` = ?, `manufacturer` = ?, `model` = ?, `os_version` = ?, `total_storage` = ?, `free_storage` = ?, `last_synced` = NOW() WHERE `pk` = ?",
+        (
+            serde_json::to_string(&inventory).unwrap(),
+            &inventory.hostname,
+            &inventory.hardware.manufacturer,
+            &inventory.hardware.model,
+            &inventory.os.pretty_name,
+            root_disk.map(|disk| disk.total_bytes),
+            root_disk.map(|disk| disk.available_bytes),
+            device.pk,
+        ),
+    )
+    .await
+    .map_err(|err| {
+        error!("Error saving inventory for device {:?}: {err}", device.id);
+        internal_error()
+    })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
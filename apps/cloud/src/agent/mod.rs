@@ -0,0 +1,453 @@
+//! The API used by the Mattrax agent (`mattraxd`).
+//!
+//! Devices authenticate using the client certificate issued to them during enrollment.
+//! TLS is terminated by the mTLS proxy which forwards the certificate in the [`CLIENT_CERT_HEADER`] header.
+//! The header is only trusted when the request also carries the secret shared with the proxy in [`PROXY_SECRET_HEADER`].
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Extension, Json, Router,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use mx_agent_api::{
+    CheckinResponse, CLIENT_CERT_HEADER, IDEMPOTENCY_KEY_HEADER, PROXY_SECRET_HEADER,
+};
+use mysql_async::prelude::*;
+use openssl::{asn1::Asn1Time, memcmp, nid::Nid, x509::X509};
+use tracing::{debug, error};
+
+use crate::Context;
+
+mod actions;
+mod diagnostics;
+mod enroll;
+mod inventory;
+mod policies;
+mod update;
+
+pub(crate) use actions::modifies_actions;
+
+/// The device which made the current request.
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub pk: u64,
+    pub id: String,
+}
+
+pub fn mount(state: Arc<Context>) -> Router<Arc<Context>> {
+    Router::new()
+        .route("/checkin", post(checkin))
+        .route(
+            "/inventory",
+            post(inventory::handler)
+                .route_layer(middleware::from_fn_with_state(state.clone(), idempotent)),
+        )
+        .route("/policies", get(policies::handler))
+        .route(
+            "/policies/status",
+            post(policies::status_handler)
+                .route_layer(middleware::from_fn_with_state(state.clone(), idempotent)),
+        )
+        .route("/update", post(update::handler))
+        .route("/ws", get(actions::ws_handler))
+        .route(
+            "/actions/result",
+            post(actions::result_handler)
+                .route_layer(middleware::from_fn_with_state(state.clone(), idempotent)),
+        )
+        .route("/diagnostics", post(diagnostics::start_handler))
+        .route("/diagnostics/:id", get(diagnostics::status_handler))
+        .route("/diagnostics/:id/:offset", put(diagnostics::chunk_handler))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth))
+        .route("/enroll", post(enroll::handler))
+        .route(
+            "/releases/:channel",
+            put(update::publish)
+                .route_layer(middleware::from_fn_with_state(state, crate::sql::auth)),
+        )
+}
+
+pub async fn auth(State(state): State<Arc<Context>>, mut request: Request, next: Next) -> Response {
+    if !from_proxy(&state, &request) {
+        debug!("Rejected request which didn't come from the mTLS proxy");
+        return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    }
+
+    let Some(cert) = request
+        .headers()
+        .get(CLIENT_CERT_HEADER)
+        .and_then(|v| STANDARD.decode(v.as_bytes()).ok())
+        .and_then(|der| X509::from_der(&der).ok())
+    else {
+        return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    };
+
+    let truststore = match state.authority.truststore(&state.db).await {
+        Ok(truststore) => truststore,
+        Err(err) => {
+            error!("Error loading device authority truststore: {err}");
+            return internal_error();
+        }
+    };
+
+    let is_trusted = truststore.iter().any(|authority| {
+        authority
+            .public_key()
+            .and_then(|key| cert.verify(&key))
+            .unwrap_or(false)
+    });
+    let is_valid = Asn1Time::days_from_now(0)
+        .map(|now| cert.not_before() <= now && cert.not_after() >= now)
+        .unwrap_or(false);
+    if !is_trusted || !is_valid {
+        debug!("Rejected untrusted or expired client certificate");
+        return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    }
+
+    let Some(mdm_id) = cert
+        .subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .and_then(|cn| std::str::from_utf8(cn.data().as_slice()).ok())
+        .map(|cn| cn.to_string())
+    else {
+        return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response();
+    };
+
+    let device = match state.db.get_conn().await {
+        Ok(mut conn) => {
+            conn.exec_first::<(u64, String), _, _>(
+                "SELECT `pk`, `id` FROM `devices` WHERE `mdm_id` = ?",
+                (&mdm_id,),
+            )
+            .await
+        }
+        Err(err) => Err(err),
+    };
+
+    match device {
+        Ok(Some((pk, id))) => {
+            request.extensions_mut().insert(Device { pk, id });
+            next.run(request).await
+        }
+        // The device was deleted or has since re-enrolled
+        Ok(None) => (StatusCode::UNAUTHORIZED, "Unauthorized").into_response(),
+        Err(err) => {
+            error!("Error looking up device {mdm_id:?}: {err}");
+            internal_error()
+        }
+    }
+}
+
+/// Check the request carries the secret shared with the mTLS proxy, so the client certificate header wasn't set by the client.
+fn from_proxy(state: &Context, request: &Request) -> bool {
+    let (Some(secret), Some(header)) = (
+        state.proxy_secret.as_deref(),
+        request.headers().get(PROXY_SECRET_HEADER),
+    ) else {
+        return false;
+    };
+    let header = header.as_bytes();
+    header.len() == secret.len() && memcmp::eq(header, secret.as_bytes())
+}
+
+/// Skip requests from the agent's outbox which were already applied.
+///
+/// The agent sends a request again if it didn't receive the response, so the same request can arrive more than once.
+/// Requests are only recorded once they succeed so a failed request can be retried.
+async fn idempotent(
+    State(state): State<Arc<Context>>,
+    Extension(device): Extension<Device>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(key) = request
+        .headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|key| !key.is_empty() && key.len() <= 64)
+        .map(|key| key.to_string())
+    else {
+        return next.run(request).await;
+    };
+
+    let mut conn = match state.db.get_conn().await {
+        Ok(conn) => conn,
+        Err(err) => {
+            error!("Error getting DB connection: {err}");
+            return internal_error();
+        }
+    };
+
+    match conn
+        .exec_first::<u8, _, _>(
+            "SELECT 1 FROM `device_requests` WHERE `device` = ? AND `idempotency_key` = ?",
+            (device.pk, &key),
+        )
+        .await
+    {
+        Ok(Some(_)) => {
+            debug!(
+                "Skipping request {key:?} from device {:?} which was already applied",
+                device.id
+            );
+            return StatusCode::OK.into_response();
+        }
+        Ok(None) => {}
+        Err(err) => {
+            error!("Error looking up request {key:?}: {err}");
+            return internal_error();
+        }
+    }
+
+    let response = next.run(request).await;
+    if response.status().is_success() {
+        // Agents retry within minutes so old keys are removed as new ones are added
+        let result = async {
+            conn.exec_drop(
+                "INSERT IGNORE INTO `device_requests` (`device`, `idempotency_key`) VALUES (?, ?)",
+                (device.pk, &key),
+            )
+            .await?;
+            conn.exec_drop(
+                "DELETE FROM `device_requests` WHERE `device` = ? AND `created_at` < NOW() - INTERVAL 7 DAY",
+                (device.pk,),
+            )
+            .await
+        }
+        .await;
+        if let Err(err) = result {
+            error!("Error saving request {key:?}: {err}");
+        }
+    }
+    response
+}
+
+async fn checkin(
+    State(state): State<Arc<Context>>,
+    Extension(device): Extension<Device>,
+) -> Result<Json<CheckinResponse>, Response> {
+    let mut conn = state.db.get_conn().await.map_err(|err| {
+        error!("Error getting DB connection: {err}");
+        internal_error()
+    })?;
+
+    conn.exec_drop(
+        "UPDATE `devices` SET `last_synced` = NOW() WHERE `pk` = ?",
+        (device.pk,),
+    )
+    .await
+    .map_err(|err| {
+        error!("Error updating device {:?}: {err}", device.id);
+        internal_error()
+    })?;
+
+    let policies = policies::assigned(&mut conn, device.pk)
+        .await
+        .map_err(|err| {
+            error!("Error getting policies for device {:?}: {err}", device.id);
+            internal_error()
+        })?
+        .into_iter()
+        .map(|(_, policy)| policy)
+        .collect();
+
+    let actions = actions::pending(&mut conn, device.pk, true)
+        .await
+        .map_err(|err| {
+            error!("Error getting actions for device {:?}: {err}", device.id);
+            internal_error()
+        })?;
+
+    Ok(Json(CheckinResponse {
+        device_id: device.id,
+        policies,
+        actions,
+    }))
+}
+
+fn internal_error() -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response()
+}
+
+/// Check if a query failed because it violated a unique constraint.
+fn is_duplicate_key(err: &mysql_async::Error) -> bool {
+    // `ER_DUP_ENTRY`
+    matches!(err, mysql_async::Error::Server(err) if err.code == 1062)
+}
+
+#[cfg(test)]
+mod test {
+    use mx_agent_api::{EnrollRequest, EnrollResponse};
+    use openssl::{pkey::PKey, rsa::Rsa, x509::X509Req};
+
+    use super::*;
+    use crate::authority::Authority;
+
+    fn csr() -> String {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut csr = X509Req::builder().unwrap();
+        csr.set_pubkey(&key).unwrap();
+        csr.sign(&key, openssl::hash::MessageDigest::sha256())
+            .unwrap();
+        String::from_utf8(csr.build().to_pem().unwrap()).unwrap()
+    }
+
+    /// Create a tenant and a token to enroll into it.
+    async fn enrollment_token(conn: &mut mysql_async::Conn) -> String {
+        let tenant = cuid2::create_id();
+        let token = cuid2::create_id();
+        conn.exec_drop(
+            "INSERT INTO `tenant` (`id`, `name`, `slug`) VALUES (?, ?, ?)",
+            (&tenant, "Test", &tenant),
+        )
+        .await
+        .unwrap();
+        conn.exec_drop(
+            "INSERT INTO `enrollment_tokens` (`token`, `tenant`) SELECT ?, `pk` FROM `tenant` WHERE `id` = ?",
+            (&token, &tenant),
+        )
+        .await
+        .unwrap();
+        token
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a MySQL database in TEST_DATABASE_URL"]
+    async fn test_enroll() {
+        let url = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL is not set");
+        let mut db = mx_db::Db::new(&url);
+        mx_db::migrations::runner()
+            .run_async(&mut *db)
+            .await
+            .unwrap();
+
+        // Every run uses its own tenants and serial number so the database can be reused
+        let mut conn = db.get_conn().await.unwrap();
+        let token_a = enrollment_token(&mut conn).await;
+        let token_b = enrollment_token(&mut conn).await;
+        drop(conn);
+
+        let context = Context {
+            internal_secret: "internal".into(),
+            db,
+            proxy_secret: Some("proxy-secret".into()),
+            action_created: tokio::sync::broadcast::channel(1).0,
+            authority: Default::default(),
+            telemetry: None,
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}/agent", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, context.mount()).await });
+
+        let client = reqwest::Client::new();
+        let serial_number = cuid2::create_id();
+        let enroll = |token: &str| {
+            let req = client.post(format!("{base}/enroll")).json(&EnrollRequest {
+                token: token.into(),
+                csr: csr(),
+                name: "test".into(),
+                serial_number: serial_number.clone(),
+                manufacturer: None,
+                model: None,
+                os_version: None,
+            });
+            async move { req.send().await.unwrap() }
+        };
+        let checkin = |certificate: &str| {
+            let cert = X509::from_pem(certificate.as_bytes()).unwrap();
+            client
+                .post(format!("{base}/checkin"))
+                .header(CLIENT_CERT_HEADER, STANDARD.encode(cert.to_der().unwrap()))
+                .header(PROXY_SECRET_HEADER, "proxy-secret")
+                .send()
+        };
+
+        assert_eq!(enroll("invalid").await.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = enroll(&token_a).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let first: EnrollResponse = resp.json().await.unwrap();
+
+        let resp = checkin(&first.certificate).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp: CheckinResponse = resp.json().await.unwrap();
+        assert_eq!(resp.device_id, first.device_id);
+
+        // Re-enrolling into the same tenant keeps the device and revokes the previous certificate
+        let resp = enroll(&token_a).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let second: EnrollResponse = resp.json().await.unwrap();
+        assert_eq!(second.device_id, first.device_id);
+        assert_eq!(
+            checkin(&first.certificate).await.unwrap().status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            checkin(&second.certificate).await.unwrap().status(),
+            StatusCode::OK
+        );
+
+        // The device can't be taken over by another tenant
+        assert_eq!(enroll(&token_b).await.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            checkin(&second.certificate).await.unwrap().status(),
+            StatusCode::OK
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forged_client_cert() {
+        let context = Context {
+            internal_secret: "internal".into(),
+            // Nothing listens here, the rejected requests must never reach the database
+            db: mx_db::Db::new("mysql://mattrax@127.0.0.1:9/mattrax"),
+            proxy_secret: Some("proxy-secret".into()),
+            action_created: tokio::sync::broadcast::channel(1).0,
+            authority: Default::default(),
+            telemetry: None,
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/agent/checkin", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, context.mount()).await });
+
+        // A certificate for another device's identity, signed by an authority the attacker controls
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut csr = X509Req::builder().unwrap();
+        csr.set_pubkey(&key).unwrap();
+        csr.sign(&key, openssl::hash::MessageDigest::sha256())
+            .unwrap();
+        let cert = Authority::issue()
+            .unwrap()
+            .sign_device_csr(&csr.build(), "victim")
+            .unwrap();
+        let cert = STANDARD.encode(cert.to_der().unwrap());
+
+        let client = reqwest::Client::new();
+        let checkin = |secret: Option<&str>| {
+            let mut req = client.post(&url).header(CLIENT_CERT_HEADER, &cert);
+            if let Some(secret) = secret {
+                req = req.header(PROXY_SECRET_HEADER, secret);
+            }
+            async move { req.send().await.unwrap().status() }
+        };
+
+        assert_eq!(checkin(None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(checkin(Some("")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            checkin(Some("proxy-secret2")).await,
+            StatusCode::UNAUTHORIZED
+        );
+        // With the proxy's secret the certificate is checked against the truststore, which fails without a database
+        assert_eq!(
+            checkin(Some("proxy-secret")).await,
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+}
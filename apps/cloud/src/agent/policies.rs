@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::Response, Extension, Json};
+use mx_agent_api::{DeployedPolicy, PolicyStatusReport};
+use mx_policy::{LinuxConfig, Script};
+use mysql_async::{prelude::*, Conn};
+use serde::Deserialize;
+use tracing::{debug, error, warn};
+
+use super::{internal_error, Device};
+use crate::Context;
+
+/// The parts of a deploy's `PolicyData` which are applied by the agent.
+#[derive(Deserialize)]
+struct DeployData {
+    #[serde(default)]
+    scripts: Vec<Script>,
+    #[serde(default)]
+    linux: Option<LinuxConfig>,
+}
+
+/// Get the latest deploy of every policy assigned to the device, either directly or through a group.
+pub async fn assigned(
+    conn: &mut Conn,
+    device_pk: u64,
+) -> Result<Vec<(u64, DeployedPolicy)>, mysql_async::Error> {
+    let deploys: Vec<(u64, String, String, String, String)> = conn
+        .exec(
+            "SELECT `d`.`pk`, `d`.`id`, `p`.`id`, `p`.`name`, `d`.`data` FROM `policy_deploy` `d` INNER JOIN `policies` `p` ON `p`.`pk` = `d`.`policy` WHERE `d`.`pk` IN (SELECT MAX(`pk`) FROM `policy_deploy` GROUP BY `policy`) AND (EXISTS (SELECT 1 FROM `policy_assignables` `pa` WHERE `pa`.`policy` = `p`.`pk` AND `pa`.`variant` = 'device' AND `pa`.`pk` = ?) OR EXISTS (SELECT 1 FROM `policy_assignables` `pa` INNER JOIN `group_assignables` `ga` ON `ga`.`group` = `pa`.`pk` WHERE `pa`.`policy` = `p`.`pk` AND `pa`.`variant` = 'group' AND `ga`.`variant` = 'device' AND `ga`.`pk` = ?)) ORDER BY `p`.`priority`, `p`.`pk`",
+            (device_pk, device_pk),
+        )
+        .await?;
+
+    Ok(deploys
+        .into_iter()
+        .map(|(pk, deploy_id, policy_id, name, data)| {
+            // A malformed deploy shouldn't prevent the device from receiving the rest
+            let data = serde_json::from_str::<DeployData>(&data).unwrap_or_else(|err| {
+                warn!("Error decoding data of policy deploy {deploy_id:?}: {err}");
+                DeployData {
+                    scripts: vec![],
+                    linux: None,
+                }
+            });
+
+            (
+                pk,
+                DeployedPolicy {
+                    deploy_id,
+                    policy_id,
+                    name,
+                    scripts: data.scripts,
+                    linux: data.linux,
+                },
+            )
+        })
+        .collect())
+}
+
+/// The policies assigned to the device. Unlike a check-in this has no side effects, so it's used for dry runs.
+pub async fn handler(
+    State(state): State<Arc<Context>>,
+    Extension(device): Extension<Device>,
+) -> Result<Json<Vec<DeployedPolicy>>, Response> {
+    let mut conn = state.db.get_conn().await.map_err(|err| {
+        error!("Error getting DB connection: {err}");
+        internal_error()
+    })?;
+
+    let policies = assigned(&mut conn, device.pk).await.map_err(|err| {
+        error!("Error getting policies for device {:?}: {err}", device.id);
+        internal_error()
+    })?;
+
+    Ok(Json(
+        policies.into_iter().map(|(_, policy)| policy).collect(),
+    ))
+}
+
+pub async fn status_handler(
+    State(state): State<Arc<Context>>,
+    Extension(device): Extension<Device>,
+    Json(reports): Json<Vec<PolicyStatusReport>>,
+) -> Result<StatusCode, Response> {
+    let mut conn = state.db.get_conn().await.map_err(|err| {
+        error!("Error getting DB connection: {err}");
+        internal_error()
+    })?;
+
+    let deploys = assigned(&mut conn, device.pk).await.map_err(|err| {
+        error!("Error getting policies for device {:?}: {err}", device.id);
+        internal_error()
+    })?;
+
+    for report in reports {
+        // The policy may have been unassigned or redeployed since the device checked in
+        let Some((deploy_pk, _)) = deploys
+            .iter()
+            .find(|(_, deploy)| deploy.deploy_id == report.deploy_id)
+        else {
+            debug!(
+                "Ignoring status of unassigned policy deploy {:?} from device {:?}",
+                report.deploy_id, device.id
+            );
+            continue;
+        };
+
+        conn.exec_drop(
+            "INSERT INTO `policy_deploy_status` (`deploy`, `device`, `variant`, `result`, `done_at`) VALUES (?, ?, ?, ?, NOW()) ON DUPLICATE KEY UPDATE `variant` = VALUES(`variant`), `result` = VALUES(`result`), `done_at` = VALUES(`done_at`)",
+            (
+                deploy_pk,
+                device.pk,
+                if report.is_success() { "success" } else { "failed" },
+                serde_json::json!({ "scripts": report.scripts, "linux": report.linux }).to_string(),
+            ),
+        )
+        .await
+        .map_err(|err| {
+            error!(
+                "Error saving status of policy deploy {:?} for device {:?}: {err}",
+                report.deploy_id, device.id
+            );
+            internal_error()
+        })?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use mx_agent_api::{ReleaseManifest, SignedManifest, UpdateRequest};
+use mysql_async::prelude::*;
+use tracing::{error, info};
+
+use super::{internal_error, Device};
+use crate::Context;
+
+/// Get the latest release on the device's channel.
+///
+/// The agent verifies the signature itself so we don't need to.
+pub async fn handler(
+    State(state): State<Arc<Context>>,
+    Extension(device): Extension<Device>,
+    Json(req): Json<UpdateRequest>,
+) -> Result<Json<Option<SignedManifest>>, Response> {
+    let mut conn = state.db.get_conn().await.map_err(|err| {
+        error!("Error getting DB connection: {err}");
+        internal_error()
+    })?;
+
+    let release: Option<(String, String)> = conn
+        .exec_first(
+            "SELECT `manifest`, `signature` FROM `agent_releases` WHERE `channel` = ?",
+            (&req.channel,),
+        )
+        .await
+        .map_err(|err| {
+            error!(
+                "Error getting {:?} release for device {:?}: {err}",
+                req.channel, device.id
+            );
+            internal_error()
+        })?;
+
+    Ok(Json(release.map(|(manifest, signature)| SignedManifest {
+        manifest,
+        signature,
+    })))
+}
+
+/// Publish a signed release to a channel. This is called by the release pipeline using the internal secret.
+pub async fn publish(
+    State(state): State<Arc<Context>>,
+    Path(channel): Path<String>,
+    Json(release): Json<SignedManifest>,
+) -> Result<StatusCode, Response> {
+    let manifest = serde_json::from_str::<ReleaseManifest>(&release.manifest).map_err(|err| {
+        (StatusCode::BAD_REQUEST, format!("Invalid manifest: {err}")).into_response()
+    })?;
+    if manifest.channel != channel {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Manifest is for a different channel",
+        )
+            .into_response());
+    }
+
+    let mut conn = state.db.get_conn().await.map_err(|err| {
+        error!("Error getting DB connection: {err}");
+        internal_error()
+    })?;
+
+    conn.exec_drop(
+        "INSERT INTO `agent_releases` (`channel`, `manifest`, `signature`) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE `manifest` = VALUES(`manifest`), `signature` = VALUES(`signature`), `updated_at` = NOW()",
+        (&channel, &release.manifest, &release.signature),
+    )
+    .await
+    .map_err(|err| {
+        error!("Error publishing {channel:?} release: {err}");
+        internal_error()
+    })?;
+
+    info!("Published mattraxd {} to {channel:?}", manifest.version);
+    Ok(StatusCode::NO_CONTENT)
+}
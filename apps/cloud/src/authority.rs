@@ -0,0 +1,203 @@
+//! The device authority is the certificate authority used to issue device identity certificates.
+//!
+//! This shares the `device_authority` table with `apps/api/src/authority` so certificates issued by either service are trusted by both.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use mysql_async::prelude::*;
+use openssl::{
+    asn1::Asn1Time,
+    bn::{BigNum, MsbOption},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    rsa::Rsa,
+    x509::{
+        extension::{BasicConstraints, ExtendedKeyUsage, KeyUsage},
+        X509Name, X509NameBuilder, X509Req, X509,
+    },
+};
+use tokio::sync::RwLock;
+use tracing::info;
+
+// We cache between requests to avoid hitting the DB on every authenticated request
+const CACHE_VALIDITY: Duration = Duration::from_secs(15 * 60);
+
+const AUTHORITY_VALIDITY_DAYS: u32 = 365;
+const DEVICE_CERT_VALIDITY_DAYS: u32 = 365;
+
+pub struct Authority {
+    pub cert: X509,
+    pub key: PKey<Private>,
+}
+
+#[derive(Default)]
+pub struct AuthorityCache {
+    active: RwLock<Option<(Arc<Authority>, Instant)>>,
+    truststore: RwLock<Option<(Arc<Vec<X509>>, Instant)>>,
+}
+
+impl AuthorityCache {
+    /// Get the authority used for issuing new device certificates. If one doesn't exist it will be issued.
+    pub async fn active(&self, db: &mx_db::Db) -> Result<Arc<Authority>, String> {
+        if let Some((authority, cached_at)) = &*self.active.read().await {
+            if cached_at.elapsed() < CACHE_VALIDITY {
+                return Ok(authority.clone());
+            }
+        }
+
+        let mut conn = db
+            .get_conn()
+            .await
+            .map_err(|err| format!("error getting DB connection: {err}"))?;
+
+        let result: Option<(String, String)> = conn
+            .query_first(
+                "SELECT `public`, `private` FROM `device_authority` WHERE `expires_at` > NOW() ORDER BY `created_at` DESC LIMIT 1",
+            )
+            .await
+            .map_err(|err| format!("error querying device authority: {err}"))?;
+
+        let authority = match result {
+            Some((public, private)) => Authority {
+                cert: X509::from_pem(public.as_bytes())
+                    .map_err(|err| format!("error decoding authority certificate: {err}"))?,
+                key: PKey::private_key_from_pem(private.as_bytes())
+                    .map_err(|err| format!("error decoding authority key: {err}"))?,
+            },
+            None => {
+                let authority = Authority::issue()
+                    .map_err(|err| format!("error issuing device authority: {err}"))?;
+
+                conn.exec_drop(
+                    "INSERT INTO `device_authority` (`public`, `private`, `created_at`, `expires_at`) VALUES (?, ?, NOW(), NOW() + INTERVAL ? DAY)",
+                    (
+                        String::from_utf8_lossy(&authority.cert.to_pem().map_err(|err| err.to_string())?).to_string(),
+                        String::from_utf8_lossy(&authority.key.private_key_to_pem_pkcs8().map_err(|err| err.to_string())?).to_string(),
+                        AUTHORITY_VALIDITY_DAYS,
+                    ),
+                )
+                .await
+                .map_err(|err| format!("error saving device authority: {err}"))?;
+
+                // The truststore must include the new authority
+                *self.truststore.write().await = None;
+
+                info!("Successfully issued a new device authority certificate");
+                authority
+            }
+        };
+
+        let authority = Arc::new(authority);
+        *self.active.write().await = Some((authority.clone(), Instant::now()));
+        Ok(authority)
+    }
+
+    /// Get all of the certificates for authorities which are still valid.
+    pub async fn truststore(&self, db: &mx_db::Db) -> Result<Arc<Vec<X509>>, String> {
+        if let Some((certs, cached_at)) = &*self.truststore.read().await {
+            if cached_at.elapsed() < CACHE_VALIDITY {
+                return Ok(certs.clone());
+            }
+        }
+
+        let mut conn = db
+            .get_conn()
+            .await
+            .map_err(|err| format!("error getting DB connection: {err}"))?;
+
+        let certs = conn
+            .query::<String, _>(
+                "SELECT `public` FROM `device_authority` WHERE `expires_at` > NOW()",
+            )
+            .await
+            .map_err(|err| format!("error querying device authorities: {err}"))?
+            .into_iter()
+            .map(|public| X509::from_pem(public.as_bytes()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("error decoding authority certificate: {err}"))?;
+
+        let certs = Arc::new(certs);
+        *self.truststore.write().await = Some((certs.clone(), Instant::now()));
+        Ok(certs)
+    }
+}
+
+impl Authority {
+    pub(crate) fn issue() -> Result<Self, openssl::error::ErrorStack> {
+        info!("Issuing a new device authority certificate");
+
+        let key = PKey::from_rsa(Rsa::generate(4096)?)?;
+
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_nid(Nid::COMMONNAME, "Mattrax Device Authority")?;
+        name.append_entry_by_nid(Nid::ORGANIZATIONNAME, "Mattrax Inc.")?;
+        let name = name.build();
+
+        let mut cert = X509::builder()?;
+        cert.set_version(2)?;
+        cert.set_serial_number(&*serial_number()?)?;
+        cert.set_subject_name(&name)?;
+        cert.set_issuer_name(&name)?;
+        cert.set_pubkey(&key)?;
+        cert.set_not_before(&*Asn1Time::days_from_now(0)?)?;
+        cert.set_not_after(&*Asn1Time::days_from_now(AUTHORITY_VALIDITY_DAYS)?)?;
+        cert.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+        cert.append_extension(
+            KeyUsage::new()
+                .critical()
+                .key_cert_sign()
+                .crl_sign()
+                .build()?,
+        )?;
+        cert.sign(&key, MessageDigest::sha256())?;
+
+        Ok(Self {
+            cert: cert.build(),
+            key,
+        })
+    }
+
+    /// Issue a client certificate for a device.
+    ///
+    /// The subject from the CSR is ignored and replaced with `common_name` so a device can't choose it's own identity.
+    pub fn sign_device_csr(
+        &self,
+        csr: &X509Req,
+        common_name: &str,
+    ) -> Result<X509, openssl::error::ErrorStack> {
+        let mut name = X509Name::builder()?;
+        name.append_entry_by_nid(Nid::COMMONNAME, common_name)?;
+        let name = name.build();
+
+        let mut cert = X509::builder()?;
+        cert.set_version(2)?;
+        cert.set_serial_number(&*serial_number()?)?;
+        cert.set_subject_name(&name)?;
+        cert.set_issuer_name(self.cert.subject_name())?;
+        cert.set_pubkey(&*csr.public_key()?)?;
+        cert.set_not_before(&*Asn1Time::days_from_now(0)?)?;
+        cert.set_not_after(&*Asn1Time::days_from_now(DEVICE_CERT_VALIDITY_DAYS)?)?;
+        cert.append_extension(BasicConstraints::new().critical().build()?)?;
+        cert.append_extension(
+            KeyUsage::new()
+                .critical()
+                .digital_signature()
+                .key_encipherment()
+                .build()?,
+        )?;
+        cert.append_extension(ExtendedKeyUsage::new().client_auth().build()?)?;
+        cert.sign(&self.key, MessageDigest::sha256())?;
+
+        Ok(cert.build())
+    }
+}
+
+fn serial_number() -> Result<openssl::asn1::Asn1Integer, openssl::error::ErrorStack> {
+    let mut serial = BigNum::new()?;
+    serial.rand(127, MsbOption::MAYBE_ZERO, false)?;
+    serial.to_asn1_integer()
+}
@@ -1,4 +1,7 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::{Arc, LazyLock},
+    time::{Duration, Instant},
+};
 
 use axum::{
     extract::{MatchedPath, Request},
@@ -8,14 +11,25 @@ use axum::{
     routing::get,
     Router,
 };
+use mx_utils::telemetry::{self, Histogram, KeyValue, Telemetry, TelemetryGuard};
+use tokio::sync::broadcast;
 use tower_http::trace::TraceLayer;
 use tracing::{info_span, Span};
 
+mod agent;
+mod authority;
 mod sql;
 
 pub struct Context {
     pub internal_secret: String,
     pub db: mx_db::Db,
+    /// Shared with the mTLS proxy so the agent API only trusts client certificates forwarded by it.
+    /// When unset every request to the agent API (except enrollment) is rejected.
+    proxy_secret: Option<String>,
+    /// Notified when rows in `device_actions` are written through the SQL adapter, so actions sockets can deliver them immediately.
+    action_created: broadcast::Sender<()>,
+    authority: authority::AuthorityCache,
+    telemetry: Option<TelemetryGuard>,
 }
 
 impl Context {
@@ -27,13 +41,29 @@ impl Context {
             db: mx_db::Db::new(
                 &std::env::var("DATABASE_URL").map_err(|_| "'DATABASE_URL' must be set")?,
             ),
+            proxy_secret: std::env::var("MTLS_PROXY_SECRET")
+                .ok()
+                .filter(|secret| !secret.is_empty()),
+            action_created: broadcast::channel(16).0,
+            authority: Default::default(),
+            telemetry: None,
         })
     }
 
     /// Mount the Mattrax Cloud API onto an Axum router
-    pub fn mount(self) -> Router {
+    pub fn mount(mut self) -> Router {
+        let telemetry = Telemetry::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+            .git_hash(env!("GIT_HASH"))
+            .init();
+        match telemetry {
+            Ok(guard) => self.telemetry = Some(guard),
+            Err(err) => {
+                eprintln!("{err}");
+                // Still log to stdout without the exporters
+                tracing_subscriber::fmt().try_init().ok();
+            }
+        }
         let this = Arc::new(self);
-        tracing_subscriber::fmt().init();
         std::panic::set_hook(Box::new(move |panic| tracing::error!("{panic}")));
 
         Router::new()
@@ -51,6 +81,7 @@ impl Context {
                     )
                 }),
             )
+            .nest("/agent", agent::mount(this.clone()))
             .nest(
                 "/psdb.v1alpha1.Database",
                 sql::mount().route_layer(middleware::from_fn_with_state(this.clone(), sql::auth)),
@@ -64,12 +95,15 @@ impl Context {
                             .get::<MatchedPath>()
                             .map(MatchedPath::as_str);
 
-                        info_span!(
+                        let span = info_span!(
                             "http_request",
                             method = ?request.method(),
                             matched_path,
                             some_other_field = tracing::field::Empty,
-                        )
+                        );
+                        // Continue the trace started by the agent
+                        telemetry::set_parent(&span, request.headers());
+                        span
                     })
                     .on_response(|resp: &Response, latency: Duration, _span: &Span| {
                         #[cfg(debug_assertions)]
@@ -81,10 +115,36 @@ impl Context {
     }
 }
 
+// Created on first use, which is after `Context::mount` has installed the meter provider
+static REQUEST_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    telemetry::meter()
+        .f64_histogram("http.server.request.duration")
+        .with_unit("s")
+        .build()
+});
+
 async fn headers(request: Request, next: Next) -> Response {
     let (method, uri) = (request.method().clone(), request.uri().clone());
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    let start = Instant::now();
     let mut response = next.run(request).await;
 
+    REQUEST_DURATION.record(
+        start.elapsed().as_secs_f64(),
+        &[
+            KeyValue::new("http.request.method", method.to_string()),
+            KeyValue::new("http.route", route),
+            KeyValue::new(
+                "http.response.status_code",
+                response.status().as_u16() as i64,
+            ),
+        ],
+    );
+
     #[cfg(debug_assertions)]
     tracing::debug!("{method} {uri} - {:?}", response.status());
 
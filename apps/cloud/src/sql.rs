@@ -6,7 +6,10 @@
 //! This implementation has also been stripped to just the stuff required by Mattrax. Eg. no `/CreateSession` endpoint because it's effectively unused in Planetscale's SDK.
 //!
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
 
 use axum::{
     extract::{Request, State},
@@ -27,16 +30,19 @@ use serde_json::json;
 use tokio::sync::RwLock;
 use tracing::{debug, error};
 
-use crate::Context;
+use crate::{agent::modifies_actions, Context};
 
 pub struct ConnectionPool {
     /// Active database transactions
     sessions: RwLock<HashMap<String, Transaction<'static>>>,
+    /// Transactions which have written to `device_actions`, so the actions sockets are notified once they commit
+    modified_actions: Mutex<HashSet<String>>,
 }
 
 pub fn mount() -> Router<Arc<Context>> {
     let pool = Arc::new(ConnectionPool {
         sessions: Default::default(),
+        modified_actions: Default::default(),
     });
 
     Router::new()
@@ -89,6 +95,10 @@ pub fn mount() -> Router<Arc<Context>> {
                                 })?;
                                 debug!("COMMIT transaction {:?}", session.id);
 
+                                if pool.modified_actions.lock().unwrap().remove(&session.id) {
+                                    state.action_created.send(()).ok();
+                                }
+
                                 return Ok(Json(json!({
                                     "session": session,
                                     "result": json!({}),
@@ -105,6 +115,7 @@ pub fn mount() -> Router<Arc<Context>> {
                                     error(format!("error rolling back transaction {:?}: {err:?}", session.id))
                                 })?;
                                 debug!("ROLLBACK transaction {:?}", session.id);
+                                pool.modified_actions.lock().unwrap().remove(&session.id);
 
                                 return Ok(Json(json!({
                                     "session": session,
@@ -126,6 +137,10 @@ pub fn mount() -> Router<Arc<Context>> {
                                         error(format!("error executing query: {err:?}"))
                                     })?;
 
+                                if modifies_actions(&data.query) {
+                                    pool.modified_actions.lock().unwrap().insert(session.id.clone());
+                                }
+
                                 (result.columns(), result.collect_and_drop::<Row>().await, tx.affected_rows().to_string(), tx.last_insert_id().map(|v| v.to_string()))
                             }
                         } else {
@@ -143,6 +158,10 @@ pub fn mount() -> Router<Arc<Context>> {
                                     error(format!("error executing query: {err:?}"))
                                 })?;
 
+                            if modifies_actions(&data.query) {
+                                state.action_created.send(()).ok();
+                            }
+
                             (result.columns(), result.collect_and_drop::<Row>().await, conn.affected_rows().to_string(), conn.last_insert_id().map(|v| v.to_string()))
                         };
 
@@ -1,9 +1,13 @@
-use std::{collections::BTreeMap, fs, path::Path};
+use std::{collections::BTreeMap, path::Path};
 
-use apple_pfm::Preference;
+use apple_pfm::{Preference, Require};
+use mx_catalog::{Document, Platform};
 use serde::Serialize;
+use serde_json::{json, Map, Value};
 use specta::{NamedType, Type};
 
+use crate::{json_schema, Generated};
+
 #[derive(Serialize, Debug, Type)]
 #[serde(rename_all = "camelCase")]
 struct AppleProfilePayload {
@@ -53,13 +57,11 @@ impl PropertyType {
             Preference::Date(_) => PropertyType::Date,
             Preference::Data(_) => PropertyType::Data,
             Preference::Dictionary { pfm_subkeys, .. } => {
+                // Keys without a name are reported by `unnamed_keys`
                 let entries = pfm_subkeys
                     .into_iter()
-                    .map(|preference| {
-                        (
-                            preference.pfm_name.clone().unwrap(),
-                            Property::parse(preference),
-                        )
+                    .filter_map(|preference| {
+                        Some((preference.pfm_name.clone()?, Property::parse(preference)))
                     })
                     .collect::<BTreeMap<_, _>>();
 
@@ -69,7 +71,7 @@ impl PropertyType {
                     PropertyType::Dictionary(entries)
                 }
             }
-            Preference::Integer(_) => PropertyType::Integer,
+            Preference::Integer { .. } => PropertyType::Integer,
             Preference::Real(_) => PropertyType::Real,
             Preference::Float(_) => PropertyType::Float,
             Preference::String {
@@ -115,6 +117,252 @@ impl Property {
     }
 }
 
+/// The JSON Schema for a payload of a domain in a policy.
+///
+/// A domain can have a manifest for each platform, so the schema accepts the keys of every manifest. A key is only
+/// required if every manifest requires it, and a key defined differently by each manifest accepts any definition.
+fn json_schema(manifests: &[&apple_pfm::Manifest]) -> Value {
+    let mut definitions = BTreeMap::<String, Vec<Value>>::new();
+    let mut required = None::<Vec<String>>;
+    for manifest in manifests {
+        let subkeys = manifest.pfm_subkeys.iter().filter(|subkey| {
+            !COMMON_PAYLOAD_KEYS.contains(&subkey.pfm_name.as_deref().unwrap_or(""))
+        });
+        let (properties, manifest_required) = dictionary_properties(subkeys);
+        for (name, schema) in properties {
+            let definitions = definitions.entry(name).or_default();
+            if !definitions.contains(&schema) {
+                definitions.push(schema);
+            }
+        }
+        match &mut required {
+            Some(required) => required.retain(|name| manifest_required.contains(name)),
+            None => required = Some(manifest_required),
+        }
+    }
+
+    let properties = definitions
+        .into_iter()
+        .map(|(name, mut definitions)| {
+            let schema = match definitions.len() {
+                1 => definitions.remove(0),
+                _ => json!({ "anyOf": definitions }),
+            };
+            (name, schema)
+        })
+        .collect();
+    let (title, description) = manifests.first().map_or(("", ""), |manifest| {
+        (&manifest.pfm_title, &manifest.pfm_description)
+    });
+    json_schema::document(
+        title,
+        Some(description),
+        json_schema::object(properties, required.unwrap_or_default()),
+    )
+}
+
+fn dictionary_schema<'a>(subkeys: impl Iterator<Item = &'a Preference>) -> Value {
+    let (properties, required) = dictionary_properties(subkeys);
+    json_schema::object(properties, required)
+}
+
+/// The schemas of the keys of a dictionary, and the names of the required keys.
+fn dictionary_properties<'a>(
+    subkeys: impl Iterator<Item = &'a Preference>,
+) -> (Map<String, Value>, Vec<String>) {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for subkey in subkeys {
+        let Some(name) = &subkey.pfm_name else {
+            continue;
+        };
+        if matches!(
+            subkey.pfm_require,
+            Some(Require::Always | Require::AlwaysNested)
+        ) {
+            required.push(name.clone());
+        }
+        properties.insert(name.clone(), preference_schema(subkey));
+    }
+    (properties, required)
+}
+
+fn preference_schema(preference: &Preference) -> Value {
+    let range = || {
+        // Some manifests write numbers as strings, which aren't valid bounds
+        let bound = |value: &Option<plist::Value>| {
+            value.as_ref().and_then(to_json).filter(Value::is_number)
+        };
+        json!({
+            "minimum": bound(&preference.pfm_range_min),
+            "maximum": bound(&preference.pfm_range_max),
+        })
+    };
+    let mut schema = match preference {
+        Preference::Array { pfm_subkeys, .. } => match pfm_subkeys.first() {
+            Some(item) => json!({ "type": "array", "items": preference_schema(item) }),
+            None => json!({ "type": "array" }),
+        },
+        Preference::Boolean(_) => json!({ "type": "boolean" }),
+        Preference::Date(_) => json!({ "type": "string", "format": "date-time" }),
+        Preference::Data(_) => json!({ "type": "string", "contentEncoding": "base64" }),
+        Preference::Dictionary { pfm_subkeys, .. } if pfm_subkeys.is_empty() => {
+            json!({ "type": "object" })
+        }
+        Preference::Dictionary { pfm_subkeys, .. } => dictionary_schema(pfm_subkeys.iter()),
+        Preference::Integer {
+            pfm_range_list,
+            pfm_range_list_titles,
+            ..
+        } => {
+            let mut schema = json!({ "type": "integer" });
+            json_schema::merge(&mut schema, range());
+            one_of(
+                schema,
+                pfm_range_list.iter().filter_map(to_json),
+                pfm_range_list_titles,
+            )
+        }
+        Preference::Real(_) | Preference::Float(_) => {
+            let mut schema = json!({ "type": "number" });
+            json_schema::merge(&mut schema, range());
+            schema
+        }
+        Preference::String {
+            pfm_range_list,
+            pfm_range_list_titles,
+            ..
+        } => one_of(
+            json!({ "type": "string" }),
+            pfm_range_list.iter().map(|value| value.as_str().into()),
+            pfm_range_list_titles,
+        ),
+        Preference::Url(_) => json!({ "type": "string", "format": "uri" }),
+        Preference::Alias(_) | Preference::UnionPolicy(_) => json!({}),
+    };
+
+    if let Some(schema) = schema.as_object_mut() {
+        schema.retain(|_, value| !value.is_null());
+    }
+    json_schema::set(&mut schema, "title", preference.pfm_title.as_deref());
+    json_schema::set(
+        &mut schema,
+        "description",
+        preference.pfm_description.as_deref(),
+    );
+    json_schema::set(
+        &mut schema,
+        "default",
+        preference.pfm_default.as_ref().and_then(to_json),
+    );
+    schema
+}
+
+/// Restrict a schema to the values of a range list, titled by the range list titles.
+fn one_of(mut schema: Value, values: impl Iterator<Item = Value>, titles: &[String]) -> Value {
+    let variants = values
+        .enumerate()
+        .map(|(i, value)| {
+            let mut variant = json!({ "const": value });
+            json_schema::set(&mut variant, "title", titles.get(i).map(String::as_str));
+            variant
+        })
+        .collect::<Vec<_>>();
+    if !variants.is_empty() {
+        schema["oneOf"] = variants.into();
+    }
+    schema
+}
+
+/// Convert a plist value to JSON. Dates and data have no equivalent so are skipped.
+fn to_json(value: &plist::Value) -> Option<Value> {
+    Some(match value {
+        plist::Value::Boolean(value) => (*value).into(),
+        plist::Value::Integer(value) => match value.as_signed() {
+            Some(value) => value.into(),
+            None => value.as_unsigned()?.into(),
+        },
+        plist::Value::Real(value) => (*value).into(),
+        plist::Value::String(value) => value.as_str().into(),
+        plist::Value::Array(values) => values.iter().filter_map(to_json).collect(),
+        plist::Value::Dictionary(values) => values
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), to_json(value)?)))
+            .collect(),
+        _ => return None,
+    })
+}
+
+/// The payloads and their keys, to be found by searching.
+pub fn search_documents(manifests: &[apple_pfm::Manifest]) -> Vec<Document> {
+    fn visit(
+        preferences: &[Preference],
+        path: &str,
+        keywords: &[String],
+        documents: &mut Vec<Document>,
+    ) {
+        for preference in preferences {
+            let Some(name) = &preference.pfm_name else {
+                continue;
+            };
+            if COMMON_PAYLOAD_KEYS.contains(&name.as_str()) {
+                continue;
+            }
+
+            let path = format!("{path}/{name}");
+            let mut document_keywords = keywords.to_vec();
+            if let Preference::String {
+                pfm_range_list_titles,
+                ..
+            }
+            | Preference::Integer {
+                pfm_range_list_titles,
+                ..
+            } = preference
+            {
+                document_keywords.extend(pfm_range_list_titles.iter().cloned());
+            }
+            documents.push(Document {
+                platform: Platform::Apple,
+                path: path.clone(),
+                title: preference.pfm_title.clone().unwrap_or_else(|| name.clone()),
+                description: preference.pfm_description.clone(),
+                keywords: document_keywords,
+            });
+
+            if let Preference::Array { pfm_subkeys, .. }
+            | Preference::Dictionary { pfm_subkeys, .. } = preference
+            {
+                visit(pfm_subkeys, &path, keywords, documents);
+            }
+        }
+    }
+
+    let mut documents = Vec::new();
+    for manifest in manifests {
+        if manifest.pfm_domain == "Configuration" {
+            continue;
+        }
+
+        documents.push(Document {
+            platform: Platform::Apple,
+            path: manifest.pfm_domain.clone(),
+            title: manifest.pfm_title.clone(),
+            description: Some(manifest.pfm_description.clone()),
+            keywords: Vec::new(),
+        });
+        // Keys are often only meaningful alongside the name of their payload
+        let keywords = [manifest.pfm_title.clone()];
+        visit(
+            &manifest.pfm_subkeys,
+            &manifest.pfm_domain,
+            &keywords,
+            &mut documents,
+        );
+    }
+    documents
+}
+
 type AppleProfilePayloadGroup = BTreeMap<String, AppleProfilePayload>;
 
 #[derive(Type, Default, Serialize)]
@@ -130,42 +378,91 @@ pub static COMMON_PAYLOAD_KEYS: [&str; 7] = [
     "PayloadOrganization",
 ];
 
-pub fn generate_bindings() {
-    let plist_files = glob::glob(&format!(
-        "{}/manifests/**/*.plist",
-        env!("CARGO_MANIFEST_DIR")
-    ))
-    .unwrap();
+/// Parse every profile manifest in a directory, returning a description of each problem found.
+pub fn load_dir(dir: &Path) -> (Vec<apple_pfm::Manifest>, Vec<String>) {
+    let pattern = glob::Pattern::escape(&dir.to_string_lossy()) + "/**/*.plist";
+    let plist_files = match glob::glob(&pattern) {
+        Ok(plist_files) => plist_files,
+        Err(err) => return (Vec::new(), vec![format!("{}: {err}", dir.display())]),
+    };
 
-    // let mut json = vec![];
+    let mut manifests = Vec::new();
+    let mut errors = Vec::new();
+    for file in plist_files {
+        match file.map_err(|err| err.to_string()).and_then(|path| {
+            apple_pfm::Manifest::from_file(&path)
+                .map_err(|err| format!("{}: {err}", path.display()))
+        }) {
+            Ok(manifest) => manifests.push(manifest),
+            Err(err) => errors.push(err),
+        }
+    }
+    (manifests, errors)
+}
 
-    // for file in plist_files {
-    //     json.push(
-    //         serde_json::to_value(
-    //             plist::from_file::<_, plist::Value>(file.unwrap().as_path()).unwrap(),
-    //         )
-    //         .unwrap(),
-    //     )
-    // }
+/// Report the keys which have no name, and so are left out of the generated files.
+///
+/// The items of an array are unnamed, so `named` is false for them.
+fn unnamed_keys(preferences: &[Preference], path: &str, named: bool, errors: &mut Vec<String>) {
+    for preference in preferences {
+        let name = preference.pfm_name.as_deref();
+        if named && name.is_none() {
+            errors.push(format!(
+                "{path}: a {} key has no pfm_name",
+                preference.pfm_type()
+            ));
+        }
 
-    // fs::write(
-    //     Path::new(env!("CARGO_MANIFEST_DIR")).join("json/apple-manifests.json"),
-    //     serde_json::to_string_pretty(&serde_json::to_value(json).unwrap()).unwrap(),
-    // )
-    // .unwrap();
+        let path = format!("{path}/{}", name.unwrap_or("*"));
+        match preference {
+            Preference::Array { pfm_subkeys, .. } => {
+                unnamed_keys(pfm_subkeys, &path, false, errors)
+            }
+            Preference::Dictionary { pfm_subkeys, .. } => {
+                unnamed_keys(pfm_subkeys, &path, true, errors)
+            }
+            _ => {}
+        }
+    }
+}
 
-    let mut payloads = AppleProfilePayloadCollection::default();
+pub fn generate(input: &Path) -> Generated {
+    let (manifests, mut errors) = load_dir(input);
 
-    for file in plist_files {
-        let manifest = apple_pfm::Manifest::from_file(file.unwrap().as_path());
+    let mut domains = BTreeMap::<&str, Vec<_>>::new();
+    for manifest in &manifests {
+        unnamed_keys(
+            &manifest.pfm_subkeys,
+            &manifest.pfm_domain,
+            true,
+            &mut errors,
+        );
+        domains
+            .entry(&manifest.pfm_domain)
+            .or_default()
+            .push(manifest);
+    }
+    // The top level of a profile rather than a payload, which is also left out of the payloads
+    domains.remove("Configuration");
+    let schemas = domains
+        .iter()
+        .map(|(domain, manifests)| {
+            (
+                json_schema::file_name(domain),
+                serde_json::to_string_pretty(&json_schema(manifests)).unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
 
+    let mut payloads = AppleProfilePayloadCollection::default();
+    for manifest in manifests {
         let properties = manifest
             .pfm_subkeys
             .into_iter()
             .filter(|subkey| {
                 !COMMON_PAYLOAD_KEYS.contains(&subkey.pfm_name.as_deref().unwrap_or(""))
             })
-            .map(|subkey| (subkey.pfm_name.clone().unwrap(), Property::parse(subkey)))
+            .filter_map(|subkey| Some((subkey.pfm_name.clone()?, Property::parse(subkey))))
             .collect();
 
         let profile = AppleProfilePayload {
@@ -184,13 +481,6 @@ pub fn generate_bindings() {
     let mut types = String::new();
     let type_map = &mut Default::default();
 
-    fs::write(
-        Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("../../packages/configuration-schemas/src/apple/payloads.json"),
-        serde_json::to_string_pretty(&payloads).unwrap(),
-    )
-    .unwrap();
-
     specta_typescript::export_named_datatype(
         &specta_typescript::Typescript::default(),
         &AppleProfilePayloadCollection::definition_named_data_type(type_map),
@@ -205,10 +495,180 @@ pub fn generate_bindings() {
         types.push('\n');
     });
 
-    fs::write(
-        Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("../../packages/configuration-schemas/src/apple/payloads.ts"),
-        types,
-    )
-    .unwrap();
+    let mut files = vec![
+        (
+            "payloads.json".into(),
+            serde_json::to_string_pretty(&payloads).unwrap(),
+        ),
+        ("payloads.ts".into(), types),
+    ];
+    files.extend(schemas);
+
+    Generated { files, errors }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>pfm_description</key>
+    <string>An example payload</string>
+    <key>pfm_domain</key>
+    <string>com.example.test</string>
+    <key>pfm_format_version</key>
+    <integer>1</integer>
+    <key>pfm_last_modified</key>
+    <date>2024-01-01T00:00:00Z</date>
+    <key>pfm_title</key>
+    <string>Test</string>
+    <key>pfm_version</key>
+    <integer>1</integer>
+    <key>pfm_subkeys</key>
+    <array>
+        <dict>
+            <key>pfm_name</key>
+            <string>PayloadType</string>
+            <key>pfm_type</key>
+            <string>string</string>
+        </dict>
+        <dict>
+            <key>pfm_name</key>
+            <string>Volume</string>
+            <key>pfm_type</key>
+            <string>integer</string>
+            <key>pfm_range_min</key>
+            <integer>0</integer>
+            <key>pfm_range_max</key>
+            <integer>10</integer>
+            <key>pfm_require</key>
+            <string>always</string>
+        </dict>
+        <dict>
+            <key>pfm_name</key>
+            <string>Mode</string>
+            <key>pfm_type</key>
+            <string>string</string>
+            <key>pfm_range_list</key>
+            <array>
+                <string>Off</string>
+                <string>On</string>
+            </array>
+            <key>pfm_range_list_titles</key>
+            <array>
+                <string>Disabled</string>
+                <string>Enabled</string>
+            </array>
+        </dict>
+        <dict>
+            <key>pfm_name</key>
+            <string>Servers</string>
+            <key>pfm_type</key>
+            <string>array</string>
+            <key>pfm_subkeys</key>
+            <array>
+                <dict>
+                    <key>pfm_type</key>
+                    <string>dictionary</string>
+                    <key>pfm_subkeys</key>
+                    <array>
+                        <dict>
+                            <key>pfm_name</key>
+                            <string>Host</string>
+                            <key>pfm_type</key>
+                            <string>string</string>
+                        </dict>
+                        <dict>
+                            <key>pfm_type</key>
+                            <string>integer</string>
+                        </dict>
+                    </array>
+                </dict>
+            </array>
+        </dict>
+    </array>
+</dict>
+</plist>
+"#;
+
+    fn manifest() -> apple_pfm::Manifest {
+        plist::from_bytes(MANIFEST.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = json_schema(&[&manifest()]);
+        assert_eq!(schema["title"], "Test");
+        assert_eq!(schema["required"], json!(["Volume"]));
+        assert_eq!(schema["properties"].get("PayloadType"), None);
+        assert_eq!(
+            schema["properties"]["Volume"],
+            json!({ "type": "integer", "minimum": 0, "maximum": 10 })
+        );
+        assert_eq!(
+            schema["properties"]["Mode"]["oneOf"],
+            json!([
+                { "const": "Off", "title": "Disabled" },
+                { "const": "On", "title": "Enabled" },
+            ])
+        );
+
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        assert!(validator.is_valid(&json!({
+            "Volume": 3,
+            "Mode": "On",
+            "Servers": [{ "Host": "example.com" }],
+        })));
+        assert!(!validator.is_valid(&json!({ "Mode": "On" })));
+        assert!(!validator.is_valid(&json!({ "Volume": 11 })));
+        assert!(!validator.is_valid(&json!({ "Volume": 1, "Mode": "Auto" })));
+        assert!(!validator.is_valid(&json!({ "Volume": 1, "Servers": [{ "Host": 1 }] })));
+        assert!(!validator.is_valid(&json!({ "Volume": 1, "Other": true })));
+    }
+
+    #[test]
+    fn test_merged_json_schema() {
+        let ios = manifest();
+        // Another platform's manifest which doesn't require `Volume`, allows more and has a different key
+        let macos = MANIFEST
+            .replace("<string>always</string>", "<string>push</string>")
+            .replace("<integer>10</integer>", "<integer>20</integer>")
+            .replace("<string>Mode</string>", "<string>Theme</string>");
+        let macos = plist::from_bytes(macos.as_bytes()).unwrap();
+
+        let schema = json_schema(&[&ios, &macos]);
+        assert_eq!(schema.get("required"), None);
+        assert_eq!(
+            schema["properties"]["Volume"]["anyOf"],
+            json!([
+                { "type": "integer", "minimum": 0, "maximum": 10 },
+                { "type": "integer", "minimum": 0, "maximum": 20 },
+            ])
+        );
+
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        assert!(validator.is_valid(&json!({ "Mode": "On", "Theme": "Off" })));
+        assert!(validator.is_valid(&json!({ "Volume": 15 })));
+        assert!(!validator.is_valid(&json!({ "Volume": 21 })));
+        assert!(!validator.is_valid(&json!({ "Other": true })));
+    }
+
+    #[test]
+    fn test_unnamed_keys() {
+        let manifest = manifest();
+        let mut errors = Vec::new();
+        unnamed_keys(
+            &manifest.pfm_subkeys,
+            &manifest.pfm_domain,
+            true,
+            &mut errors,
+        );
+        assert_eq!(
+            errors,
+            ["com.example.test/Servers/*: a integer key has no pfm_name"]
+        );
+    }
 }
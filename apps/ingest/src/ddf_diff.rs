@@ -0,0 +1,442 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use ms_ddf::{AllowedValues, Applicability, DdfIndex, ResolvedNode};
+use serde::Serialize;
+
+use crate::windows_ddf;
+
+/// The differences between two releases of the DDF files.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DdfDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    format_changes: Vec<Change>,
+    allowed_value_changes: Vec<Change>,
+    default_changes: Vec<Change>,
+    applicability_changes: Vec<ApplicabilityChange>,
+    deprecated: Vec<Deprecation>,
+}
+
+#[derive(Serialize, Debug)]
+struct Change {
+    path: String,
+    old: String,
+    new: String,
+}
+
+#[derive(Serialize, Debug)]
+struct ApplicabilityChange {
+    path: String,
+    /// The constraints which weren't in the old release.
+    added: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Deprecation {
+    path: String,
+    os_build_deprecated: Option<String>,
+}
+
+impl DdfDiff {
+    pub fn new(old: &DdfIndex, new: &DdfIndex) -> Self {
+        let old = by_path(old);
+        let new = by_path(new);
+        let mut diff = Self {
+            removed: old
+                .keys()
+                .filter(|path| !new.contains_key(*path))
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+
+        for (path, node) in &new {
+            let Some(old) = old.get(path) else {
+                diff.added.push(path.clone());
+                continue;
+            };
+
+            let (old_format, new_format) = (old.format().to_string(), node.format().to_string());
+            if old_format != new_format {
+                diff.format_changes.push(Change {
+                    path: path.clone(),
+                    old: old_format,
+                    new: new_format,
+                });
+            }
+
+            if !same_constraint(old.allowed_values(), node.allowed_values()) {
+                diff.allowed_value_changes.push(Change {
+                    path: path.clone(),
+                    old: describe_allowed_values(old.allowed_values()),
+                    new: describe_allowed_values(node.allowed_values()),
+                });
+            }
+
+            let (old_default, new_default) = (
+                &old.node.properties.default_value,
+                &node.node.properties.default_value,
+            );
+            if old_default != new_default {
+                let describe = |default: &Option<String>| match default {
+                    Some(value) => format!("{value:?}"),
+                    None => "none".into(),
+                };
+                diff.default_changes.push(Change {
+                    path: path.clone(),
+                    old: describe(old_default),
+                    new: describe(new_default),
+                });
+            }
+
+            let old_constraints = constraints(old.applicability);
+            let added = constraints(node.applicability)
+                .into_iter()
+                .filter(|constraint| !old_constraints.contains(constraint))
+                .collect::<Vec<_>>();
+            if !added.is_empty() {
+                diff.applicability_changes.push(ApplicabilityChange {
+                    path: path.clone(),
+                    added,
+                });
+            }
+
+            if old.node.properties.deprecated.is_none() {
+                if let Some(deprecated) = &node.node.properties.deprecated {
+                    diff.deprecated.push(Deprecation {
+                        path: path.clone(),
+                        os_build_deprecated: deprecated.os_build_deprecated.clone(),
+                    });
+                }
+            }
+        }
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.format_changes.is_empty()
+            && self.allowed_value_changes.is_empty()
+            && self.default_changes.is_empty()
+            && self.applicability_changes.is_empty()
+            && self.deprecated.is_empty()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::from("# DDF changes\n");
+        if self.is_empty() {
+            md.push_str("\nNo changes.\n");
+            return md;
+        }
+
+        let mut section = |title: &str, lines: Vec<String>| {
+            if !lines.is_empty() {
+                write!(md, "\n## {title}\n\n").unwrap();
+                for line in lines {
+                    writeln!(md, "- {line}").unwrap();
+                }
+            }
+        };
+        let changes = |changes: &[Change]| {
+            changes
+                .iter()
+                .map(|c| format!("`{}`: {} → {}", c.path, c.old, c.new))
+                .collect()
+        };
+
+        section("Added", code(&self.added));
+        section("Removed", code(&self.removed));
+        section("Format changes", changes(&self.format_changes));
+        section(
+            "Allowed value changes",
+            changes(&self.allowed_value_changes),
+        );
+        section("Default value changes", changes(&self.default_changes));
+        section(
+            "New applicability constraints",
+            self.applicability_changes
+                .iter()
+                .map(|c| format!("`{}`: {}", c.path, c.added.join(", ")))
+                .collect(),
+        );
+        section(
+            "Deprecated",
+            self.deprecated
+                .iter()
+                .map(|d| match &d.os_build_deprecated {
+                    Some(build) => format!("`{}` from {build}", d.path),
+                    None => format!("`{}`", d.path),
+                })
+                .collect(),
+        );
+        md
+    }
+}
+
+/// Compare the DDF files in two directories, printing the differences as Markdown or JSON.
+///
+/// Returns the problems found reading the files.
+pub fn run(old: &Path, new: &Path, json: bool) -> Vec<String> {
+    let (old, mut errors) = windows_ddf::load_dir(old);
+    let (new, new_errors) = windows_ddf::load_dir(new);
+    errors.extend(new_errors);
+    let diff = DdfDiff::new(&DdfIndex::new(&old), &DdfIndex::new(&new));
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff).unwrap());
+    } else {
+        print!("{}", diff.to_markdown());
+    }
+    errors
+}
+
+fn by_path<'a>(index: &DdfIndex<'a>) -> BTreeMap<String, ResolvedNode<'a>> {
+    index
+        .nodes()
+        .into_iter()
+        .map(|node| (node.path.clone(), node))
+        .collect()
+}
+
+fn code(paths: &[String]) -> Vec<String> {
+    paths.iter().map(|path| format!("`{path}`")).collect()
+}
+
+/// Whether the allowed values accept the same values, ignoring their descriptions, defaults and examples.
+fn same_constraint(old: Option<&AllowedValues>, new: Option<&AllowedValues>) -> bool {
+    match (
+        old.unwrap_or(&AllowedValues::None),
+        new.unwrap_or(&AllowedValues::None),
+    ) {
+        (AllowedValues::Enum(old), AllowedValues::Enum(new))
+        | (AllowedValues::Flag(old), AllowedValues::Flag(new)) => old
+            .iter()
+            .map(|v| &v.value)
+            .eq(new.iter().map(|v| &v.value)),
+        (AllowedValues::Range(old), AllowedValues::Range(new)) => {
+            (old.min, old.max) == (new.min, new.max)
+        }
+        (
+            AllowedValues::List { delimiter, item },
+            AllowedValues::List {
+                delimiter: new_delimiter,
+                item: new_item,
+            },
+        ) => delimiter == new_delimiter && same_constraint(Some(item), Some(new_item)),
+        (AllowedValues::Sddl(_), AllowedValues::Sddl(_))
+        | (AllowedValues::Json(_), AllowedValues::Json(_)) => true,
+        (old, new) => old == new,
+    }
+}
+
+fn describe_allowed_values(allowed_values: Option<&AllowedValues>) -> String {
+    let values = |values: &[ms_ddf::EnumValue]| {
+        let values = values.iter().map(|v| v.value.as_str());
+        values.collect::<Vec<_>>().join(", ")
+    };
+    match allowed_values {
+        None | Some(AllowedValues::None) => "any".into(),
+        Some(AllowedValues::Enum(v)) => format!("one of {}", values(v)),
+        Some(AllowedValues::Flag(v)) => format!("flags {}", values(v)),
+        Some(AllowedValues::Range(range)) => format!("range {range}"),
+        Some(AllowedValues::List { delimiter, item }) => format!(
+            "list of {} separated by {delimiter:?}",
+            describe_allowed_values(Some(item))
+        ),
+        Some(AllowedValues::Admx(admx)) => format!("ADMX {}/{}", admx.file, admx.name),
+        Some(AllowedValues::Regex(pattern)) => format!("matching {pattern}"),
+        Some(AllowedValues::Xsd(_)) => "XSD".into(),
+        Some(AllowedValues::Sddl(_)) => "SDDL".into(),
+        Some(AllowedValues::Json(_)) => "JSON".into(),
+    }
+}
+
+/// The requirements of an applicability, which can be compared between releases.
+fn constraints(applicability: Option<&Applicability>) -> Vec<String> {
+    let Some(applicability) = applicability else {
+        return Vec::new();
+    };
+
+    let mut constraints = Vec::new();
+    if let Some(build) = &applicability.os_build_version {
+        constraints.push(format!("OS build {build}"));
+    }
+    if let Some(version) = &applicability.csp_version {
+        constraints.push(format!("CSP version {version}"));
+    }
+    if let Some(list) = &applicability.edition_allow_list {
+        let ids = list.ids.iter().map(|id| id.to_string());
+        constraints.push(format!("editions {}", ids.collect::<Vec<_>>().join(";")));
+    }
+    if applicability.requires_azure_ad.is_some() {
+        constraints.push("requires Azure AD".into());
+    }
+    constraints
+}
+
+#[cfg(test)]
+mod test {
+    use ms_ddf::{Command, DFFormatVariant, Deprecated, EnumValue, MgmtTree, Node, Range};
+
+    use super::*;
+
+    fn tree(children: Vec<Node>) -> MgmtTree {
+        let root = children.into_iter().fold(
+            Node::new("Test", DFFormatVariant::Node).path("./Device/Vendor/MSFT"),
+            Node::child,
+        );
+        MgmtTree::new().node(root)
+    }
+
+    fn int(name: &str) -> Node {
+        Node::new(name, DFFormatVariant::Int).access([Command::Get, Command::Replace])
+    }
+
+    fn enum_values(values: &[(&str, &str)]) -> AllowedValues {
+        let values = values.iter().map(|(value, description)| EnumValue {
+            value: value.to_string(),
+            description: Some(description.to_string()),
+        });
+        AllowedValues::Enum(values.collect())
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = tree(vec![
+            int("Removed"),
+            int("Format"),
+            int("Values").allowed_values(AllowedValues::Range("[0-10]".parse().unwrap())),
+            int("Applies"),
+            int("Deprecates"),
+        ]);
+        let mut deprecates = int("Deprecates");
+        deprecates.properties.deprecated = Some(Deprecated {
+            os_build_deprecated: Some("10.0.26100".into()),
+        });
+        let new = tree(vec![
+            Node::new("Added", DFFormatVariant::String).access([Command::Get]),
+            Node::new("Format", DFFormatVariant::String).access([Command::Get, Command::Replace]),
+            int("Values").allowed_values(AllowedValues::Range("[0-20]".parse().unwrap())),
+            int("Applies").applicability(Applicability {
+                os_build_version: Some("10.0.22000".into()),
+                csp_version: None,
+                edition_allow_list: Some("0x4;0x88*;".parse().unwrap()),
+                requires_azure_ad: None,
+            }),
+            deprecates,
+        ]);
+
+        let diff = DdfDiff::new(&DdfIndex::new([&old]), &DdfIndex::new([&new]));
+        assert_eq!(
+            diff.to_markdown(),
+            "# DDF changes
+
+## Added
+
+- `./Device/Vendor/MSFT/Test/Added`
+
+## Removed
+
+- `./Device/Vendor/MSFT/Test/Removed`
+
+## Format changes
+
+- `./Device/Vendor/MSFT/Test/Format`: int → chr
+
+## Allowed value changes
+
+- `./Device/Vendor/MSFT/Test/Values`: range [0-10] → range [0-20]
+
+## New applicability constraints
+
+- `./Device/Vendor/MSFT/Test/Applies`: OS build 10.0.22000, editions 0x4;0x88*
+
+## Deprecated
+
+- `./Device/Vendor/MSFT/Test/Deprecates` from 10.0.26100
+"
+        );
+        assert_eq!(
+            serde_json::to_value(&diff).unwrap(),
+            serde_json::json!({
+                "added": ["./Device/Vendor/MSFT/Test/Added"],
+                "removed": ["./Device/Vendor/MSFT/Test/Removed"],
+                "formatChanges": [{
+                    "path": "./Device/Vendor/MSFT/Test/Format",
+                    "old": "int",
+                    "new": "chr",
+                }],
+                "allowedValueChanges": [{
+                    "path": "./Device/Vendor/MSFT/Test/Values",
+                    "old": "range [0-10]",
+                    "new": "range [0-20]",
+                }],
+                "defaultChanges": [],
+                "applicabilityChanges": [{
+                    "path": "./Device/Vendor/MSFT/Test/Applies",
+                    "added": ["OS build 10.0.22000", "editions 0x4;0x88*"],
+                }],
+                "deprecated": [{
+                    "path": "./Device/Vendor/MSFT/Test/Deprecates",
+                    "osBuildDeprecated": "10.0.26100",
+                }],
+            })
+        );
+
+        let unchanged = DdfDiff::new(&DdfIndex::new([&old]), &DdfIndex::new([&old]));
+        assert!(unchanged.is_empty());
+        assert_eq!(unchanged.to_markdown(), "# DDF changes\n\nNo changes.\n");
+    }
+
+    #[test]
+    fn test_constraint_changes() {
+        let range = |description: &str| {
+            AllowedValues::Range(Range {
+                min: 0,
+                max: 10,
+                default: None,
+                description: Some(description.into()),
+            })
+        };
+        let old = tree(vec![
+            int("Range").allowed_values(range("Seconds")),
+            int("Enum")
+                .allowed_values(enum_values(&[("0", "Off"), ("1", "On")]))
+                .default_value("0"),
+            int("List").allowed_values(AllowedValues::List {
+                delimiter: ',',
+                item: Box::new(range("Seconds")),
+            }),
+        ]);
+        let new = tree(vec![
+            int("Range").allowed_values(range("The number of seconds")),
+            int("Enum")
+                .allowed_values(enum_values(&[("0", "Disabled"), ("1", "Enabled")]))
+                .default_value("1"),
+            int("List").allowed_values(AllowedValues::List {
+                delimiter: ';',
+                item: Box::new(range("Seconds")),
+            }),
+        ]);
+
+        // Only the delimiter and the default changed what's allowed, the descriptions didn't
+        let diff = DdfDiff::new(&DdfIndex::new([&old]), &DdfIndex::new([&new]));
+        assert_eq!(
+            diff.to_markdown(),
+            "# DDF changes
+
+## Allowed value changes
+
+- `./Device/Vendor/MSFT/Test/List`: list of range [0-10] separated by ',' → list of range [0-10] separated by ';'
+
+## Default value changes
+
+- `./Device/Vendor/MSFT/Test/Enum`: \"0\" → \"1\"
+"
+        );
+    }
+}
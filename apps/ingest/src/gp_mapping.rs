@@ -0,0 +1,333 @@
+//! The mapping between Group Policies and the CSP nodes which configure them, for translating GPOs into policies.
+
+use std::{cmp::Ordering, collections::BTreeMap, fs, path::Path};
+
+use ms_ddf::{
+    admx::{self, PolicyDefinitionResources, PolicyDefinitions},
+    AdmxBacked, AllowedValues, DdfIndex,
+};
+use serde::Serialize;
+use specta::Type;
+
+/// The language of the ADML files the display names are read from.
+const LANGUAGE: &str = "en-US";
+
+/// The Group Policies which can be configured with a CSP, looked up by policy or by node.
+#[derive(Type, Serialize, Default)]
+pub struct GpMappingTable {
+    /// Sorted by the area and name of the policy.
+    policies: Vec<GroupPolicy>,
+    /// The policies each node maps to, keyed by OMA-URI.
+    nodes: BTreeMap<String, Vec<NodeGroupPolicy>>,
+}
+
+/// A Group Policy and the nodes which configure it.
+#[derive(Type, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupPolicy {
+    #[serde(flatten)]
+    policy: GroupPolicyRef,
+    /// The name of the policy shown in the Group Policy editor and in GPO reports, if its ADMX file was provided.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_name: Option<String>,
+    nodes: Vec<GroupPolicyNode>,
+}
+
+/// A Group Policy, identified the same way as in its ADMX file.
+///
+/// Policies are compared by area and name only, as nodes mapped with `MSFT:GpMapping` don't name the file.
+#[derive(Type, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupPolicyRef {
+    /// The category path of the policy, such as `Printing~AT~ControlPanel~CplPrinters`.
+    area: String,
+    /// The name of the policy in its ADMX file, such as `NoAddPrinter`.
+    name: String,
+    /// The ADMX file the policy is defined in, if the node is backed by it.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+}
+
+impl PartialEq for GroupPolicyRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for GroupPolicyRef {}
+
+impl PartialOrd for GroupPolicyRef {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for GroupPolicyRef {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.area, &self.name).cmp(&(&other.area, &other.name))
+    }
+}
+
+#[derive(Type, Serialize)]
+pub struct GroupPolicyNode {
+    path: String,
+    /// The element of the policy the node maps to, if it only maps to part of it.
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    element: Option<String>,
+}
+
+/// A Group Policy which a node configures.
+#[derive(Type, Serialize)]
+pub struct NodeGroupPolicy {
+    #[serde(flatten)]
+    policy: GroupPolicyRef,
+    #[specta(optional)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    element: Option<String>,
+}
+
+/// An ADMX file, and the strings of its ADML file if there is one.
+pub struct AdmxFile {
+    definitions: PolicyDefinitions,
+    resources: Option<PolicyDefinitionResources>,
+}
+
+impl AdmxFile {
+    /// The display name of a policy, if it's defined in this file.
+    fn display_name(&self, policy: &AdmxBacked) -> Option<String> {
+        let policy = self.definitions.policy(policy)?;
+        let resources = self.resources.as_ref()?;
+        resources.string(&policy.display_name).map(Into::into)
+    }
+}
+
+/// Parse the ADMX files of a `PolicyDefinitions` directory and their English ADML files, returning a description of
+/// each problem found.
+pub fn load_admx(dir: &Path) -> (Vec<AdmxFile>, Vec<String>) {
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => return (files, vec![format!("{}: {err}", dir.display())]),
+    };
+    let mut paths = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "admx")
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    // The files usually start with a byte order mark
+    let read = |path: &Path| {
+        fs::read_to_string(path)
+            .map(|contents| contents.trim_start_matches('\u{feff}').to_string())
+            .map_err(|err| format!("{}: {err}", path.display()))
+    };
+    for path in paths {
+        let definitions = match read(&path).and_then(|contents| {
+            admx::from_str(&contents).map_err(|err| format!("{}: {err}", path.display()))
+        }) {
+            Ok(definitions) => definitions,
+            Err(err) => {
+                errors.push(err);
+                continue;
+            }
+        };
+
+        let adml = match path.file_stem() {
+            Some(stem) => dir.join(LANGUAGE).join(stem).with_extension("adml"),
+            None => continue,
+        };
+        let resources = match read(&adml).and_then(|contents| {
+            admx::resources_from_str(&contents).map_err(|err| format!("{}: {err}", adml.display()))
+        }) {
+            Ok(resources) => Some(resources),
+            Err(err) => {
+                errors.push(err);
+                None
+            }
+        };
+        files.push(AdmxFile {
+            definitions,
+            resources,
+        });
+    }
+    (files, errors)
+}
+
+impl GpMappingTable {
+    /// Collect the policies mapped with `MSFT:GpMapping` and the nodes which are backed by an ADMX policy, named with
+    /// the display names from `admx`.
+    pub fn new(index: &DdfIndex, admx: &[AdmxFile]) -> Self {
+        let mut policies = BTreeMap::<GroupPolicyRef, Vec<GroupPolicyNode>>::new();
+        for node in index.nodes() {
+            let (policy, element) = if let Some(mapping) = &node.node.properties.gp_mapping {
+                let policy = GroupPolicyRef {
+                    area: mapping.gp_area_path.clone(),
+                    name: mapping.gp_english_name.clone(),
+                    file: None,
+                };
+                (policy, mapping.gp_element.clone())
+            } else if let Some(AllowedValues::Admx(admx)) = node.allowed_values() {
+                let policy = GroupPolicyRef {
+                    area: admx.area.clone(),
+                    name: admx.name.clone(),
+                    file: Some(admx.file.clone()),
+                };
+                (policy, None)
+            } else {
+                continue;
+            };
+
+            // A policy can be mapped by one node and back another, and only the latter names the file
+            let (mut key, mut nodes) = policies
+                .remove_entry(&policy)
+                .unwrap_or_else(|| (policy.clone(), Vec::new()));
+            key.file = key.file.or(policy.file);
+            nodes.push(GroupPolicyNode {
+                path: node.path.clone(),
+                element,
+            });
+            policies.insert(key, nodes);
+        }
+
+        let mut table = Self::default();
+        for (policy, nodes) in policies {
+            for node in &nodes {
+                let policies = table.nodes.entry(node.path.clone()).or_default();
+                policies.push(NodeGroupPolicy {
+                    policy: policy.clone(),
+                    element: node.element.clone(),
+                });
+            }
+            let backed = AdmxBacked {
+                area: policy.area.clone(),
+                name: policy.name.clone(),
+                file: policy.file.clone().unwrap_or_default(),
+            };
+            let display_name = admx.iter().find_map(|file| file.display_name(&backed));
+            table.policies.push(GroupPolicy {
+                policy,
+                display_name,
+                nodes,
+            });
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ms_ddf::{Command, DFFormatVariant, GpMapping, MgmtTree, Node};
+    use serde_json::json;
+
+    use super::*;
+
+    const AREA: &str = "Printing~AT~ControlPanel~CplPrinters";
+
+    const ADMX: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<policyDefinitions revision="1.0" schemaVersion="1.0">
+  <policyNamespaces>
+    <target prefix="Printing" namespace="Microsoft.Policies.Printing" />
+    <using prefix="windows" namespace="Microsoft.Policies.Windows" />
+  </policyNamespaces>
+  <resources minRequiredRevision="1.0" />
+  <policies>
+    <policy name="NoAddPrinter" class="User" displayName="$(string.NoAddPrinter)" explainText="$(string.NoAddPrinter_Help)" key="Software\Microsoft\Windows\CurrentVersion\Policies\Explorer" valueName="NoAddPrinter">
+      <parentCategory ref="windows:CplPrinters" />
+      <supportedOn ref="windows:SUPPORTED_Win2k" />
+    </policy>
+  </policies>
+</policyDefinitions>"#;
+
+    const ADML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<policyDefinitionResources revision="1.0" schemaVersion="1.0">
+  <displayName />
+  <description />
+  <resources>
+    <stringTable>
+      <string id="NoAddPrinter">Prevent addition of printers</string>
+      <string id="NoAddPrinter_Help">Prevents users from installing printers.</string>
+    </stringTable>
+  </resources>
+</policyDefinitionResources>"#;
+
+    fn printers() -> MgmtTree {
+        let mut mapped = Node::new("MappedPrinters", DFFormatVariant::Int)
+            .access([Command::Get, Command::Replace]);
+        mapped.properties.gp_mapping = Some(GpMapping {
+            gp_english_name: "NoAddPrinter".into(),
+            gp_area_path: AREA.into(),
+            gp_element: None,
+        });
+        let backed = Node::new("BackedPrinters", DFFormatVariant::String)
+            .access([Command::Get, Command::Replace])
+            .allowed_values(AllowedValues::Admx(AdmxBacked {
+                area: AREA.into(),
+                name: "NoAddPrinter".into(),
+                file: "Printing.admx".into(),
+            }));
+        let root = Node::new("Policy", DFFormatVariant::Node)
+            .path("./User/Vendor/MSFT")
+            .child(mapped)
+            .child(backed);
+        MgmtTree::new().node(root)
+    }
+
+    #[test]
+    fn test_gp_mapping() {
+        let tree = printers();
+        let table = GpMappingTable::new(&DdfIndex::new([&tree]), &[]);
+
+        // Both nodes configure the same policy, and the file is taken from the one backed by it
+        let policy = json!({
+            "area": AREA,
+            "name": "NoAddPrinter",
+            "file": "Printing.admx",
+        });
+        assert_eq!(
+            serde_json::to_value(&table).unwrap(),
+            json!({
+                "policies": [{
+                    "area": AREA,
+                    "name": "NoAddPrinter",
+                    "file": "Printing.admx",
+                    "nodes": [
+                        { "path": "./User/Vendor/MSFT/Policy/BackedPrinters" },
+                        { "path": "./User/Vendor/MSFT/Policy/MappedPrinters" },
+                    ],
+                }],
+                "nodes": {
+                    "./User/Vendor/MSFT/Policy/BackedPrinters": [policy],
+                    "./User/Vendor/MSFT/Policy/MappedPrinters": [policy],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_display_name() {
+        let tree = printers();
+        let admx = [AdmxFile {
+            definitions: admx::from_str(ADMX).unwrap(),
+            resources: Some(admx::resources_from_str(ADML).unwrap()),
+        }];
+        let table = GpMappingTable::new(&DdfIndex::new([&tree]), &admx);
+        assert_eq!(
+            table.policies[0].display_name.as_deref(),
+            Some("Prevent addition of printers")
+        );
+
+        let other = [AdmxFile {
+            definitions: admx::from_str(&ADMX.replace("\"Printing\"", "\"Other\"")).unwrap(),
+            resources: Some(admx::resources_from_str(ADML).unwrap()),
+        }];
+        let table = GpMappingTable::new(&DdfIndex::new([&tree]), &other);
+        assert_eq!(table.policies[0].display_name, None);
+    }
+}
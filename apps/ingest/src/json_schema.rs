@@ -0,0 +1,106 @@
+//! Helpers for emitting JSON Schema (draft 2020-12) so tools which don't use our TypeScript types can validate policies.
+
+use serde_json::{json, Map, Value};
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Wrap the schema of a CSP or payload into a standalone document.
+pub fn document(title: &str, description: Option<&str>, schema: Value) -> Value {
+    let mut document = json!({
+        "$schema": DRAFT,
+        "title": title,
+    });
+    if let Some(description) = description {
+        document["description"] = description.into();
+    }
+    merge(&mut document, schema);
+    document
+}
+
+/// An object with a fixed set of keys.
+pub fn object(properties: Map<String, Value>, required: Vec<String>) -> Value {
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    });
+    if !required.is_empty() {
+        schema["required"] = required.into();
+    }
+    schema
+}
+
+/// Add the keys of `from` to `into`, which must both be objects.
+pub fn merge(into: &mut Value, from: Value) {
+    if let (Some(into), Value::Object(from)) = (into.as_object_mut(), from) {
+        into.extend(from);
+    }
+}
+
+/// Set a key if the value is present.
+pub fn set(schema: &mut Value, key: &str, value: Option<impl Into<Value>>) {
+    if let Some(value) = value {
+        schema[key] = value.into();
+    }
+}
+
+/// The path to write the schema to, relative to the output directory.
+pub fn file_name(name: &str) -> String {
+    let name = name.trim_start_matches("./");
+    let name = name.replace(
+        |c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-',
+        "_",
+    );
+    format!("schema/{name}.json")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_document() {
+        let mut properties = Map::new();
+        properties.insert("Name".into(), json!({ "type": "string" }));
+        properties.insert("Count".into(), json!({ "type": "integer" }));
+        let schema = document(
+            "Example",
+            Some("An example"),
+            object(properties, vec!["Name".into()]),
+        );
+        assert_eq!(schema["$schema"], DRAFT);
+        assert_eq!(schema["title"], "Example");
+        assert_eq!(schema["description"], "An example");
+
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        assert!(validator.is_valid(&json!({ "Name": "a", "Count": 1 })));
+        assert!(validator.is_valid(&json!({ "Name": "a" })));
+        assert!(!validator.is_valid(&json!({ "Count": 1 })));
+        assert!(!validator.is_valid(&json!({ "Name": "a", "Other": true })));
+        assert!(!validator.is_valid(&json!({ "Name": 1 })));
+
+        let schema = object(Map::new(), Vec::new());
+        assert_eq!(schema.get("required"), None);
+    }
+
+    #[test]
+    fn test_set() {
+        let mut schema = json!({});
+        set(&mut schema, "title", Some("Title"));
+        set(&mut schema, "description", None::<&str>);
+        assert_eq!(schema, json!({ "title": "Title" }));
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            file_name("./Device/Vendor/MSFT/Policy"),
+            "schema/Device_Vendor_MSFT_Policy.json"
+        );
+        assert_eq!(
+            file_name("com.apple.wifi.managed"),
+            "schema/com.apple.wifi.managed.json"
+        );
+        assert_eq!(file_name("a b{c}"), "schema/a_b_c_.json");
+    }
+}
@@ -1,7 +1,192 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::{Parser, Subcommand};
+
 mod apple_manifests;
+mod ddf_diff;
+mod gp_mapping;
+mod json_schema;
+mod search_index;
 mod windows_ddf;
 
-fn main() {
-    windows_ddf::generate_bindings();
-    apple_manifests::generate_bindings();
+const WINDOWS_DDF: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/ddf");
+const WINDOWS_SCHEMAS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../packages/configuration-schemas/src/windows"
+);
+const APPLE_MANIFESTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/manifests");
+const APPLE_SCHEMAS: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../packages/configuration-schemas/src/apple"
+);
+const SEARCH_INDEX: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/../../packages/configuration-schemas/src"
+);
+
+#[derive(Parser)]
+#[command(name = env!("CARGO_PKG_NAME"))]
+#[command(about = "Generate configuration schemas from vendor policy definitions")]
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    /// Generates every schema when omitted
+    #[command(subcommand)]
+    command: Option<Commands>,
+    /// Fail if any generated file is out of date instead of writing them
+    #[arg(long)]
+    check: bool,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Generate the Windows CSP schemas from DDF files
+    WindowsDdf(GenerateWindows),
+    /// Generate the Apple payload schemas from profile manifests
+    AppleManifests(Generate),
+    /// Generate the index used to search the settings of every platform
+    SearchIndex(GenerateSearchIndex),
+    /// Compare two directories of DDF files, such as the releases for two Windows builds
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        /// Print JSON instead of Markdown
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(clap::Args, Default)]
+struct Generate {
+    /// The directory to read from. Defaults to the copy in this repository
+    #[arg(long)]
+    input: Option<PathBuf>,
+    /// The directory to write to. Defaults to `packages/configuration-schemas`
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Fail if the generated files are out of date instead of writing them
+    #[arg(long)]
+    check: bool,
+}
+
+#[derive(clap::Args)]
+struct GenerateWindows {
+    #[command(flatten)]
+    generate: Generate,
+    /// A `PolicyDefinitions` directory, such as `C:\Windows\PolicyDefinitions`, to name the Group Policies in
+    /// `gp-mapping.json` with their English display names
+    #[arg(long)]
+    admx: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Default)]
+struct GenerateSearchIndex {
+    /// The directory of DDF files. Defaults to the copy in this repository
+    #[arg(long)]
+    windows_ddf: Option<PathBuf>,
+    /// The directory of profile manifests. Defaults to the copy in this repository
+    #[arg(long)]
+    apple_manifests: Option<PathBuf>,
+    /// The directory to write to. Defaults to `packages/configuration-schemas`
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Fail if the index is out of date instead of writing it
+    #[arg(long)]
+    check: bool,
+}
+
+/// The files generated from a source, and the problems found reading it.
+pub struct Generated {
+    pub files: Vec<(String, String)>,
+    pub errors: Vec<String>,
+}
+
+impl Generate {
+    fn run(
+        &self,
+        input: &str,
+        output: &str,
+        generate: impl FnOnce(&Path) -> Generated,
+    ) -> Vec<String> {
+        let input = self.input.as_deref().unwrap_or(Path::new(input));
+        let output = self.output.as_deref().unwrap_or(Path::new(output));
+        write(output, self.check, generate(input))
+    }
+}
+
+impl GenerateSearchIndex {
+    fn run(&self) -> Vec<String> {
+        let windows_ddf = self.windows_ddf.as_deref();
+        let apple_manifests = self.apple_manifests.as_deref();
+        let output = self.output.as_deref().unwrap_or(Path::new(SEARCH_INDEX));
+        let generated = search_index::generate(
+            windows_ddf.unwrap_or(Path::new(WINDOWS_DDF)),
+            apple_manifests.unwrap_or(Path::new(APPLE_MANIFESTS)),
+        );
+        write(output, self.check, generated)
+    }
+}
+
+/// Write the generated files into the output directory, or in check mode report the ones which differ.
+fn write(output: &Path, check: bool, generated: Generated) -> Vec<String> {
+    let Generated { files, mut errors } = generated;
+    for (name, contents) in files {
+        let path = output.join(name);
+        if check {
+            if fs::read_to_string(&path).ok().as_deref() != Some(contents.as_str()) {
+                errors.push(format!("{} is out of date", path.display()));
+            }
+        } else if let Err(err) = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, contents))
+        {
+            errors.push(format!("{}: {err}", path.display()));
+        }
+    }
+    errors
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let errors = match cli.command {
+        Some(Commands::WindowsDdf(args)) => {
+            let admx = args.admx.as_deref();
+            let generate = |input: &Path| windows_ddf::generate(input, admx);
+            args.generate.run(WINDOWS_DDF, WINDOWS_SCHEMAS, generate)
+        }
+        Some(Commands::AppleManifests(args)) => {
+            args.run(APPLE_MANIFESTS, APPLE_SCHEMAS, apple_manifests::generate)
+        }
+        Some(Commands::SearchIndex(args)) => args.run(),
+        Some(Commands::Diff { old, new, json }) => ddf_diff::run(&old, &new, json),
+        None => {
+            let args = Generate {
+                check: cli.check,
+                ..Default::default()
+            };
+            let generate = |input: &Path| windows_ddf::generate(input, None);
+            let mut errors = args.run(WINDOWS_DDF, WINDOWS_SCHEMAS, generate);
+            errors.extend(args.run(APPLE_MANIFESTS, APPLE_SCHEMAS, apple_manifests::generate));
+            let search_index = GenerateSearchIndex {
+                check: cli.check,
+                ..Default::default()
+            };
+            errors.extend(search_index.run());
+            errors
+        }
+    };
+
+    if errors.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    for err in &errors {
+        eprintln!("{err}");
+    }
+    eprintln!("{} problem(s) found", errors.len());
+    ExitCode::FAILURE
 }
@@ -0,0 +1,23 @@
+//! The search index over every setting in the catalog, which the web UI loads from `search.json`.
+
+use std::path::Path;
+
+use ms_ddf::DdfIndex;
+use mx_catalog::SearchIndex;
+
+use crate::{apple_manifests, windows_ddf, Generated};
+
+pub fn generate(windows_ddf: &Path, apple_manifests: &Path) -> Generated {
+    let (trees, mut errors) = windows_ddf::load_dir(windows_ddf);
+    let (manifests, apple_errors) = apple_manifests::load_dir(apple_manifests);
+    errors.extend(apple_errors);
+
+    let mut documents = windows_ddf::search_documents(&DdfIndex::new(&trees));
+    documents.extend(apple_manifests::search_documents(&manifests));
+    let index = SearchIndex::new(documents);
+
+    Generated {
+        files: vec![("search.json".into(), serde_json::to_string(&index).unwrap())],
+        errors,
+    }
+}
@@ -4,11 +4,18 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use ms_ddf::{AllowedValueGroupedNodes, DFFormatVariant, MgmtTree, Node};
+use ms_ddf::{AllowedValues, DFFormatVariant, DdfIndex, MgmtTree, Node, OccurrenceVariant};
+use mx_catalog::{Document, Platform};
 use serde::Serialize;
+use serde_json::{json, Value};
 use specta::{NamedType, Type};
 use specta_typescript::Typescript;
 
+use crate::{
+    gp_mapping::{self, GpMappingTable},
+    json_schema, Generated,
+};
+
 #[derive(Serialize, Debug, Type)]
 #[serde(rename_all = "camelCase")]
 struct WindowsCSP {
@@ -33,6 +40,17 @@ struct WindowsDDFNode {
     description: Option<String>,
     #[serde(flatten)]
     format: Format,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[specta(optional)]
+    allowed_values: Option<NodeAllowedValues>,
+    /// Set when the value is a list of items separated by this delimiter, which each match `allowed_values`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[specta(optional)]
+    list_delimiter: Option<String>,
+    occurrence: Occurrence,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[specta(optional)]
+    applicability: Option<Applicability>,
     scope: Scope,
     dynamic: Option<String>,
 }
@@ -42,35 +60,65 @@ struct WindowsDDFNode {
 enum Format {
     #[serde(rename_all = "camelCase")]
     Int {
-        default_value: i32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[specta(optional, type = Option<f64>)]
+        default_value: Option<i64>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Bool {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[specta(optional)]
+        default_value: Option<bool>,
+    },
+    #[serde(rename_all = "camelCase")]
+    String {
+        #[serde(skip_serializing_if = "Option::is_none")]
         #[specta(optional)]
-        allowed_values: Option<IntAllowedValues>,
+        default_value: Option<String>,
     },
-    Bool,
-    String,
     Node {
         #[serde(skip_serializing_if = "WindowsDFFNodeGroup::is_empty")]
         nodes: WindowsDFFNodeGroup,
     },
     Null,
-    Base64,
+    #[serde(rename_all = "camelCase")]
+    Base64 {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[specta(optional)]
+        default_value: Option<String>,
+    },
+    Date,
     Time,
-    Float,
+    #[serde(rename_all = "camelCase")]
+    Float {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[specta(optional)]
+        default_value: Option<f64>,
+    },
     Xml,
     Bin,
 }
 
 impl Format {
-    fn parse(node: &Node, scope: Scope) -> Option<Self> {
+    fn parse(
+        node: &Node,
+        scope: Scope,
+        applicability: Option<&ms_ddf::Applicability>,
+    ) -> Option<Self> {
+        let default_value = node.properties.default_value.as_deref().map(str::trim);
         Some(match &*node.properties.df_format {
-            DFFormatVariant::Bool => Self::Bool,
-            DFFormatVariant::String => Self::String,
+            DFFormatVariant::Bool => Self::Bool {
+                default_value: default_value.and_then(parse_bool),
+            },
+            DFFormatVariant::String => Self::String {
+                default_value: default_value.map(Into::into),
+            },
             DFFormatVariant::Node => {
                 let mut nodes = WindowsDFFNodeGroup::new();
 
                 if node.node_name.is_empty() {
                     for child in &node.children {
-                        nodes.extend(handle_node(child, &String::new(), scope));
+                        nodes.extend(handle_node(child, "", scope, applicability));
                     }
                 }
 
@@ -81,72 +129,101 @@ impl Format {
                 Self::Node { nodes }
             }
             DFFormatVariant::Null => Self::Null,
-            DFFormatVariant::Base64 => Self::Base64,
+            DFFormatVariant::Base64 => Self::Base64 {
+                default_value: default_value.map(Into::into),
+            },
+            DFFormatVariant::Date => Self::Date,
             DFFormatVariant::Time => Self::Time,
-            DFFormatVariant::Float => Self::Float,
+            DFFormatVariant::Float => Self::Float {
+                default_value: default_value.and_then(|value| value.parse().ok()),
+            },
             DFFormatVariant::Xml => Self::Xml,
             DFFormatVariant::Bin => Self::Bin,
             DFFormatVariant::Int => Self::Int {
-                default_value: 0,
-                allowed_values: IntAllowedValues::parse(node),
+                default_value: default_value.and_then(|value| value.parse().ok()),
             },
-            format => todo!("{}", format.to_string()),
         })
     }
 }
 
+/// DDF files write booleans as `true`, `True` or `1`.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
 #[derive(Serialize, Debug, Type)]
 #[serde(tag = "valueType", rename_all = "camelCase")]
-enum IntAllowedValues {
+enum NodeAllowedValues {
     Range {
-        min: i32,
-        max: i32,
+        #[specta(type = f64)]
+        min: i64,
+        #[specta(type = f64)]
+        max: i64,
     },
     Enum {
         #[serde(rename = "enum")]
         r#enum: BTreeMap<String, EnumContent>,
     },
+    /// Any combination of the values, added together.
+    Flag {
+        flags: BTreeMap<String, EnumContent>,
+    },
+    Regex {
+        pattern: String,
+    },
+    /// An ADMX-backed policy, whose value is the `<enabled/>` or `<disabled/>` XML.
+    Admx {
+        file: String,
+        name: String,
+    },
+    Xsd,
+    Sddl,
+    Json,
 }
 
-impl IntAllowedValues {
-    fn parse(node: &Node) -> Option<Self> {
-        let allowed_values = node.properties.allowed_values.as_ref()?;
-
-        Some(match allowed_values.value_type {
-            ms_ddf::ValueType::Range => {
-                let AllowedValueGroupedNodes::Value(value) = &allowed_values.values else {
-                    return None;
-                };
-
-                let (min, max) = value.value[1..value.value.len() - 2].split_once('-')?;
-
-                Self::Range {
-                    min: min.parse().ok()?,
-                    max: max.parse().ok()?,
-                }
-            }
-            ms_ddf::ValueType::ENUM => {
-                let AllowedValueGroupedNodes::Enum(variants) = &allowed_values.values else {
-                    return None;
-                };
-
-                Self::Enum {
-                    r#enum: variants
-                        .iter()
-                        .map(|variant| {
-                            (
-                                variant.value.clone(),
-                                EnumContent {
-                                    description: variant.value_description.clone(),
-                                },
-                            )
-                        })
-                        .collect(),
-                }
-            }
-            _ => {
-                return None;
-            }
+impl NodeAllowedValues {
+    fn parse(allowed_values: &AllowedValues) -> Option<Self> {
+        let variants = |variants: &[ms_ddf::EnumValue]| {
+            variants
+                .iter()
+                .map(|variant| {
+                    (
+                        variant.value.clone(),
+                        EnumContent {
+                            description: variant.description.clone(),
+                        },
+                    )
+                })
+                .collect()
+        };
+
+        Some(match allowed_values {
+            AllowedValues::Range(range) => Self::Range {
+                min: range.min,
+                max: range.max,
+            },
+            AllowedValues::Enum(values) => Self::Enum {
+                r#enum: variants(values),
+            },
+            AllowedValues::Flag(values) => Self::Flag {
+                flags: variants(values),
+            },
+            AllowedValues::Regex(pattern) => Self::Regex {
+                pattern: pattern.clone(),
+            },
+            AllowedValues::Admx(admx) => Self::Admx {
+                file: admx.file.clone(),
+                name: admx.name.clone(),
+            },
+            AllowedValues::Xsd(_) => Self::Xsd,
+            AllowedValues::Sddl(_) => Self::Sddl,
+            AllowedValues::Json(_) => Self::Json,
+            AllowedValues::List { item, .. } => return Self::parse(item),
+            AllowedValues::None => return None,
         })
     }
 }
@@ -156,18 +233,80 @@ struct EnumContent {
     description: Option<String>,
 }
 
+#[derive(Serialize, Debug, Type)]
+#[serde(rename_all = "camelCase")]
+enum Occurrence {
+    One,
+    ZeroOrOne,
+    ZeroOrMore,
+    OneOrMore,
+    ZeroOrN(i32),
+    OneOrN(i32),
+}
+
+impl Occurrence {
+    fn parse(node: &Node) -> Self {
+        match node.properties.occurrence.as_deref() {
+            Some(OccurrenceVariant::One) | None => Self::One,
+            Some(OccurrenceVariant::ZeroOrOne) => Self::ZeroOrOne,
+            Some(OccurrenceVariant::ZeroOrMore) => Self::ZeroOrMore,
+            Some(OccurrenceVariant::OneOrMore) => Self::OneOrMore,
+            Some(OccurrenceVariant::ZeroOrN(n)) => Self::ZeroOrN(*n),
+            Some(OccurrenceVariant::OneOrN(n)) => Self::OneOrN(*n),
+        }
+    }
+}
+
+/// What a device requires for the node to apply, inherited from the closest ancestor which has one.
+#[derive(Serialize, Debug, Type)]
+#[serde(rename_all = "camelCase")]
+struct Applicability {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[specta(optional)]
+    os_build_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[specta(optional)]
+    csp_version: Option<String>,
+    /// The ids of the Windows editions the node applies to, or every edition when unset.
+    /// An id such as `0x88*` includes every edition whose id starts with those hex digits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[specta(optional)]
+    editions: Option<Vec<String>>,
+    requires_azure_ad: bool,
+}
+
+impl Applicability {
+    fn parse(applicability: &ms_ddf::Applicability) -> Self {
+        Self {
+            os_build_version: applicability.os_build_version.clone(),
+            csp_version: applicability.csp_version.clone(),
+            editions: applicability
+                .edition_allow_list
+                .as_ref()
+                .map(|list| list.ids.iter().map(ToString::to_string).collect()),
+            requires_azure_ad: applicability.requires_azure_ad.is_some(),
+        }
+    }
+}
+
 type WindowsDFFNodeGroup = BTreeMap<String, WindowsDDFNode>;
 
-fn handle_node(node: &Node, path: &str, scope: Scope) -> WindowsDFFNodeGroup {
+fn handle_node(
+    node: &Node,
+    path: &str,
+    scope: Scope,
+    applicability: Option<&ms_ddf::Applicability>,
+) -> WindowsDFFNodeGroup {
     let mut collection = WindowsDFFNodeGroup::default();
 
     let access_type = &node.properties.access_type;
+    let applicability = node.properties.applicability.as_ref().or(applicability);
 
     let mut path = node
         .path
         .as_ref()
         .map(|new_path| {
-            if path == "" {
+            if path.is_empty() {
                 new_path.to_string()
             } else {
                 format!("{path}/{new_path}")
@@ -178,20 +317,20 @@ fn handle_node(node: &Node, path: &str, scope: Scope) -> WindowsDFFNodeGroup {
     let mut dynamic = None;
 
     if let (Some(title), "") = (&node.properties.df_title, node.node_name.as_str()) {
-        if path == "" || path == "/" {
+        if path.is_empty() || path == "/" {
             path = format!("{{{title}}}");
         } else {
             dynamic = Some(title.to_string());
         }
     } else {
-        if path == "" || path == "/" {
+        if path.is_empty() || path == "/" {
             path = format!("/{}", &node.node_name);
         } else {
             path = format!("{path}/{}", &node.node_name);
         }
 
         for child in &node.children {
-            collection.extend(handle_node(child, &path, scope));
+            collection.extend(handle_node(child, &path, scope, applicability));
         }
     }
 
@@ -199,11 +338,12 @@ fn handle_node(node: &Node, path: &str, scope: Scope) -> WindowsDFFNodeGroup {
         return collection;
     }
 
-    if let Some(format) = Format::parse(node, scope) {
+    if let Some(format) = Format::parse(node, scope, applicability) {
         if matches!(format, Format::Node { .. }) && !node.node_name.is_empty() {
             return collection;
         }
 
+        let allowed_values = node.properties.allowed_values.as_ref();
         collection.insert(
             path,
             WindowsDDFNode {
@@ -211,6 +351,13 @@ fn handle_node(node: &Node, path: &str, scope: Scope) -> WindowsDFFNodeGroup {
                 title: node.properties.df_title.clone(),
                 description: node.properties.description.clone(),
                 format,
+                allowed_values: allowed_values.and_then(NodeAllowedValues::parse),
+                list_delimiter: match allowed_values {
+                    Some(AllowedValues::List { delimiter, .. }) => Some(delimiter.to_string()),
+                    _ => None,
+                },
+                occurrence: Occurrence::parse(node),
+                applicability: applicability.map(Applicability::parse),
                 scope,
                 dynamic,
             },
@@ -220,7 +367,7 @@ fn handle_node(node: &Node, path: &str, scope: Scope) -> WindowsDFFNodeGroup {
     collection
 }
 
-fn handle_mgmt_tree(tree: MgmtTree) -> Vec<(PathBuf, WindowsCSP)> {
+fn handle_mgmt_tree(tree: MgmtTree) -> Vec<Result<(PathBuf, WindowsCSP), String>> {
     tree.nodes
         .into_iter()
         .map(|node| {
@@ -229,7 +376,9 @@ fn handle_mgmt_tree(tree: MgmtTree) -> Vec<(PathBuf, WindowsCSP)> {
                 nodes: Default::default(),
             };
 
-            let path = node.path.unwrap();
+            let Some(path) = node.path else {
+                return Err(format!("{}: the root node has no Path", node.node_name));
+            };
 
             let scope = if path.starts_with("./User") {
                 Scope::User
@@ -237,46 +386,244 @@ fn handle_mgmt_tree(tree: MgmtTree) -> Vec<(PathBuf, WindowsCSP)> {
                 Scope::Device
             };
 
-            for node in node.children {
-                csp.nodes.extend(handle_node(&node, "/", scope))
+            for child in &node.children {
+                let applicability = node.properties.applicability.as_ref();
+                csp.nodes
+                    .extend(handle_node(child, "/", scope, applicability))
             }
 
-            (PathBuf::from(path).join(node.node_name), csp)
+            Ok((PathBuf::from(path).join(node.node_name), csp))
         })
         .collect()
 }
 
-#[derive(Type, Default, Serialize)]
-struct WindowsCSPCollection(BTreeMap<PathBuf, WindowsCSP>);
+impl WindowsCSP {
+    /// The JSON Schema for the nodes of this CSP in a policy.
+    fn json_schema(&self, path: &Path) -> Value {
+        let properties = self
+            .nodes
+            .iter()
+            .map(|(path, node)| (node.key(path), node.json_schema()))
+            .collect();
+        json_schema::document(
+            &self.name,
+            Some(&path.display().to_string()),
+            json_schema::object(properties, Vec::new()),
+        )
+    }
+}
+
+impl WindowsDDFNode {
+    /// The key of the node in a policy, which includes the title of a dynamic node.
+    fn key(&self, path: &str) -> String {
+        match &self.dynamic {
+            Some(dynamic) => format!("{path}/{{{dynamic}}}"),
+            None => path.to_string(),
+        }
+    }
 
-pub fn generate_bindings() {
-    let files = fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("./ddf")).unwrap();
+    fn json_schema(&self) -> Value {
+        let mut schema = match &self.format {
+            Format::Node { nodes } => {
+                let properties = nodes
+                    .iter()
+                    .map(|(path, node)| (node.key(path), node.json_schema()))
+                    .collect();
+                let required = nodes
+                    .iter()
+                    .filter(|(_, node)| node.occurrence.is_required())
+                    .map(|(path, node)| node.key(path))
+                    .collect();
+                json!({
+                    "type": "object",
+                    "additionalProperties": json_schema::object(properties, required),
+                })
+            }
+            // Each item of a list could be checked with a pattern, but DDF regexes aren't anchored
+            _ if self.list_delimiter.is_some() => json!({ "type": "string" }),
+            _ => self.value_schema(),
+        };
+        if self.dynamic.is_some() && !matches!(self.format, Format::Node { .. }) {
+            schema = json!({ "type": "array", "items": schema });
+        }
 
-    let mut policy_collection = WindowsCSPCollection::default();
+        json_schema::set(&mut schema, "title", self.title.as_deref());
+        json_schema::set(&mut schema, "description", self.description.as_deref());
+        schema
+    }
+
+    fn value_schema(&self) -> Value {
+        let mut schema = match &self.format {
+            Format::Int { default_value } => json!({ "type": "integer", "default": default_value }),
+            Format::Bool { default_value } => {
+                json!({ "type": "boolean", "default": default_value })
+            }
+            Format::String { default_value } => {
+                json!({ "type": "string", "default": default_value })
+            }
+            Format::Base64 { default_value } => json!({
+                "type": "string",
+                "contentEncoding": "base64",
+                "default": default_value,
+            }),
+            Format::Float { default_value } => {
+                json!({ "type": "number", "default": default_value })
+            }
+            Format::Date => json!({ "type": "string", "format": "date" }),
+            Format::Time => json!({ "type": "string", "format": "time" }),
+            Format::Xml => json!({ "type": "string", "contentMediaType": "application/xml" }),
+            Format::Bin => json!({ "type": "string" }),
+            Format::Null => json!({ "type": "null" }),
+            Format::Node { .. } => json!({ "type": "object" }),
+        };
+        if schema["default"].is_null() {
+            schema.as_object_mut().unwrap().remove("default");
+        }
+
+        match &self.allowed_values {
+            Some(NodeAllowedValues::Range { min, max }) => {
+                json_schema::merge(&mut schema, json!({ "minimum": min, "maximum": max }));
+            }
+            Some(NodeAllowedValues::Enum { r#enum }) => {
+                let is_int = matches!(self.format, Format::Int { .. });
+                let one_of = r#enum
+                    .iter()
+                    .map(|(value, content)| {
+                        let value = match value.trim().parse::<i64>() {
+                            Ok(value) if is_int => value.into(),
+                            _ => Value::from(value.as_str()),
+                        };
+                        let mut variant = json!({ "const": value });
+                        json_schema::set(
+                            &mut variant,
+                            "description",
+                            content.description.as_deref(),
+                        );
+                        variant
+                    })
+                    .collect::<Vec<_>>();
+                schema["oneOf"] = one_of.into();
+            }
+            Some(NodeAllowedValues::Regex { pattern }) => {
+                let pattern = pattern
+                    .strip_prefix('/')
+                    .and_then(|p| p.strip_suffix('/'))
+                    .unwrap_or(pattern);
+                schema["pattern"] = pattern.into();
+            }
+            Some(NodeAllowedValues::Admx { .. }) => {
+                schema["pattern"] = r"^\s*<(enabled|disabled)".into();
+            }
+            Some(NodeAllowedValues::Xsd) => schema["contentMediaType"] = "application/xml".into(),
+            Some(NodeAllowedValues::Json) => schema["contentMediaType"] = "application/json".into(),
+            Some(NodeAllowedValues::Flag { .. } | NodeAllowedValues::Sddl) | None => {}
+        }
+        schema
+    }
+}
+
+impl Occurrence {
+    fn is_required(&self) -> bool {
+        matches!(self, Self::One | Self::OneOrMore | Self::OneOrN(_))
+    }
+}
+
+/// The nodes which can be configured, to be found by searching.
+pub fn search_documents(index: &DdfIndex) -> Vec<Document> {
+    index
+        .nodes()
+        .into_iter()
+        .filter(|node| {
+            let access_type = node.access_type();
+            let configurable = access_type.add.is_some() || access_type.replace.is_some();
+            configurable && (node.is_dynamic() || !matches!(node.format(), DFFormatVariant::Node))
+        })
+        .map(|resolved| {
+            let properties = &resolved.node.properties;
+            let mut keywords = Vec::new();
+            if let Some(gp_mapping) = &properties.gp_mapping {
+                keywords.push(gp_mapping.gp_english_name.clone());
+            }
+            match resolved.allowed_values().map(AllowedValues::item) {
+                Some(AllowedValues::Enum(values) | AllowedValues::Flag(values)) => {
+                    keywords.extend(values.iter().filter_map(|value| value.description.clone()));
+                }
+                Some(AllowedValues::Admx(admx)) => keywords.push(admx.name.clone()),
+                _ => {}
+            }
+
+            Document {
+                platform: Platform::Windows,
+                path: resolved.path.clone(),
+                title: properties
+                    .df_title
+                    .clone()
+                    .unwrap_or_else(|| resolved.node.node_name.clone()),
+                description: properties.description.clone(),
+                keywords,
+            }
+        })
+        .collect()
+}
 
+#[derive(Type, Default, Serialize)]
+struct WindowsCSPCollection(BTreeMap<PathBuf, WindowsCSP>);
+
+/// Parse every DDF file in a directory, skipping any nodes which are malformed.
+///
+/// Returns the parsed files and a description of each problem found.
+pub fn load_dir(dir: &Path) -> (Vec<MgmtTree>, Vec<String>) {
+    let mut files = match fs::read_dir(dir) {
+        Ok(files) => files.filter_map(Result::ok).collect::<Vec<_>>(),
+        Err(err) => return (Vec::new(), vec![format!("{}: {err}", dir.display())]),
+    };
+    // Sorted so the first definition of a node is the same on every machine
+    files.sort_by_key(|file| file.path());
+
+    let mut trees = Vec::new();
+    let mut errors = Vec::new();
     for file in files {
-        let file = file.unwrap();
-        if !file.file_name().to_string_lossy().ends_with(".xml") {
+        let path = file.path();
+        if path.extension().is_none_or(|extension| extension != "xml") {
             continue;
         }
-        let contents = fs::read(file.path()).unwrap();
-
-        let root: MgmtTree = easy_xml::de::from_bytes(contents.as_slice())
-            .unwrap_or_else(|_| panic!("Failed to parse {:?}", file.path()));
 
-        for (path, csp) in handle_mgmt_tree(root) {
-            if !csp.nodes.is_empty() {
-                policy_collection.0.insert(path, csp);
+        let result = fs::read(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| {
+                ms_ddf::from_bytes_lenient(&contents).map_err(|err| err.to_string())
+            });
+        match result {
+            Ok((root, node_errors)) => {
+                let node_errors = node_errors.into_iter();
+                errors.extend(node_errors.map(|err| format!("{}:{err}", path.display())));
+                trees.push(root);
             }
+            Err(err) => errors.push(format!("{}: {err}", path.display())),
         }
     }
+    (trees, errors)
+}
 
-    fs::write(
-        Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("../../packages/configuration-schemas/src/windows/ddf.json"),
-        serde_json::to_string_pretty(&policy_collection).unwrap(),
-    )
-    .unwrap();
+/// Generate the schemas from a directory of DDF files, naming the Group Policies with the ADMX files in `admx`.
+pub fn generate(input: &Path, admx: Option<&Path>) -> Generated {
+    let (trees, mut errors) = load_dir(input);
+    let (admx, admx_errors) = admx.map(gp_mapping::load_admx).unwrap_or_default();
+    errors.extend(admx_errors);
+    let gp_mapping = GpMappingTable::new(&DdfIndex::new(&trees), &admx);
+
+    let mut policy_collection = WindowsCSPCollection::default();
+    for root in trees {
+        for result in handle_mgmt_tree(root) {
+            match result {
+                Ok((path, csp)) if !csp.nodes.is_empty() => {
+                    policy_collection.0.insert(path, csp);
+                }
+                Ok(_) => {}
+                Err(err) => errors.push(err),
+            }
+        }
+    }
 
     let mut types = String::new();
     let type_map = &mut Default::default();
@@ -287,6 +634,7 @@ pub fn generate_bindings() {
         type_map,
     )
     .unwrap();
+    let gp_mapping_type = GpMappingTable::definition_named_data_type(type_map);
 
     type_map.iter().for_each(|(_, ty)| {
         types.push_str(
@@ -294,11 +642,126 @@ pub fn generate_bindings() {
         );
         types.push('\n');
     });
+    types.push_str(
+        &specta_typescript::export_named_datatype(&Default::default(), &gp_mapping_type, type_map)
+            .unwrap(),
+    );
+    types.push('\n');
+
+    let mut files = vec![
+        (
+            "ddf.json".into(),
+            serde_json::to_string_pretty(&policy_collection).unwrap(),
+        ),
+        ("ddf.ts".into(), types),
+        (
+            "gp-mapping.json".into(),
+            serde_json::to_string_pretty(&gp_mapping).unwrap(),
+        ),
+    ];
+    for (path, csp) in &policy_collection.0 {
+        files.push((
+            json_schema::file_name(&path.to_string_lossy()),
+            serde_json::to_string_pretty(&csp.json_schema(path)).unwrap(),
+        ));
+    }
 
-    fs::write(
-        Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("../../packages/configuration-schemas/src/windows/ddf.ts"),
-        types,
-    )
-    .unwrap();
+    Generated { files, errors }
+}
+
+#[cfg(test)]
+mod test {
+    use ms_ddf::{Command, EnumValue};
+
+    use super::*;
+
+    fn csp() -> (PathBuf, WindowsCSP) {
+        let configurable = [Command::Get, Command::Replace];
+        let root = Node::new("Test", DFFormatVariant::Node)
+            .path("./Device/Vendor/MSFT")
+            .access([Command::Get])
+            .child(
+                Node::new("Level", DFFormatVariant::Int)
+                    .access(configurable)
+                    .default_value("5")
+                    .allowed_values(AllowedValues::Range("[0-10]".parse().unwrap())),
+            )
+            .child(
+                Node::new("Mode", DFFormatVariant::Int)
+                    .access(configurable)
+                    .allowed_values(AllowedValues::Enum(vec![
+                        EnumValue {
+                            value: "0".into(),
+                            description: Some("Off".into()),
+                        },
+                        EnumValue {
+                            value: "1".into(),
+                            description: Some("On".into()),
+                        },
+                    ])),
+            )
+            .child(
+                Node::new("Rules", DFFormatVariant::Node)
+                    .access([Command::Get])
+                    .child(
+                        Node::dynamic("RuleName").child(
+                            Node::new("Enabled", DFFormatVariant::Bool)
+                                .access(configurable)
+                                .occurrence(OccurrenceVariant::One),
+                        ),
+                    ),
+            );
+
+        let mut csps = handle_mgmt_tree(MgmtTree::new().node(root));
+        assert_eq!(csps.len(), 1);
+        csps.remove(0).unwrap()
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let (path, csp) = csp();
+        assert_eq!(path, Path::new("./Device/Vendor/MSFT/Test"));
+        let schema = csp.json_schema(&path);
+        assert_eq!(schema["title"], "Test");
+        assert_eq!(
+            schema["properties"]["/Level"],
+            json!({ "type": "integer", "default": 5, "minimum": 0, "maximum": 10 })
+        );
+        assert_eq!(
+            schema["properties"]["/Mode"]["oneOf"],
+            json!([
+                { "const": 0, "description": "Off" },
+                { "const": 1, "description": "On" },
+            ])
+        );
+
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        assert!(validator.is_valid(&json!({
+            "/Level": 10,
+            "/Mode": 1,
+            "/Rules/{RuleName}": {
+                "First": { "/Enabled": true },
+                "Second": { "/Enabled": false },
+            },
+        })));
+        assert!(validator.is_valid(&json!({})));
+        assert!(!validator.is_valid(&json!({ "/Level": 11 })));
+        assert!(!validator.is_valid(&json!({ "/Level": "5" })));
+        assert!(!validator.is_valid(&json!({ "/Mode": 2 })));
+        assert!(!validator.is_valid(&json!({ "/Rules/{RuleName}": { "First": {} } })));
+        assert!(!validator.is_valid(&json!({
+            "/Rules/{RuleName}": { "First": { "/Enabled": true, "/Other": 1 } },
+        })));
+        assert!(!validator.is_valid(&json!({ "/Other": 1 })));
+    }
+
+    #[test]
+    fn test_missing_path() {
+        let tree = MgmtTree::new().node(Node::new("Test", DFFormatVariant::Node));
+        let csps = handle_mgmt_tree(tree);
+        assert_eq!(
+            csps[0].as_ref().unwrap_err(),
+            "Test: the root node has no Path"
+        );
+    }
 }
@@ -0,0 +1,350 @@
+//! Actions requested by an administrator, such as syncing or rebooting the device.
+//!
+//! The agent holds a WebSocket open so actions are received immediately.
+//! If it's down, the same actions are returned from the next check-in instead.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use futures_util::{SinkExt, StreamExt};
+use mx_agent_api::{Action, ActionKind, ActionResult, AgentMessage, ServerMessage};
+use tokio::{process::Command, sync::mpsc};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, info, warn};
+
+use crate::{checkin, client::Client, ctl::Job, diagnostics, outbox::Outbox, scripts};
+
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(5 * 60);
+
+// Keeps the connection alive through NAT and detects a dead connection
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+// Duplicates arrive within one check-in interval so only the most recent ids need to be remembered
+const MAX_HANDLED: usize = 1024;
+
+/// The most recently handled actions.
+///
+/// Actions can be received over the WebSocket and from a check-in, so this is used to only perform each once.
+/// Reporting the result marks the action as complete so it doesn't need to outlive the process.
+#[derive(Default)]
+pub struct Handled {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl Handled {
+    /// Returns `false` if the action was already handled.
+    pub fn insert(&mut self, id: &str) -> bool {
+        if !self.ids.insert(id.to_string()) {
+            return false;
+        }
+
+        self.order.push_back(id.to_string());
+        if self.order.len() > MAX_HANDLED {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Hold the actions WebSocket open in the background, forwarding actions to the check-in loop.
+pub fn listen(client: Client, jobs: mpsc::Sender<Job>) {
+    tokio::spawn(async move {
+        let mut delay = INITIAL_RECONNECT_DELAY;
+        loop {
+            match client.websocket("/agent/ws").await {
+                Ok(socket) => {
+                    info!("Connected to the actions socket");
+                    delay = INITIAL_RECONNECT_DELAY;
+                    match session(socket, &jobs).await {
+                        Ok(()) => info!("Actions socket closed by the server"),
+                        Err(err) => warn!("Actions socket disconnected: {err}"),
+                    }
+                }
+                Err(err) => debug!("Error connecting to the actions socket: {err}"),
+            }
+
+            if jobs.is_closed() {
+                return;
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    });
+}
+
+async fn session(
+    mut socket: tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >,
+    jobs: &mpsc::Sender<Job>,
+) -> Result<(), String> {
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    let mut awaiting_pong = false;
+    loop {
+        tokio::select! {
+            _ = ping.tick() => {
+                if awaiting_pong {
+                    return Err("no response to ping".into());
+                }
+                awaiting_pong = true;
+                socket
+                    .send(Message::Ping(vec![]))
+                    .await
+                    .map_err(|err| format!("error sending ping: {err}"))?;
+            }
+            msg = socket.next() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    let ServerMessage::Action(action) = match serde_json::from_str(&text) {
+                        Ok(msg) => msg,
+                        Err(err) => {
+                            warn!("Ignoring invalid message on the actions socket: {err}");
+                            continue;
+                        }
+                    };
+
+                    info!("Received {:?} action {:?}", action.kind, action.id);
+                    let id = action.id.clone();
+                    jobs.send(Job::Action(action))
+                        .await
+                        .map_err(|_| "the agent is shutting down".to_string())?;
+
+                    // Once acknowledged the server won't resend the action until it's redelivered by a check-in
+                    let ack = serde_json::to_string(&AgentMessage::Ack { id }).unwrap();
+                    socket
+                        .send(Message::Text(ack))
+                        .await
+                        .map_err(|err| format!("error sending acknowledgement: {err}"))?;
+                }
+                Some(Ok(Message::Pong(_))) => awaiting_pong = false,
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err.to_string()),
+            },
+        }
+    }
+}
+
+/// Perform an action in the background so long running actions don't hold up the check-in loop.
+///
+/// The result is sent back to the check-in loop to be reported through the outbox.
+pub fn spawn(data_dir: PathBuf, client: Client, action: Action, jobs: mpsc::Sender<Job>) {
+    tokio::spawn(async move {
+        let result = perform(&data_dir, &client, &action).await;
+        if jobs.send(Job::ActionResult(result)).await.is_err() {
+            warn!(
+                "Agent shut down before the result of action {:?} was reported",
+                action.id
+            );
+        }
+    });
+}
+
+/// Perform an action. [`ActionKind::Sync`] is handled by the check-in loop.
+pub async fn perform(data_dir: &Path, client: &Client, action: &Action) -> ActionResult {
+    let result = match action.kind {
+        ActionKind::Script => match &action.script {
+            Some(script) => {
+                let result = scripts::run_now(data_dir, script, &scripts::Limits::default()).await;
+                return ActionResult {
+                    id: action.id.clone(),
+                    success: result.is_success(),
+                    output: String::new(),
+                    script: Some(result),
+                };
+            }
+            None => Err("the action has no script".to_string()),
+        },
+        ActionKind::Logs => diagnostics::upload(data_dir, client, Some(&action.id)).await,
+        ActionKind::Restart => restart().await,
+        ActionKind::Sync => Ok(String::new()),
+        kind => Err(format!("{kind:?} is not supported by this agent")),
+    };
+
+    if let Err(err) = &result {
+        warn!("Action {:?} failed: {err}", action.id);
+    }
+    ActionResult {
+        id: action.id.clone(),
+        success: result.is_ok(),
+        output: result.unwrap_or_else(|err| err),
+        script: None,
+    }
+}
+
+/// Queue the result of an action to be reported and try to send it.
+pub async fn report(client: &Client, outbox: &mut Outbox, result: &ActionResult) {
+    match outbox.push(
+        &format!("action:{}", result.id),
+        "/agent/actions/result",
+        result,
+    ) {
+        Ok(()) => checkin::drain(client, outbox).await,
+        Err(err) => warn!("Error queuing result of action {:?}: {err}", result.id),
+    }
+}
+
+// The reboot is delayed so the result can be reported first
+async fn restart() -> Result<String, String> {
+    let output = Command::new("shutdown")
+        .args(["-r", "+1", "Restart requested by Mattrax"])
+        .output()
+        .await
+        .map_err(|err| format!("error running shutdown: {err}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "shutdown exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok("Restarting in 1 minute".into())
+}
+
+#[cfg(test)]
+mod test {
+    use mx_agent_api::CLIENT_CERT_HEADER;
+    use mx_policy::{Script, Shell};
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::Request;
+
+    use super::*;
+    use crate::{config::Config, identity::Identity};
+
+    fn client(server_url: &str) -> Client {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let certificate = rcgen::CertificateParams::new(vec!["device".into()])
+            .unwrap()
+            .self_signed(&key)
+            .unwrap();
+        Client::new(
+            &Config {
+                server_url: server_url.into(),
+                device_id: "device".into(),
+                channel: None,
+            },
+            &Identity {
+                key: key.serialize_pem(),
+                certificate: certificate.pem(),
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_handled() {
+        let mut handled = Handled::default();
+        assert!(handled.insert("a"));
+        assert!(!handled.insert("a"));
+
+        for i in 0..MAX_HANDLED {
+            handled.insert(&i.to_string());
+        }
+        assert_eq!(handled.ids.len(), MAX_HANDLED);
+        // The oldest id was forgotten
+        assert!(handled.insert("a"));
+    }
+
+    #[tokio::test]
+    // The handshake callback's error type is defined by tungstenite
+    #[allow(clippy::result_large_err)]
+    async fn test_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut has_cert = false;
+            let mut socket = tokio_tungstenite::accept_hdr_async(stream, |req: &Request, resp| {
+                has_cert = req.headers().contains_key(CLIENT_CERT_HEADER);
+                Ok(resp)
+            })
+            .await
+            .unwrap();
+
+            let action = ServerMessage::Action(Action {
+                id: "restart".into(),
+                kind: ActionKind::Restart,
+                script: None,
+            });
+            socket
+                .send(Message::Text(serde_json::to_string(&action).unwrap()))
+                .await
+                .unwrap();
+            let ack = loop {
+                match socket.next().await.unwrap().unwrap() {
+                    Message::Text(text) => {
+                        break serde_json::from_str::<AgentMessage>(&text).unwrap()
+                    }
+                    _ => continue,
+                }
+            };
+            socket.close(None).await.unwrap();
+            (has_cert, ack)
+        });
+
+        // Debug builds forward the certificate themselves over plain HTTP
+        let socket = client(&format!("http://{addr}"))
+            .websocket("/agent/ws")
+            .await
+            .unwrap();
+        let (jobs, mut received) = mpsc::channel(1);
+        let session = tokio::spawn(async move { session(socket, &jobs).await });
+
+        let Some(Job::Action(action)) = received.recv().await else {
+            panic!("expected an action");
+        };
+        assert_eq!(action.id, "restart");
+        let (has_cert, AgentMessage::Ack { id }) = server.await.unwrap();
+        assert!(has_cert);
+        assert_eq!(id, "restart");
+        assert_eq!(session.await.unwrap(), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_perform() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        // Nothing is listening so any request fails
+        let client = client("http://127.0.0.1:9");
+
+        let result = perform(
+            dir,
+            &client,
+            &Action {
+                id: "script".into(),
+                kind: ActionKind::Script,
+                script: Some(Script {
+                    shell: Shell::Bash,
+                    supported: None,
+                    trigger: None,
+                    run: "echo hello".into(),
+                }),
+            },
+        )
+        .await;
+        assert!(result.success);
+        assert_eq!(result.script.unwrap().stdout, "hello\n");
+
+        for kind in [ActionKind::Script, ActionKind::Wipe] {
+            let result = perform(
+                dir,
+                &client,
+                &Action {
+                    id: "fail".into(),
+                    kind,
+                    script: None,
+                },
+            )
+            .await;
+            assert!(!result.success);
+            assert!(!result.output.is_empty());
+        }
+    }
+}
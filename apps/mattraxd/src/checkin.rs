@@ -0,0 +1,147 @@
+//! A check-in fetches the policies assigned to the device and applies them.
+//!
+//! Reports are queued in the [`Outbox`] so they are delivered once the device is back online.
+
+use std::{path::Path, sync::LazyLock, time::Instant};
+
+use mx_agent_api::{Action, DeployedPolicy, Inventory, PolicyStatusReport};
+use mx_utils::telemetry::{self, Histogram, KeyValue};
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::{client::Client, inventory, linux, outbox::Outbox, scripts, status::Status};
+
+// Created on first use, which is after the meter provider has been installed
+static SYNC_DURATION: LazyLock<Histogram<f64>> = LazyLock::new(|| {
+    telemetry::meter()
+        .f64_histogram("mattraxd.sync.duration")
+        .with_unit("s")
+        .build()
+});
+
+pub struct Outcome {
+    /// The policies assigned to the device.
+    pub policies: Vec<DeployedPolicy>,
+    /// The status of every policy which had something to apply.
+    pub reports: Vec<PolicyStatusReport>,
+    /// Actions which weren't received over the WebSocket.
+    pub actions: Vec<Action>,
+}
+
+/// Check in and apply the assigned policies.
+pub async fn run(
+    data_dir: &Path,
+    client: &Client,
+    outbox: &mut Outbox,
+    event: scripts::Event,
+) -> Result<Outcome, String> {
+    // Every request made during the sync is part of the same trace
+    let span = info_span!("device_sync", ?event);
+    let start = Instant::now();
+    let result = sync(data_dir, client, outbox, event).instrument(span).await;
+
+    SYNC_DURATION.record(
+        start.elapsed().as_secs_f64(),
+        &[KeyValue::new("success", result.is_ok())],
+    );
+    result
+}
+
+async fn sync(
+    data_dir: &Path,
+    client: &Client,
+    outbox: &mut Outbox,
+    event: scripts::Event,
+) -> Result<Outcome, String> {
+    let result = client.checkin().await;
+    Status::record(data_dir, &result.as_ref().map(|_| ()).map_err(Clone::clone))?;
+    let resp = result?;
+    info!("Checked in with {} policies", resp.policies.len());
+
+    if event == scripts::Event::Startup {
+        queue_inventory(outbox).await?;
+    }
+
+    let reports = apply(data_dir, &resp.policies, event, false).await?;
+    for report in &reports {
+        // The server only keeps the latest status of each deploy
+        outbox.push(
+            &format!("policy-status:{}", report.deploy_id),
+            "/agent/policies/status",
+            &[report],
+        )?;
+    }
+
+    // We just reached the server so this is a good time to send anything queued while offline
+    drain(client, outbox).await;
+
+    Ok(Outcome {
+        policies: resp.policies,
+        reports,
+        actions: resp.actions,
+    })
+}
+
+/// Check how the device differs from the assigned policies without changing anything.
+///
+/// The policies are fetched without checking in, the Linux configuration is only checked for drift,
+/// scripts are skipped and nothing is reported to Mattrax.
+pub async fn dry_run(data_dir: &Path, client: &Client) -> Result<Vec<PolicyStatusReport>, String> {
+    let policies = client.policies().await?;
+    apply(data_dir, &policies, scripts::Event::Checkin, true).await
+}
+
+/// Apply the policies, returning the status of every policy which had something to apply.
+async fn apply(
+    data_dir: &Path,
+    policies: &[DeployedPolicy],
+    event: scripts::Event,
+    dry_run: bool,
+) -> Result<Vec<PolicyStatusReport>, String> {
+    let mut reports = Vec::new();
+    for policy in policies {
+        // The Linux configuration is applied first so scripts can depend on it
+        let linux = match policy.linux.clone() {
+            Some(config) => {
+                tokio::task::spawn_blocking(move || linux::Engine::system(dry_run).apply(&config))
+                    .await
+                    .map_err(|err| format!("error applying Linux configuration: {err}"))?
+            }
+            None => Vec::new(),
+        };
+
+        let scripts = if dry_run {
+            Vec::new()
+        } else {
+            scripts::run(data_dir, policy, event, &scripts::Limits::default()).await?
+        };
+
+        if !linux.is_empty() || !scripts.is_empty() {
+            reports.push(PolicyStatusReport {
+                deploy_id: policy.deploy_id.clone(),
+                scripts,
+                linux,
+            });
+        }
+    }
+
+    Ok(reports)
+}
+
+/// Collect the device's inventory and queue it to be reported.
+pub async fn queue_inventory(outbox: &mut Outbox) -> Result<Inventory, String> {
+    let inventory = tokio::task::spawn_blocking(inventory::collect)
+        .await
+        .map_err(|err| format!("error collecting inventory: {err}"))?;
+    outbox.push("inventory", "/agent/inventory", &inventory)?;
+    Ok(inventory)
+}
+
+/// Send any queued reports. Failures are retried on the next check-in.
+pub async fn drain(client: &Client, outbox: &mut Outbox) {
+    if outbox.len() > 0 {
+        match outbox.drain(client).await {
+            Ok(sent) => info!("Sent {sent} queued reports"),
+            Err(err) => warn!("Error sending queued reports, will retry: {err}"),
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{checkin, client::Client, ctl, outbox::Outbox, scripts::Event};
+
+#[derive(clap::Args)]
+#[command(about = "Check in with Mattrax and apply the assigned policies")]
+pub struct Command {
+    /// Show how the device differs from the Linux configuration without changing anything. Scripts are not run.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+impl Command {
+    pub async fn run(&self, data_dir: &Path) -> Result<(), String> {
+        let client = Client::load(data_dir)?;
+
+        if self.dry_run {
+            let reports = checkin::dry_run(data_dir, &client).await?;
+            for item in reports.iter().flat_map(|report| &report.linux) {
+                println!(
+                    "{:?} {:?} {}: {:?}{}",
+                    item.status,
+                    item.kind,
+                    item.id,
+                    item.drift,
+                    item.error
+                        .as_ref()
+                        .map(|err| format!(" ({err})"))
+                        .unwrap_or_default()
+                );
+            }
+            return Ok(());
+        }
+
+        // The daemon owns the outbox so it has to do the check-in
+        if ctl::is_running(data_dir) {
+            let result = ctl::call(data_dir, "checkin", Value::Null).await?;
+            println!("{}", serde_json::to_string_pretty(&result).unwrap());
+            return Ok(());
+        }
+
+        let mut outbox = Outbox::open(data_dir)?;
+        let outcome = checkin::run(data_dir, &client, &mut outbox, Event::Checkin).await?;
+        println!("Checked in with {} policies", outcome.policies.len());
+        Ok(())
+    }
+}
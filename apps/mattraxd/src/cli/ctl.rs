@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use clap::Subcommand;
+use serde_json::{json, Value};
+
+use crate::ctl;
+
+#[derive(clap::Args)]
+#[command(about = "Control the running mattraxd daemon")]
+pub struct Command {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Show the daemon's status
+    Status,
+    /// Check in with Mattrax now
+    Checkin,
+    /// List the policies assigned to the device
+    Policies,
+    /// Show recent log lines
+    Logs {
+        #[arg(short = 'n', long, default_value_t = 100)]
+        lines: usize,
+    },
+    /// Collect and report the device's inventory now
+    Inventory,
+    /// Show or change the log filter, in the same format as `RUST_LOG`
+    LogFilter { filter: Option<String> },
+}
+
+impl Command {
+    pub async fn run(&self, data_dir: &Path) -> Result<(), String> {
+        let (method, params) = match &self.command {
+            Commands::Status => ("status", Value::Null),
+            Commands::Checkin => ("checkin", Value::Null),
+            Commands::Policies => ("policies", Value::Null),
+            Commands::Logs { lines } => ("logs", json!({ "lines": lines })),
+            Commands::Inventory => ("inventory", Value::Null),
+            Commands::LogFilter { filter } => ("log_filter", json!({ "filter": filter })),
+        };
+
+        let result = ctl::call(data_dir, method, params).await?;
+        match result {
+            Value::Array(lines) if method == "logs" => {
+                lines
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .for_each(|line| println!("{line}"));
+            }
+            result => println!("{}", serde_json::to_string_pretty(&result).unwrap()),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,32 @@
+use std::path::{Path, PathBuf};
+
+use crate::{client::Client, diagnostics};
+
+#[derive(clap::Args)]
+#[command(about = "Collect a diagnostics bundle of the agent's logs, state and inventory")]
+pub struct Command {
+    /// Where to write the bundle
+    #[arg(short, long, default_value = "mattraxd-diagnostics.tar.gz")]
+    output: PathBuf,
+    /// Upload the bundle to Mattrax instead of writing it to a file
+    #[arg(long, conflicts_with = "output")]
+    upload: bool,
+}
+
+impl Command {
+    pub async fn run(&self, data_dir: &Path) -> Result<(), String> {
+        if self.upload {
+            let client = Client::load(data_dir)?;
+            let id = diagnostics::upload(data_dir, &client, None).await?;
+            println!("Uploaded diagnostics {id}");
+            return Ok(());
+        }
+
+        let (data_dir, output) = (data_dir.to_path_buf(), self.output.clone());
+        tokio::task::spawn_blocking(move || diagnostics::collect(&data_dir, &output))
+            .await
+            .map_err(|err| format!("error collecting diagnostics: {err}"))??;
+        println!("Wrote diagnostics to {:?}", self.output);
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use mx_agent_api::EnrollRequest;
+use tracing::info;
+
+use crate::{
+    client::Client,
+    config::Config,
+    identity::{self, Identity},
+    inventory,
+};
+
+#[derive(clap::Args)]
+#[command(about = "Enroll the device with Mattrax")]
+pub struct Command {
+    /// The Mattrax server to enroll with. Eg. `https://cloud.mattrax.app`
+    #[arg(long)]
+    server: String,
+    /// The enrollment token from the Mattrax dashboard
+    #[arg(long)]
+    token: String,
+    /// Enroll again even if the device is already enrolled
+    #[arg(long)]
+    force: bool,
+}
+
+impl Command {
+    pub async fn run(&self, data_dir: &Path) -> Result<(), String> {
+        if !self.force && Config::load(data_dir)?.is_some() {
+            return Err("The device is already enrolled. Use `--force` to enroll again.".into());
+        }
+
+        let inventory = inventory::collect();
+        let serial_number = inventory::serial_number(&inventory)
+            .ok_or("Unable to determine a unique hardware identifier for the device")?;
+        let name = inventory.hostname.clone();
+        let server_url = self.server.trim_end_matches('/').to_string();
+
+        // The existing identity is kept until the server issues a certificate, so a failed enrollment doesn't break it
+        let key = identity::generate_key()?;
+        let csr = identity::certificate_signing_request(&key, &name)?;
+
+        info!("Enrolling with {server_url:?}");
+        let resp = Client::enroll(
+            &server_url,
+            &EnrollRequest {
+                token: self.token.clone(),
+                csr,
+                name,
+                serial_number,
+                manufacturer: inventory.hardware.manufacturer.clone(),
+                model: inventory.hardware.model.clone(),
+                os_version: inventory.os.pretty_name.clone(),
+            },
+        )
+        .await?;
+
+        identity::save(data_dir, &key, &resp.certificate)?;
+        let config = Config {
+            server_url,
+            device_id: resp.device_id,
+            channel: None,
+        };
+        config.save(data_dir)?;
+
+        // Ensure the server accepts our new identity
+        let identity =
+            Identity::load(data_dir)?.ok_or("Device identity missing after enrollment")?;
+        let client = Client::new(&config, &identity)?;
+        client.checkin().await?;
+        client.report_inventory(&inventory).await?;
+
+        info!("Successfully enrolled as device {:?}", config.device_id);
+        Ok(())
+    }
+}
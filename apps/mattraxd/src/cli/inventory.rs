@@ -0,0 +1,69 @@
+use crate::inventory;
+
+#[derive(clap::Args)]
+#[command(about = "Print the device's inventory")]
+pub struct Command {
+    /// Output the full inventory document as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+impl Command {
+    pub fn run(&self) {
+        let inventory = inventory::collect();
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&inventory).unwrap());
+            return;
+        }
+
+        println!("Hostname: {}", inventory.hostname);
+        println!(
+            "OS: {} ({})",
+            inventory.os.pretty_name.as_deref().unwrap_or("unknown"),
+            inventory.os.kernel.as_deref().unwrap_or("unknown kernel"),
+        );
+        let hardware = [&inventory.hardware.manufacturer, &inventory.hardware.model]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        println!(
+            "Hardware: {} (serial: {})",
+            if hardware.is_empty() {
+                "unknown".into()
+            } else {
+                hardware.join(" ")
+            },
+            inventory::serial_number(&inventory)
+                .as_deref()
+                .unwrap_or("unknown"),
+        );
+        println!(
+            "CPU: {} ({} cores, {} threads)",
+            inventory.cpu.model.as_deref().unwrap_or("unknown"),
+            inventory.cpu.physical_cores,
+            inventory.cpu.logical_cores,
+        );
+        println!("Memory: {} MiB", inventory.memory.total_bytes / 1024 / 1024);
+        for disk in &inventory.disks {
+            println!(
+                "Disk: {} on {} ({}): {} / {} MiB free",
+                disk.device,
+                disk.mount_point,
+                disk.filesystem,
+                disk.available_bytes / 1024 / 1024,
+                disk.total_bytes / 1024 / 1024,
+            );
+        }
+        for interface in &inventory.network_interfaces {
+            println!(
+                "Network: {} ({}) {}",
+                interface.name,
+                interface.state.as_deref().unwrap_or("unknown"),
+                interface.addresses.join(", "),
+            );
+        }
+        println!("Packages: {}", inventory.packages.len());
+    }
+}
@@ -2,7 +2,15 @@ use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 
+mod checkin;
+mod ctl;
+mod diagnostics;
+mod enroll;
+mod inventory;
+mod serve;
+mod status;
 mod test;
+mod update;
 
 #[derive(Parser)]
 #[command(name = env!("CARGO_PKG_NAME"))]
@@ -24,5 +32,13 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 pub enum Commands {
+    Checkin(checkin::Command),
+    Ctl(ctl::Command),
+    Diagnostics(diagnostics::Command),
+    Enroll(enroll::Command),
+    Inventory(inventory::Command),
+    Serve(serve::Command),
+    Status(status::Command),
     Test(test::Command),
+    Update(update::Command),
 }
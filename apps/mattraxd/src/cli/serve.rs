@@ -0,0 +1,227 @@
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use mx_agent_api::{ActionKind, ActionResult};
+use mx_utils::file_logger::LogFilter;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::{
+    actions, checkin,
+    client::Client,
+    config::Config,
+    ctl::{self, AppliedPolicy, Job},
+    outbox::Outbox,
+    scripts::Event,
+    update,
+};
+
+// The first retry after a failed check-in. This doubles on each failure up to the check-in interval.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(30);
+
+const UPDATE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(clap::Args)]
+#[command(about = "Run the agent, checking in with Mattrax periodically")]
+pub struct Command {
+    /// Minutes between check-ins
+    #[arg(long, default_value_t = 15)]
+    interval: u64,
+}
+
+impl Command {
+    pub async fn run(&self, data_dir: &Path, log_filter: Option<LogFilter>) -> Result<(), String> {
+        let started = tokio::time::Instant::now();
+        if let Some(exe) = update::startup(data_dir)? {
+            return Err(update::restart(&exe));
+        }
+        // A newly installed version is rolled back if it doesn't check in soon after starting
+        let mut health_check = update::health_check_deadline(data_dir, started);
+
+        let config = Config::load(data_dir)?
+            .ok_or("The device is not enrolled. Run `mattraxd enroll` first.")?;
+        let client = Client::load(data_dir)?;
+        let mut outbox = Outbox::open(data_dir)?;
+        let interval = Duration::from_secs(self.interval.max(1) * 60);
+        let mut event = Event::Startup;
+        let mut failures = 0;
+        let mut last_update_check = None::<Instant>;
+
+        let shared = Arc::new(ctl::Shared {
+            log_filter,
+            ..Default::default()
+        });
+        let (jobs_tx, mut jobs) = mpsc::channel(8);
+        ctl::listen(data_dir, shared.clone(), jobs_tx.clone())?;
+        actions::listen(client.clone(), jobs_tx.clone());
+
+        let mut handled = actions::Handled::default();
+        // Sync actions waiting on the next check-in
+        let mut syncs = Vec::new();
+
+        info!("Starting agent {}", env!("CARGO_PKG_VERSION"));
+        let mut requested = None;
+        loop {
+            let result = checkin::run(data_dir, &client, &mut outbox, event).await;
+            let delay = match &result {
+                Ok(outcome) => {
+                    failures = 0;
+                    event = Event::Checkin;
+
+                    *shared.policies.lock().unwrap() = outcome
+                        .policies
+                        .iter()
+                        .map(|policy| AppliedPolicy {
+                            policy_id: policy.policy_id.clone(),
+                            deploy_id: policy.deploy_id.clone(),
+                            name: policy.name.clone(),
+                            scripts: policy.scripts.len(),
+                            linux_items: policy.linux.as_ref().map_or(0, |linux| {
+                                linux.files.len()
+                                    + linux.services.len()
+                                    + linux.sysctl.len()
+                                    + linux.packages.len()
+                            }),
+                            success: outcome
+                                .reports
+                                .iter()
+                                .find(|report| report.deploy_id == policy.deploy_id)
+                                .map(|report| report.is_success()),
+                        })
+                        .collect();
+
+                    // Checking in proves the new version works
+                    if health_check.take().is_some() {
+                        update::confirm(data_dir)?;
+                    }
+
+                    if update::is_supported()
+                        && last_update_check.is_none_or(|at| at.elapsed() >= UPDATE_INTERVAL)
+                    {
+                        last_update_check = Some(Instant::now());
+                        match update::check(data_dir, &client, &config).await {
+                            Ok(Some(installed)) => {
+                                info!("Restarting into mattraxd {}", installed.version);
+                                return Err(update::restart(&installed.exe));
+                            }
+                            Ok(None) => {}
+                            Err(err) => warn!("Error checking for updates: {err}"),
+                        }
+                    }
+
+                    interval
+                }
+                Err(err) => {
+                    failures += 1;
+                    let delay = backoff(failures).min(interval);
+                    error!("Error checking in, retrying in {}s: {err}", delay.as_secs());
+                    delay
+                }
+            };
+
+            // Reply to `mattraxd ctl checkin` if that's what triggered this check-in
+            if let Some(reply) = requested.take() {
+                reply_checkin(reply, &result);
+            }
+
+            for id in syncs.drain(..) {
+                let report = ActionResult {
+                    id,
+                    success: result.is_ok(),
+                    output: match &result {
+                        Ok(outcome) => {
+                            format!("Checked in with {} policies", outcome.policies.len())
+                        }
+                        Err(err) => err.clone(),
+                    },
+                    script: None,
+                };
+                actions::report(&client, &mut outbox, &report).await;
+            }
+
+            // Actions which were missed while the WebSocket was disconnected
+            if let Ok(outcome) = result {
+                for action in outcome.actions {
+                    if handled.insert(&action.id) {
+                        // The check-in we just did satisfies a sync
+                        actions::spawn(
+                            data_dir.to_path_buf(),
+                            client.clone(),
+                            action,
+                            jobs_tx.clone(),
+                        );
+                    }
+                }
+            }
+
+            // Wait for the next check-in, handling requests from `mattraxd ctl` in the meantime
+            let deadline = tokio::time::Instant::now() + delay;
+            requested = loop {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(deadline) => break None,
+                    _ = tokio::time::sleep_until(health_check.unwrap_or(deadline)), if health_check.is_some() => {
+                        let exe = update::fail_health_check(data_dir)?;
+                        return Err(update::restart(&exe));
+                    }
+                    Some(job) = jobs.recv() => match job {
+                        Job::Checkin(reply) => break Some(reply),
+                        Job::Inventory(reply) => {
+                            let result = checkin::queue_inventory(&mut outbox).await;
+                            if result.is_ok() {
+                                checkin::drain(&client, &mut outbox).await;
+                            }
+                            let _ = reply.send(result.map(|inventory| json!(inventory)));
+                        }
+                        Job::Action(action) => {
+                            if !handled.insert(&action.id) {
+                                continue;
+                            }
+
+                            if action.kind == ActionKind::Sync {
+                                syncs.push(action.id);
+                                break None;
+                            }
+                            actions::spawn(
+                                data_dir.to_path_buf(),
+                                client.clone(),
+                                action,
+                                jobs_tx.clone(),
+                            );
+                        }
+                        Job::ActionResult(result) => {
+                            actions::report(&client, &mut outbox, &result).await;
+                        }
+                    },
+                }
+            };
+        }
+    }
+}
+
+fn reply_checkin(
+    reply: tokio::sync::oneshot::Sender<Result<serde_json::Value, String>>,
+    result: &Result<checkin::Outcome, String>,
+) {
+    let _ = reply.send(match result {
+        Ok(outcome) => Ok(json!({
+            "policies": outcome.policies.len(),
+            "reports": outcome.reports,
+        })),
+        Err(err) => Err(err.clone()),
+    });
+}
+
+/// Exponential backoff with jitter so a fleet of devices doesn't retry in lockstep after an outage.
+fn backoff(failures: u32) -> Duration {
+    let delay = INITIAL_BACKOFF.saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)));
+    let jitter = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_millis() as u64
+        % (delay.as_millis() as u64 / 4).max(1);
+    delay + Duration::from_millis(jitter)
+}
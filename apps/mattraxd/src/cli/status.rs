@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use crate::{config::Config, outbox::Outbox, status};
+
+#[derive(clap::Args)]
+#[command(about = "Show the agent's enrollment and connection status")]
+pub struct Command {}
+
+impl Command {
+    pub fn run(&self, data_dir: &Path) -> Result<(), String> {
+        let Some(config) = Config::load(data_dir)? else {
+            println!("Not enrolled");
+            return Ok(());
+        };
+        let status = status::Status::load(data_dir)?;
+        let pending = Outbox::pending(data_dir)?;
+        let now = status::now();
+
+        println!("Server: {}", config.server_url);
+        println!("Device: {}", config.device_id);
+        println!(
+            "Last check-in: {}",
+            status
+                .last_checkin
+                .map(|at| ago(now, at))
+                .unwrap_or_else(|| "never".into())
+        );
+        if let Some(err) = &status.last_error {
+            println!(
+                "Last error: {err} ({})",
+                status
+                    .last_attempt
+                    .map(|at| ago(now, at))
+                    .unwrap_or_default()
+            );
+        }
+        println!(
+            "Queued reports: {}{}",
+            pending.len(),
+            pending
+                .iter()
+                .map(|e| e.queued_at)
+                .min()
+                .map(|at| format!(" (oldest {})", ago(now, at)))
+                .unwrap_or_default()
+        );
+
+        Ok(())
+    }
+}
+
+fn ago(now: u64, at: u64) -> String {
+    match now.saturating_sub(at) {
+        secs @ 0..=59 => format!("{secs}s ago"),
+        secs @ 60..=3599 => format!("{}m ago", secs / 60),
+        secs @ 3600..=86399 => format!("{}h ago", secs / 3600),
+        secs => format!("{}d ago", secs / 86400),
+    }
+}
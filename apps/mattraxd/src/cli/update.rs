@@ -0,0 +1,42 @@
+use std::path::Path;
+
+use crate::{client::Client, config::Config, update};
+
+#[derive(clap::Args)]
+#[command(about = "Update mattraxd to the latest release")]
+pub struct Command {
+    /// Switch to a different release channel. Eg. `stable` or `beta`
+    #[arg(long)]
+    channel: Option<String>,
+    /// Restore the version from before the last update
+    #[arg(long, conflicts_with = "channel")]
+    rollback: bool,
+}
+
+impl Command {
+    pub async fn run(&self, data_dir: &Path) -> Result<(), String> {
+        if self.rollback {
+            update::rollback(data_dir)?;
+            println!("Rolled back. Restart mattraxd to use the previous version.");
+            return Ok(());
+        }
+
+        let mut config = Config::load(data_dir)?
+            .ok_or("The device is not enrolled. Run `mattraxd enroll` first.")?;
+        if let Some(channel) = &self.channel {
+            config.channel = Some(channel.clone());
+            config.save(data_dir)?;
+        }
+
+        let client = Client::load(data_dir)?;
+        match update::check(data_dir, &client, &config).await? {
+            Some(installed) => println!(
+                "Installed mattraxd {}. Restart mattraxd to use it.",
+                installed.version
+            ),
+            None => println!("mattraxd is up to date"),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+//! HTTP client for the agent API in Mattrax Cloud.
+
+use std::{path::Path, sync::Arc};
+
+use mx_agent_api::{
+    CheckinResponse, DeployedPolicy, DiagnosticsStatus, DiagnosticsUpload, EnrollRequest,
+    EnrollResponse, Inventory, SignedManifest, UpdateRequest, CLIENT_CERT_HEADER,
+    IDEMPOTENCY_KEY_HEADER, PROXY_SECRET_HEADER,
+};
+use mx_utils::telemetry;
+use reqwest::{header::HeaderMap, Method, RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    tungstenite::{client::IntoClientRequest, http::HeaderValue},
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+use tracing::Span;
+
+use crate::{config::Config, identity::Identity};
+
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    server_url: String,
+    /// The headers the mTLS proxy would add, see [`Client::new`].
+    proxy_headers: Vec<(&'static str, String)>,
+    tls: Arc<rustls::ClientConfig>,
+}
+
+impl Client {
+    /// Create a client which authenticates using the device's identity.
+    pub fn new(config: &Config, identity: &Identity) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .identity(identity.to_reqwest()?)
+            .build()
+            .map_err(|err| format!("error creating HTTP client: {err}"))?;
+
+        // Plain HTTP is only used for local development against the Axum server in `apps/cloud`.
+        // There is no TLS to carry the certificate so we forward it the same way the mTLS proxy does,
+        // using the proxy secret the server was started with (`MATTRAXD_PROXY_SECRET`).
+        let mut proxy_headers = Vec::new();
+        if cfg!(debug_assertions) && config.server_url.starts_with("http://") {
+            proxy_headers.push((CLIENT_CERT_HEADER, identity.certificate_der_base64()));
+            if let Ok(secret) = std::env::var("MATTRAXD_PROXY_SECRET") {
+                proxy_headers.push((PROXY_SECRET_HEADER, secret));
+            }
+        }
+
+        Ok(Self {
+            http,
+            server_url: config.server_url.clone(),
+            proxy_headers,
+            tls: Arc::new(identity.to_rustls()?),
+        })
+    }
+
+    /// Load the client for the device enrolled in the data directory.
+    pub fn load(data_dir: &Path) -> Result<Self, String> {
+        let not_enrolled =
+            || "The device is not enrolled. Run `mattraxd enroll` first.".to_string();
+        let config = Config::load(data_dir)?.ok_or_else(not_enrolled)?;
+        let identity = Identity::load(data_dir)?.ok_or_else(not_enrolled)?;
+        Self::new(&config, &identity)
+    }
+
+    /// Enroll the device. This is the only request which doesn't require the device's identity.
+    pub async fn enroll(server_url: &str, req: &EnrollRequest) -> Result<EnrollResponse, String> {
+        let http = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .build()
+            .map_err(|err| format!("error creating HTTP client: {err}"))?;
+
+        decode(
+            http.post(format!("{server_url}/agent/enroll"))
+                .json(req)
+                .send()
+                .await,
+        )
+        .await
+    }
+
+    pub async fn checkin(&self) -> Result<CheckinResponse, String> {
+        self.post("/agent/checkin", &()).await
+    }
+
+    /// Get the assigned policies without checking in.
+    pub async fn policies(&self) -> Result<Vec<DeployedPolicy>, String> {
+        decode(self.request(Method::GET, "/agent/policies").send().await).await
+    }
+
+    pub async fn report_inventory(&self, inventory: &Inventory) -> Result<(), String> {
+        self.post::<_, ()>("/agent/inventory", inventory).await
+    }
+
+    pub async fn release_manifest(&self, channel: &str) -> Result<Option<SignedManifest>, String> {
+        self.post(
+            "/agent/update",
+            &UpdateRequest {
+                channel: channel.to_string(),
+            },
+        )
+        .await
+    }
+
+    pub async fn start_diagnostics(
+        &self,
+        upload: &DiagnosticsUpload,
+    ) -> Result<DiagnosticsStatus, String> {
+        self.post("/agent/diagnostics", upload).await
+    }
+
+    pub async fn diagnostics_status(&self, id: &str) -> Result<DiagnosticsStatus, String> {
+        decode(
+            self.request(Method::GET, &format!("/agent/diagnostics/{id}"))
+                .send()
+                .await,
+        )
+        .await
+    }
+
+    pub async fn upload_diagnostics_chunk(
+        &self,
+        id: &str,
+        offset: u64,
+        chunk: Vec<u8>,
+    ) -> Result<DiagnosticsStatus, String> {
+        decode(
+            self.request(Method::PUT, &format!("/agent/diagnostics/{id}/{offset}"))
+                .header("content-type", "application/octet-stream")
+                .body(chunk)
+                .send()
+                .await,
+        )
+        .await
+    }
+
+    /// Send a queued request from the outbox. Returns `Ok(false)` if the server rejected the request, so retrying won't help.
+    ///
+    /// Retries must use the same `idempotency_key` so the server doesn't apply the request twice.
+    pub async fn send(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+        idempotency_key: &str,
+    ) -> Result<bool, String> {
+        let resp = self
+            .request(Method::POST, path)
+            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key)
+            .json(body)
+            .send()
+            .await
+            .map_err(|err| format!("error sending request: {err}"))?;
+        let status = resp.status();
+        match status {
+            // Authentication and rate limiting errors can resolve themselves
+            StatusCode::UNAUTHORIZED
+            | StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS => Err(format!("request failed with status {status}")),
+            _ if status.is_client_error() => Ok(false),
+            _ if !status.is_success() => Err(format!("request failed with status {status}")),
+            _ => Ok(true),
+        }
+    }
+
+    /// Open a WebSocket to the server, authenticated the same way as other requests.
+    pub async fn websocket(
+        &self,
+        path: &str,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, String> {
+        let url = match self.server_url.split_once("://") {
+            Some(("https", rest)) => format!("wss://{rest}{path}"),
+            Some(("http", rest)) => format!("ws://{rest}{path}"),
+            _ => return Err(format!("invalid server URL {:?}", self.server_url)),
+        };
+
+        let mut req = url
+            .into_client_request()
+            .map_err(|err| format!("error creating WebSocket request: {err}"))?;
+        req.headers_mut()
+            .insert("user-agent", HeaderValue::from_static(USER_AGENT));
+        telemetry::inject(&Span::current(), req.headers_mut());
+        for (name, value) in &self.proxy_headers {
+            req.headers_mut().insert(
+                *name,
+                HeaderValue::from_str(value)
+                    .map_err(|err| format!("invalid {name} header: {err}"))?,
+            );
+        }
+
+        let (stream, _) = tokio_tungstenite::connect_async_tls_with_config(
+            req,
+            None,
+            false,
+            Some(Connector::Rustls(self.tls.clone())),
+        )
+        .await
+        .map_err(|err| format!("error connecting WebSocket: {err}"))?;
+        Ok(stream)
+    }
+
+    async fn post<T: Serialize + ?Sized, R: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &T,
+    ) -> Result<R, String> {
+        decode(self.request(Method::POST, path).json(body).send().await).await
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut headers = HeaderMap::new();
+        telemetry::inject(&Span::current(), &mut headers);
+        for (name, value) in &self.proxy_headers {
+            if let Ok(value) = value.parse() {
+                headers.insert(*name, value);
+            }
+        }
+        self.http
+            .request(method, format!("{}{path}", self.server_url))
+            .headers(headers)
+    }
+}
+
+async fn decode<R: DeserializeOwned>(
+    resp: Result<reqwest::Response, reqwest::Error>,
+) -> Result<R, String> {
+    let resp = resp.map_err(|err| format!("error sending request: {err}"))?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        let body = resp.text().await.unwrap_or_default();
+        return Err(format!("request failed with status {status}: {body}"));
+    }
+
+    let body = resp
+        .bytes()
+        .await
+        .map_err(|err| format!("error reading response: {err}"))?;
+
+    // Endpoints with nothing to return respond with `204 No Content`
+    serde_json::from_slice(if body.is_empty() { b"null" } else { &body })
+        .map_err(|err| format!("error decoding response: {err}"))
+}
@@ -0,0 +1,43 @@
+//! Configuration saved to the data directory when the device enrolls.
+
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::file;
+
+const FILE: &str = "config.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    /// The Mattrax server the device is enrolled with. Eg. `https://cloud.mattrax.app`
+    pub server_url: String,
+    pub device_id: String,
+    /// The release channel for agent updates. Defaults to `stable`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+}
+
+impl Config {
+    /// Load the config from the data directory. Returns `None` if the device hasn't been enrolled.
+    pub fn load(data_dir: &Path) -> Result<Option<Self>, String> {
+        let path = data_dir.join(FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents =
+            fs::read_to_string(&path).map_err(|err| format!("error reading {path:?}: {err}"))?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .map_err(|err| format!("error decoding {path:?}: {err}"))
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<(), String> {
+        let path = data_dir.join(FILE);
+        fs::create_dir_all(data_dir)
+            .map_err(|err| format!("error creating {data_dir:?}: {err}"))?;
+        file::write_atomic(&path, serde_json::to_string_pretty(self).unwrap())
+    }
+}
@@ -0,0 +1,196 @@
+//! A local control API for the agent daemon, used by `mattraxd ctl`.
+//!
+//! The daemon listens on a Unix socket in the data directory and speaks newline delimited JSON-RPC 2.0.
+//! Only root or the user running the daemon can connect, enforced by both the socket's permissions and the peer's credentials.
+
+use std::{path::Path, sync::Mutex, time::Duration};
+
+use mx_agent_api::{Action, ActionResult};
+use mx_utils::file_logger::LogFilter;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+    sync::oneshot,
+};
+
+mod server;
+
+pub use server::listen;
+
+const SOCKET: &str = "mattraxd.sock";
+
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+const SERVER_ERROR: i32 = -32000;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Response {
+    jsonrpc: String,
+    id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<Error>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Error {
+    code: i32,
+    message: String,
+}
+
+/// Work which must be done by the check-in loop as it owns the outbox.
+pub enum Job {
+    Checkin(oneshot::Sender<Result<Value, String>>),
+    Inventory(oneshot::Sender<Result<Value, String>>),
+    /// An action received over the WebSocket. Its result is reported through the outbox.
+    Action(Action),
+    /// The result of an action performed in the background, to be reported through the outbox.
+    ActionResult(ActionResult),
+}
+
+/// State shared between the check-in loop and the control socket.
+#[derive(Default)]
+pub struct Shared {
+    pub policies: Mutex<Vec<AppliedPolicy>>,
+    /// Not set if the daemon isn't logging to files.
+    pub log_filter: Option<LogFilter>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppliedPolicy {
+    pub policy_id: String,
+    pub deploy_id: String,
+    pub name: String,
+    pub scripts: usize,
+    pub linux_items: usize,
+    /// Whether the policy applied successfully during the last check-in. `None` if there was nothing to do.
+    pub success: Option<bool>,
+}
+
+/// Check if the daemon is running by connecting to its socket.
+pub fn is_running(data_dir: &Path) -> bool {
+    std::os::unix::net::UnixStream::connect(data_dir.join(SOCKET)).is_ok()
+}
+
+/// Call a method on the running daemon.
+pub async fn call(data_dir: &Path, method: &str, params: Value) -> Result<Value, String> {
+    // Check-ins and inventory wait for the check-in loop, which may be busy running scripts, so they get longer
+    let timeout = match method {
+        "checkin" | "inventory" => Duration::from_secs(15 * 60),
+        _ => Duration::from_secs(30),
+    };
+    tokio::time::timeout(timeout, request(data_dir, method, params))
+        .await
+        .map_err(|_| format!("mattraxd didn't respond to {method:?} within {timeout:?}"))?
+}
+
+async fn request(data_dir: &Path, method: &str, params: Value) -> Result<Value, String> {
+    let path = data_dir.join(SOCKET);
+    let stream = UnixStream::connect(&path)
+        .await
+        .map_err(|err| format!("error connecting to {path:?}, is mattraxd running? {err}"))?;
+    let (reader, mut writer) = stream.into_split();
+
+    let mut req = serde_json::to_vec(&Request {
+        jsonrpc: "2.0".into(),
+        id: 1.into(),
+        method: method.into(),
+        params,
+    })
+    .unwrap();
+    req.push(b'\n');
+    writer
+        .write_all(&req)
+        .await
+        .map_err(|err| format!("error sending request: {err}"))?;
+
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await
+        .map_err(|err| format!("error reading response: {err}"))?
+        .ok_or("mattraxd closed the connection")?;
+    let resp: Response =
+        serde_json::from_str(&line).map_err(|err| format!("error decoding response: {err}"))?;
+
+    match resp.error {
+        Some(err) => Err(err.message),
+        None => Ok(resp.result.unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_call() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let shared = Arc::new(Shared::default());
+        shared.policies.lock().unwrap().push(AppliedPolicy {
+            policy_id: "policy".into(),
+            deploy_id: "deploy".into(),
+            name: "Example".into(),
+            scripts: 1,
+            linux_items: 0,
+            success: Some(true),
+        });
+        let (tx, mut rx) = mpsc::channel(1);
+        assert!(!is_running(dir));
+        listen(dir, shared, tx).unwrap();
+        assert!(is_running(dir));
+        tokio::spawn(async move {
+            while let Some(job) = rx.recv().await {
+                if let Job::Checkin(reply) = job {
+                    let _ = reply.send(Ok(json!({ "policies": 1 })));
+                }
+            }
+        });
+
+        let policies = call(dir, "policies", Value::Null).await.unwrap();
+        assert_eq!(policies[0]["policyId"], "policy");
+        assert_eq!(
+            call(dir, "checkin", Value::Null).await.unwrap(),
+            json!({ "policies": 1 })
+        );
+        assert!(call(dir, "unknown", Value::Null).await.is_err());
+        assert!(call(dir, "logs", json!({ "lines": "ten" })).await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_call_timeout() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        // A daemon which accepts the connection but never responds
+        let listener = tokio::net::UnixListener::bind(dir.join(SOCKET)).unwrap();
+        tokio::spawn(async move {
+            let _stream = listener.accept().await.unwrap();
+            std::future::pending::<()>().await
+        });
+
+        let err = call(dir, "status", Value::Null).await.unwrap_err();
+        assert_eq!(err, "mattraxd didn't respond to \"status\" within 30s");
+    }
+}
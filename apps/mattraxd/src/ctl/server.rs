@@ -0,0 +1,288 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use nix::unistd::geteuid;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::{mpsc, oneshot},
+};
+use tracing::{debug, info, warn};
+
+use super::{
+    Error, Job, Request, Response, Shared, INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR,
+    SERVER_ERROR, SOCKET,
+};
+use crate::{config::Config, outbox::Outbox, status::Status, update};
+
+const DEFAULT_LOG_LINES: usize = 100;
+const MAX_LOG_LINES: usize = 5000;
+const TAIL_CHUNK: u64 = 64 * 1024;
+
+/// Start listening on the control socket.
+pub fn listen(data_dir: &Path, shared: Arc<Shared>, jobs: mpsc::Sender<Job>) -> Result<(), String> {
+    let path = data_dir.join(SOCKET);
+    // Left behind if the daemon didn't shut down cleanly
+    let _ = fs::remove_file(&path);
+
+    let listener =
+        UnixListener::bind(&path).map_err(|err| format!("error binding {path:?}: {err}"))?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))
+        .map_err(|err| format!("error setting permissions of {path:?}: {err}"))?;
+
+    let data_dir = data_dir.to_path_buf();
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle(
+                        stream,
+                        data_dir.clone(),
+                        shared.clone(),
+                        jobs.clone(),
+                    ));
+                }
+                Err(err) => warn!("Error accepting control connection: {err}"),
+            }
+        }
+    });
+
+    Ok(())
+}
+
+async fn handle(
+    stream: UnixStream,
+    data_dir: PathBuf,
+    shared: Arc<Shared>,
+    jobs: mpsc::Sender<Job>,
+) {
+    // The socket's permissions should already prevent this, but they can be changed
+    match stream.peer_cred() {
+        Ok(cred) if cred.uid() == 0 || cred.uid() == geteuid().as_raw() => {
+            debug!(
+                "Control connection from uid {} (pid {:?})",
+                cred.uid(),
+                cred.pid()
+            );
+        }
+        Ok(cred) => {
+            warn!("Rejected control connection from uid {}", cred.uid());
+            return;
+        }
+        Err(err) => {
+            warn!("Error getting control connection credentials: {err}");
+            return;
+        }
+    }
+
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let resp = match serde_json::from_str::<Request>(&line) {
+            Ok(req) => {
+                info!("Control request {:?}", req.method);
+                let result = dispatch(&req.method, req.params, &data_dir, &shared, &jobs).await;
+                Response {
+                    jsonrpc: "2.0".into(),
+                    id: req.id,
+                    result: result.as_ref().ok().cloned(),
+                    error: result.err(),
+                }
+            }
+            Err(err) => Response {
+                jsonrpc: "2.0".into(),
+                id: Value::Null,
+                result: None,
+                error: Some(Error {
+                    code: PARSE_ERROR,
+                    message: format!("invalid request: {err}"),
+                }),
+            },
+        };
+
+        let mut resp = serde_json::to_vec(&resp).unwrap();
+        resp.push(b'\n');
+        if writer.write_all(&resp).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn dispatch(
+    method: &str,
+    params: Value,
+    data_dir: &Path,
+    shared: &Shared,
+    jobs: &mpsc::Sender<Job>,
+) -> Result<Value, Error> {
+    let server_error = |message: String| Error {
+        code: SERVER_ERROR,
+        message,
+    };
+
+    match method {
+        "status" => status(data_dir).map_err(server_error),
+        "policies" => Ok(json!(*shared.policies.lock().unwrap())),
+        "logs" => {
+            #[derive(Deserialize)]
+            struct Params {
+                lines: Option<usize>,
+            }
+            let params = match params {
+                Value::Null => Params { lines: None },
+                params => serde_json::from_value::<Params>(params).map_err(|err| Error {
+                    code: INVALID_PARAMS,
+                    message: format!("invalid params: {err}"),
+                })?,
+            };
+
+            logs(
+                data_dir,
+                params.lines.unwrap_or(DEFAULT_LOG_LINES).min(MAX_LOG_LINES),
+            )
+            .map(Value::from)
+            .map_err(server_error)
+        }
+        "log_filter" => {
+            #[derive(Deserialize)]
+            struct Params {
+                filter: Option<String>,
+            }
+            let params = match params {
+                Value::Null => Params { filter: None },
+                params => serde_json::from_value::<Params>(params).map_err(|err| Error {
+                    code: INVALID_PARAMS,
+                    message: format!("invalid params: {err}"),
+                })?,
+            };
+
+            let log_filter = shared
+                .log_filter
+                .as_ref()
+                .ok_or_else(|| server_error("the daemon isn't logging to files".into()))?;
+            if let Some(filter) = &params.filter {
+                log_filter.reload(filter).map_err(|message| Error {
+                    code: INVALID_PARAMS,
+                    message,
+                })?;
+                info!("Log filter changed to {filter:?}");
+            }
+            Ok(json!({ "filter": log_filter.current() }))
+        }
+        "checkin" | "inventory" => {
+            let (tx, rx) = oneshot::channel();
+            let job = match method {
+                "checkin" => Job::Checkin(tx),
+                _ => Job::Inventory(tx),
+            };
+            jobs.send(job)
+                .await
+                .map_err(|_| server_error("the agent is shutting down".into()))?;
+            rx.await
+                .map_err(|_| server_error("the agent is shutting down".into()))?
+                .map_err(server_error)
+        }
+        _ => Err(Error {
+            code: METHOD_NOT_FOUND,
+            message: format!("unknown method {method:?}"),
+        }),
+    }
+}
+
+fn status(data_dir: &Path) -> Result<Value, String> {
+    let config = Config::load(data_dir)?;
+    let status = Status::load(data_dir)?;
+
+    Ok(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "deviceId": config.as_ref().map(|c| &c.device_id),
+        "serverUrl": config.as_ref().map(|c| &c.server_url),
+        "lastCheckin": status.last_checkin,
+        "lastError": status.last_error,
+        "queuedReports": Outbox::pending(data_dir)?.len(),
+        "updatePending": update::is_pending(data_dir),
+    }))
+}
+
+/// The last lines of the newest log file.
+fn logs(data_dir: &Path, lines: usize) -> Result<Vec<String>, String> {
+    let dir = data_dir.join("logs");
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(vec![]);
+    };
+
+    let Some(newest) = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "log"))
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+    else {
+        return Ok(vec![]);
+    };
+
+    tail(&newest.path(), lines).map_err(|err| format!("error reading {:?}: {err}", newest.path()))
+}
+
+/// Read the last lines of a file, without reading all of it as logs can be large.
+fn tail(path: &Path, lines: usize) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?;
+    let mut start = file.metadata()?.len();
+    let mut contents = Vec::new();
+    let mut newlines = 0;
+    // The first line read is usually partial, so read until there's one more line than needed
+    while start > 0 && newlines <= lines {
+        let len = start.min(TAIL_CHUNK);
+        start -= len;
+        let mut chunk = vec![0; len as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk)?;
+        newlines += chunk.iter().filter(|b| **b == b'\n').count();
+        chunk.extend_from_slice(&contents);
+        contents = chunk;
+    }
+
+    // A multi-byte character can be cut off at the start, and the log may not be valid UTF-8
+    let contents = String::from_utf8_lossy(&contents);
+    let all = contents.lines().collect::<Vec<_>>();
+    Ok(all[all.len().saturating_sub(lines)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tail() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("mattraxd.log");
+
+        // Spans several chunks, with invalid UTF-8 in the last lines
+        let mut contents = (0..20_000)
+            .map(|i| format!("line {i}\n"))
+            .collect::<String>()
+            .into_bytes();
+        contents.extend_from_slice(b"invalid \xff\nlast\n");
+        fs::write(&path, contents).unwrap();
+
+        assert_eq!(
+            tail(&path, 3).unwrap(),
+            ["line 19999", "invalid \u{fffd}", "last"]
+        );
+        let all = tail(&path, 30_000).unwrap();
+        assert_eq!(all.len(), 20_002);
+        assert_eq!(all[0], "line 0");
+        assert!(tail(&path, 0).unwrap().is_empty());
+
+        fs::write(&path, "").unwrap();
+        assert!(tail(&path, 3).unwrap().is_empty());
+    }
+}
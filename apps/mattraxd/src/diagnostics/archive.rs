@@ -0,0 +1,101 @@
+//! A minimal writer for gzip compressed tar archives. Only regular files are supported.
+
+use std::io::{self, Write};
+
+use flate2::{write::GzEncoder, Compression};
+
+const BLOCK: usize = 512;
+
+pub struct Archive<W: Write> {
+    gz: GzEncoder<W>,
+}
+
+impl<W: Write> Archive<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            gz: GzEncoder::new(writer, Compression::default()),
+        }
+    }
+
+    /// Add a file. `path` must be relative and at most 100 bytes.
+    pub fn append(&mut self, path: &str, data: &[u8], mtime: u64) -> io::Result<()> {
+        self.gz
+            .write_all(&header(path, data.len() as u64, mtime)?)?;
+        self.gz.write_all(data)?;
+        // Entries are padded to a whole number of blocks
+        let padding = (BLOCK - data.len() % BLOCK) % BLOCK;
+        self.gz.write_all(&[0; BLOCK][..padding])
+    }
+
+    /// Write the end of archive marker and flush the compressor.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.gz.write_all(&[0; BLOCK * 2])?;
+        self.gz.finish()
+    }
+}
+
+// A POSIX ustar header. Numeric fields are octal with a trailing NUL.
+fn header(path: &str, size: u64, mtime: u64) -> io::Result<[u8; BLOCK]> {
+    if path.len() > 100 || path.starts_with('/') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid archive path {path:?}"),
+        ));
+    }
+
+    let mut header = [0u8; BLOCK];
+    let mut field = |offset: usize, value: &[u8]| {
+        header[offset..offset + value.len()].copy_from_slice(value);
+    };
+    field(0, path.as_bytes());
+    field(100, b"0000644\0"); // mode
+    field(108, b"0000000\0"); // uid
+    field(116, b"0000000\0"); // gid
+    field(124, format!("{size:011o}\0").as_bytes());
+    field(136, format!("{mtime:011o}\0").as_bytes());
+    field(148, b"        "); // The checksum is calculated with its own field set to spaces
+    field(156, b"0"); // regular file
+    field(257, b"ustar\0");
+    field(263, b"00");
+    field(265, b"root");
+    field(297, b"root");
+
+    let checksum = header.iter().map(|&b| b as u32).sum::<u32>();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+    Ok(header)
+}
+
+#[cfg(test)]
+mod test {
+    use std::process::Command;
+
+    use super::*;
+
+    #[test]
+    fn test_archive() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let mut archive = Archive::new(Vec::new());
+        archive.append("summary.json", b"{}", 0).unwrap();
+        archive
+            .append("logs/mttx.log", &[b'a'; BLOCK + 1], 1700000000)
+            .unwrap();
+        assert!(archive.append("/etc/passwd", b"", 0).is_err());
+        std::fs::write(dir.join("bundle.tar.gz"), archive.finish().unwrap()).unwrap();
+
+        // Check the archive can be read by the system's `tar`
+        let status = Command::new("tar")
+            .arg("-xzf")
+            .arg("bundle.tar.gz")
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(std::fs::read(dir.join("summary.json")).unwrap(), b"{}");
+        assert_eq!(
+            std::fs::read(dir.join("logs/mttx.log")).unwrap(),
+            [b'a'; BLOCK + 1]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+//! Diagnostics bundles let support investigate a device without needing access to it.
+//!
+//! A bundle is a `.tar.gz` of the agent's logs, state and inventory with secrets redacted.
+//! The device's identity is never included.
+
+use std::{
+    fs,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use mx_agent_api::{DiagnosticsUpload, DIAGNOSTICS_CHUNK_SIZE, MAX_DIAGNOSTICS_SIZE};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::{client::Client, config::Config, file, inventory, outbox::Outbox, update};
+
+mod archive;
+mod redact;
+
+use archive::Archive;
+
+const DIR: &str = "diagnostics";
+const UPLOAD_FILE: &str = "upload.json";
+const BUNDLE_FILE: &str = "bundle.tar.gz";
+
+/// State files from the data directory which are included in the bundle.
+const STATE_FILES: &[&str] = &["config.json", "status.json", "scripts.json", "update.json"];
+
+// Only the end of larger files is included
+const MAX_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Write a diagnostics bundle to `path`. This is blocking as it collects the inventory.
+pub fn collect(data_dir: &Path, path: &Path) -> Result<(), String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let file = fs::File::create(path).map_err(|err| format!("error creating {path:?}: {err}"))?;
+    let mut archive = Archive::new(file);
+    let write_error = |err| format!("error writing {path:?}: {err}");
+
+    let summary = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "collectedAt": now,
+        "deviceId": Config::load(data_dir).ok().flatten().map(|config| config.device_id),
+        "queuedReports": Outbox::pending(data_dir).ok().map(|entries| entries.len()),
+        "updatePending": update::is_pending(data_dir),
+    });
+    archive
+        .append(
+            "summary.json",
+            &serde_json::to_vec_pretty(&summary).unwrap(),
+            now,
+        )
+        .map_err(write_error)?;
+
+    let inventory = serde_json::to_string_pretty(&inventory::collect()).unwrap();
+    archive
+        .append("inventory.json", redact::redact(&inventory).as_bytes(), now)
+        .map_err(write_error)?;
+
+    for name in STATE_FILES {
+        if let Some((contents, mtime)) = read_tail(&data_dir.join(name), MAX_FILE_BYTES)? {
+            archive
+                .append(
+                    &format!("state/{name}"),
+                    redact::redact(&contents).as_bytes(),
+                    mtime,
+                )
+                .map_err(write_error)?;
+        }
+    }
+
+    // This includes every rotated log file, not just the current one
+    if let Ok(entries) = fs::read_dir(data_dir.join("logs")) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !entry.path().is_file() {
+                continue;
+            }
+
+            if let Some((contents, mtime)) = read_tail(&entry.path(), MAX_FILE_BYTES)? {
+                archive
+                    .append(
+                        &format!("logs/{name}"),
+                        redact::redact(&contents).as_bytes(),
+                        mtime,
+                    )
+                    .map_err(write_error)?;
+            }
+        }
+    }
+
+    archive
+        .finish()
+        .and_then(|file| file.sync_all())
+        .map_err(write_error)
+}
+
+/// An upload which hasn't completed, saved so it can be resumed.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Upload {
+    id: String,
+    action: Option<String>,
+    path: PathBuf,
+}
+
+/// Collect a bundle and upload it to Mattrax, returning its id.
+///
+/// An interrupted upload for the same action is resumed instead of collecting a new bundle.
+pub async fn upload(
+    data_dir: &Path,
+    client: &Client,
+    action: Option<&str>,
+) -> Result<String, String> {
+    let dir = data_dir.join(DIR);
+    fs::create_dir_all(&dir).map_err(|err| format!("error creating {dir:?}: {err}"))?;
+    let state_path = dir.join(UPLOAD_FILE);
+
+    let previous = match fs::read_to_string(&state_path) {
+        Ok(contents) => serde_json::from_str::<Upload>(&contents)
+            .ok()
+            .filter(|upload| upload.action.as_deref() == action && upload.path.exists()),
+        Err(_) => None,
+    };
+    // The server may have discarded the upload
+    let resumed = match previous {
+        Some(upload) => match client.diagnostics_status(&upload.id).await {
+            Ok(status) => Some((upload, status)),
+            Err(err) => {
+                warn!(
+                    "Unable to resume upload of diagnostics {:?}: {err}",
+                    upload.id
+                );
+                None
+            }
+        },
+        None => None,
+    };
+
+    let (upload, mut status) = match resumed {
+        Some((upload, status)) => {
+            info!(
+                "Resuming upload of diagnostics {:?} from {} bytes",
+                upload.id, status.received
+            );
+            (upload, status)
+        }
+        None => {
+            let path = dir.join(BUNDLE_FILE);
+            let (data_dir, bundle) = (data_dir.to_path_buf(), path.clone());
+            tokio::task::spawn_blocking(move || collect(&data_dir, &bundle))
+                .await
+                .map_err(|err| format!("error collecting diagnostics: {err}"))??;
+
+            let (size, sha256) = digest(&path)?;
+            if size > MAX_DIAGNOSTICS_SIZE {
+                return Err(format!("diagnostics bundle is too large ({size} bytes)"));
+            }
+            let status = client
+                .start_diagnostics(&DiagnosticsUpload {
+                    size,
+                    sha256,
+                    action: action.map(Into::into),
+                })
+                .await?;
+
+            let upload = Upload {
+                id: status.id.clone(),
+                action: action.map(Into::into),
+                path,
+            };
+            file::write_atomic(&state_path, serde_json::to_vec(&upload).unwrap())?;
+            (upload, status)
+        }
+    };
+
+    let mut failures = 0;
+    while !status.complete {
+        let chunk = read_chunk(&upload.path, status.received)?;
+        match client
+            .upload_diagnostics_chunk(&upload.id, status.received, chunk)
+            .await
+        {
+            Ok(next) => status = next,
+            Err(err) => {
+                failures += 1;
+                if failures >= MAX_ATTEMPTS {
+                    return Err(format!("error uploading diagnostics: {err}"));
+                }
+                warn!(
+                    "Error uploading diagnostics {:?}, retrying: {err}",
+                    upload.id
+                );
+                tokio::time::sleep(RETRY_DELAY * failures).await;
+
+                // The chunk may have been received even though the request failed
+                status = client.diagnostics_status(&upload.id).await?;
+            }
+        }
+    }
+
+    info!("Uploaded diagnostics {:?}", upload.id);
+    let _ = fs::remove_file(&upload.path);
+    let _ = fs::remove_file(&state_path);
+    Ok(upload.id)
+}
+
+/// Read a text file, keeping only the last `max` bytes. Returns `None` if the file doesn't exist.
+fn read_tail(path: &Path, max: u64) -> Result<Option<(String, u64)>, String> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("error opening {path:?}: {err}")),
+    };
+    let metadata = file
+        .metadata()
+        .map_err(|err| format!("error reading {path:?}: {err}"))?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut contents = Vec::new();
+    file.seek(SeekFrom::Start(metadata.len().saturating_sub(max)))
+        .and_then(|_| file.take(max).read_to_end(&mut contents))
+        .map_err(|err| format!("error reading {path:?}: {err}"))?;
+    Ok(Some((
+        String::from_utf8_lossy(&contents).into_owned(),
+        mtime,
+    )))
+}
+
+fn read_chunk(path: &Path, offset: u64) -> Result<Vec<u8>, String> {
+    let mut file = fs::File::open(path).map_err(|err| format!("error opening {path:?}: {err}"))?;
+    let mut chunk = Vec::with_capacity(DIAGNOSTICS_CHUNK_SIZE);
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| {
+            file.take(DIAGNOSTICS_CHUNK_SIZE as u64)
+                .read_to_end(&mut chunk)
+        })
+        .map_err(|err| format!("error reading {path:?}: {err}"))?;
+    Ok(chunk)
+}
+
+fn digest(path: &Path) -> Result<(u64, String), String> {
+    let mut file = fs::File::open(path).map_err(|err| format!("error opening {path:?}: {err}"))?;
+    let mut hasher = Sha256::new();
+    let size = std::io::copy(&mut file, &mut hasher)
+        .map_err(|err| format!("error reading {path:?}: {err}"))?;
+    let sha256 = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Ok((size, sha256))
+}
+
+#[cfg(test)]
+mod test {
+    use std::process::Command;
+
+    use super::*;
+
+    #[test]
+    fn test_collect() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::create_dir_all(dir.join("logs")).unwrap();
+        fs::create_dir_all(dir.join("identity")).unwrap();
+        fs::write(dir.join("identity/device.key"), "secret").unwrap();
+        fs::write(dir.join("status.json"), "{}").unwrap();
+        fs::write(
+            dir.join("logs/mttx.2024-01-01.log"),
+            "enrolling with token=abc123\n",
+        )
+        .unwrap();
+
+        let bundle = dir.join(BUNDLE_FILE);
+        collect(dir, &bundle).unwrap();
+
+        let out = dir.join("out");
+        fs::create_dir_all(&out).unwrap();
+        let status = Command::new("tar")
+            .arg("-xzf")
+            .arg(&bundle)
+            .current_dir(&out)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        assert!(out.join("summary.json").exists());
+        assert!(out.join("inventory.json").exists());
+        assert_eq!(
+            fs::read_to_string(out.join("state/status.json")).unwrap(),
+            "{}"
+        );
+        assert_eq!(
+            fs::read_to_string(out.join("logs/mttx.2024-01-01.log")).unwrap(),
+            "enrolling with token=[REDACTED]\n"
+        );
+        assert!(!out.join("identity").exists());
+    }
+}
@@ -0,0 +1,45 @@
+//! Helpers for the files the agent keeps in its data directory.
+
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Replace the contents of a file so a crash or power loss leaves either the old or the new contents, never a partial file.
+///
+/// The contents are written to a temporary file next to `path` which is then renamed over it.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), String> {
+    let mut tmp = path.as_os_str().to_owned();
+    // The daemon and CLI commands can write the same file at the same time
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    let tmp = PathBuf::from(tmp);
+
+    let mut file = File::create(&tmp).map_err(|err| format!("error creating {tmp:?}: {err}"))?;
+    file.write_all(contents.as_ref())
+        .and_then(|_| file.sync_all())
+        .map_err(|err| format!("error writing {tmp:?}: {err}"))?;
+    std::fs::rename(&tmp, path).map_err(|err| {
+        let _ = std::fs::remove_file(&tmp);
+        format!("error saving {path:?}: {err}")
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_atomic() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("state.json");
+
+        write_atomic(&path, "old").unwrap();
+        write_atomic(&path, "new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        // The temporary file was renamed over the original
+        assert_eq!(std::fs::read_dir(tmp.path()).unwrap().count(), 1);
+
+        assert!(write_atomic(&tmp.path().join("missing/state.json"), "new").is_err());
+    }
+}
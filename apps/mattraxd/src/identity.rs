@@ -0,0 +1,131 @@
+//! The device's identity is a keypair generated on the device and a client certificate issued by Mattrax during enrollment.
+//!
+//! The private key never leaves the device.
+
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use rcgen::{CertificateParams, DnType, KeyPair};
+
+const KEY_FILE: &str = "device.key";
+const CERT_FILE: &str = "device.crt";
+
+fn dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("identity")
+}
+
+pub struct Identity {
+    pub key: String,
+    pub certificate: String,
+}
+
+impl Identity {
+    /// Load the identity from the data directory. Returns `None` if the device hasn't been enrolled.
+    pub fn load(data_dir: &Path) -> Result<Option<Self>, String> {
+        let dir = dir(data_dir);
+        let (key_path, cert_path) = (dir.join(KEY_FILE), dir.join(CERT_FILE));
+        if !key_path.exists() || !cert_path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            key: fs::read_to_string(&key_path)
+                .map_err(|err| format!("error reading {key_path:?}: {err}"))?,
+            certificate: fs::read_to_string(&cert_path)
+                .map_err(|err| format!("error reading {cert_path:?}: {err}"))?,
+        }))
+    }
+
+    pub fn to_reqwest(&self) -> Result<reqwest::Identity, String> {
+        reqwest::Identity::from_pem(format!("{}\n{}", self.certificate, self.key).as_bytes())
+            .map_err(|err| format!("error loading device identity: {err}"))
+    }
+
+    /// A TLS configuration which presents the device's certificate, for connections not made through `reqwest`.
+    pub fn to_rustls(&self) -> Result<rustls::ClientConfig, String> {
+        let certificates = rustls_pemfile::certs(&mut self.certificate.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("error loading device certificate: {err}"))?;
+        let key = rustls_pemfile::private_key(&mut self.key.as_bytes())
+            .map_err(|err| format!("error loading device key: {err}"))?
+            .ok_or("error loading device key: no private key found")?;
+
+        let roots = rustls::RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| {
+            builder
+                .with_root_certificates(roots)
+                .with_client_auth_cert(certificates, key)
+        })
+        .map_err(|err| format!("error creating TLS configuration: {err}"))
+    }
+
+    /// The certificate as base64 encoded DER, which is just the body of the PEM.
+    pub fn certificate_der_base64(&self) -> String {
+        self.certificate
+            .lines()
+            .filter(|line| !line.starts_with("-----"))
+            .collect()
+    }
+}
+
+/// Generate a new keypair for the device. It's only kept in memory until it's [`save`]d with its certificate.
+pub fn generate_key() -> Result<KeyPair, String> {
+    KeyPair::generate().map_err(|err| format!("error generating keypair: {err}"))
+}
+
+/// Create a PEM encoded certificate signing request for the device's keypair.
+pub fn certificate_signing_request(key: &KeyPair, name: &str) -> Result<String, String> {
+    let mut params = CertificateParams::default();
+    params.distinguished_name.push(DnType::CommonName, name);
+
+    params
+        .serialize_request(key)
+        .and_then(|csr| csr.pem())
+        .map_err(|err| format!("error creating certificate signing request: {err}"))
+}
+
+/// Save the keypair and the certificate issued for it, replacing any existing identity.
+pub fn save(data_dir: &Path, key: &KeyPair, certificate: &str) -> Result<(), String> {
+    let dir = dir(data_dir);
+    write_private(&dir, KEY_FILE, &key.serialize_pem())?;
+    write_private(&dir, CERT_FILE, certificate)
+}
+
+// Write a file which is only accessible by the current user (which should be root).
+// We write to a temporary file first so a crash can't leave a partially written key.
+fn write_private(dir: &Path, name: &str, contents: &str) -> Result<(), String> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder
+        .create(dir)
+        .map_err(|err| format!("error creating {dir:?}: {err}"))?;
+
+    let path = dir.join(name);
+    let tmp_path = dir.join(format!("{name}.tmp"));
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options
+        .open(&tmp_path)
+        .map_err(|err| format!("error creating {tmp_path:?}: {err}"))?;
+    file.write_all(contents.as_bytes())
+        .and_then(|_| file.sync_all())
+        .map_err(|err| format!("error writing {tmp_path:?}: {err}"))?;
+
+    fs::rename(&tmp_path, &path).map_err(|err| format!("error saving {path:?}: {err}"))
+}
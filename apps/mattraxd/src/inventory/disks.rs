@@ -0,0 +1,46 @@
+use std::{collections::HashSet, fs};
+
+use mx_agent_api::Disk;
+use nix::sys::statvfs::statvfs;
+
+// Read-only images (Eg. Snap packages) which aren't useful to report
+const IGNORED_FILESYSTEMS: &[&str] = &["squashfs", "iso9660"];
+
+pub fn collect() -> Vec<Disk> {
+    let Ok(mounts) = fs::read_to_string("/proc/mounts") else {
+        return Vec::new();
+    };
+
+    let mut seen = HashSet::new();
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            Some((parts.next()?, parts.next()?, parts.next()?))
+        })
+        .filter(|(device, _, filesystem)| {
+            device.starts_with("/dev/") && !IGNORED_FILESYSTEMS.contains(filesystem)
+        })
+        // Bind mounts and btrfs subvolumes show up multiple times so we only report the first mount
+        .filter(|(device, _, _)| seen.insert(device.to_string()))
+        .filter_map(|(device, mount_point, filesystem)| {
+            // Spaces and other special characters are octal escaped
+            let mount_point = mount_point.replace("\\040", " ");
+            let stat = statvfs(mount_point.as_str()).ok()?;
+
+            #[allow(clippy::useless_conversion)] // The types differ between platforms
+            let fragment_size = u64::from(stat.fragment_size());
+            #[allow(clippy::useless_conversion)]
+            let (blocks, blocks_available) =
+                (u64::from(stat.blocks()), u64::from(stat.blocks_available()));
+
+            Some(Disk {
+                device: device.to_string(),
+                mount_point,
+                filesystem: filesystem.to_string(),
+                total_bytes: blocks * fragment_size,
+                available_bytes: blocks_available * fragment_size,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,89 @@
+use std::{collections::HashSet, fs};
+
+use mx_agent_api::{Cpu, Hardware, Memory};
+
+use super::read_trimmed;
+
+// Some vendors fill the DMI fields in with junk
+const DMI_PLACEHOLDERS: &[&str] = &[
+    "0",
+    "None",
+    "Default string",
+    "To Be Filled By O.E.M.",
+    "System Serial Number",
+    "Not Specified",
+];
+
+fn dmi(name: &str) -> Option<String> {
+    read_trimmed(&format!("/sys/class/dmi/id/{name}"))
+        .filter(|v| !DMI_PLACEHOLDERS.iter().any(|p| v.eq_ignore_ascii_case(p)))
+}
+
+pub fn collect() -> Hardware {
+    Hardware {
+        manufacturer: dmi("sys_vendor"),
+        model: dmi("product_name"),
+        // `product_serial` and `product_uuid` are only readable by root
+        serial_number: dmi("product_serial"),
+        uuid: dmi("product_uuid"),
+    }
+}
+
+pub fn cpu() -> Cpu {
+    let Ok(cpuinfo) = fs::read_to_string("/proc/cpuinfo") else {
+        return Default::default();
+    };
+
+    let mut model = None;
+    let mut logical_cores = 0;
+    let mut physical_cores = HashSet::new();
+    let mut physical_id = None;
+
+    for line in cpuinfo.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+
+        match key {
+            "processor" => logical_cores += 1,
+            "model name" if model.is_none() => model = Some(value.to_string()),
+            "physical id" => physical_id = Some(value.to_string()),
+            "core id" => {
+                physical_cores.insert((physical_id.clone(), value.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    Cpu {
+        model,
+        logical_cores,
+        // Some architectures don't report core ids
+        physical_cores: match physical_cores.len() {
+            0 => logical_cores,
+            n => n as u32,
+        },
+    }
+}
+
+pub fn memory() -> Memory {
+    let Ok(meminfo) = fs::read_to_string("/proc/meminfo") else {
+        return Default::default();
+    };
+
+    let get = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|v| v.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+            .unwrap_or_default()
+    };
+
+    Memory {
+        total_bytes: get("MemTotal"),
+        available_bytes: get("MemAvailable"),
+        swap_total_bytes: get("SwapTotal"),
+    }
+}
@@ -0,0 +1,59 @@
+//! Collect hardware and software facts about the device.
+//!
+//! Currently only Linux is supported. Anything which can't be determined is left empty instead of failing the whole collection.
+
+use std::{
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use mx_agent_api::{Inventory, INVENTORY_VERSION};
+
+mod disks;
+mod hardware;
+mod network;
+mod os;
+mod packages;
+
+pub fn collect() -> Inventory {
+    Inventory {
+        version: INVENTORY_VERSION,
+        collected_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        hostname: hostname(),
+        os: os::collect(),
+        hardware: hardware::collect(),
+        cpu: hardware::cpu(),
+        memory: hardware::memory(),
+        disks: disks::collect(),
+        network_interfaces: network::collect(),
+        packages: packages::collect(),
+    }
+}
+
+pub fn hostname() -> String {
+    read_trimmed("/proc/sys/kernel/hostname")
+        .or_else(|| read_trimmed("/etc/hostname"))
+        .unwrap_or_else(|| "unknown".into())
+}
+
+/// A unique hardware identifier for the device.
+///
+/// Virtual machines often don't have a serial so we fallback to the DMI UUID and then the machine ID.
+pub fn serial_number(inventory: &Inventory) -> Option<String> {
+    inventory
+        .hardware
+        .serial_number
+        .clone()
+        .or_else(|| inventory.hardware.uuid.clone())
+        .or_else(|| read_trimmed("/etc/machine-id"))
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
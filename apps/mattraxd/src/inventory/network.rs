@@ -0,0 +1,49 @@
+use std::{collections::BTreeMap, fs};
+
+use mx_agent_api::NetworkInterface;
+use nix::ifaddrs::getifaddrs;
+
+use super::read_trimmed;
+
+pub fn collect() -> Vec<NetworkInterface> {
+    let mut addresses = BTreeMap::<String, Vec<String>>::new();
+    if let Ok(ifaddrs) = getifaddrs() {
+        for ifaddr in ifaddrs {
+            let Some(address) = ifaddr.address else {
+                continue;
+            };
+
+            let address = if let Some(v4) = address.as_sockaddr_in() {
+                v4.ip().to_string()
+            } else if let Some(v6) = address.as_sockaddr_in6() {
+                v6.ip().to_string()
+            } else {
+                continue;
+            };
+
+            addresses
+                .entry(ifaddr.interface_name)
+                .or_default()
+                .push(address);
+        }
+    }
+
+    let Ok(entries) = fs::read_dir("/sys/class/net") else {
+        return Vec::new();
+    };
+
+    let mut interfaces = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name != "lo")
+        .map(|name| NetworkInterface {
+            mac_address: read_trimmed(&format!("/sys/class/net/{name}/address"))
+                .filter(|mac| mac != "00:00:00:00:00:00"),
+            state: read_trimmed(&format!("/sys/class/net/{name}/operstate")),
+            addresses: addresses.remove(&name).unwrap_or_default(),
+            name,
+        })
+        .collect::<Vec<_>>();
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    interfaces
+}
@@ -0,0 +1,68 @@
+use std::{collections::HashMap, fs};
+
+use mx_agent_api::OperatingSystem;
+
+use super::read_trimmed;
+
+pub fn collect() -> OperatingSystem {
+    let release = fs::read_to_string("/etc/os-release")
+        .or_else(|_| fs::read_to_string("/usr/lib/os-release"))
+        .map(|v| parse_os_release(&v))
+        .unwrap_or_default();
+
+    OperatingSystem {
+        id: release.get("ID").cloned(),
+        name: release.get("NAME").cloned(),
+        version_id: release.get("VERSION_ID").cloned(),
+        pretty_name: release.get("PRETTY_NAME").cloned(),
+        kernel: read_trimmed("/proc/sys/kernel/osrelease"),
+        arch: std::env::consts::ARCH.into(),
+    }
+}
+
+// Ref: https://www.freedesktop.org/software/systemd/man/latest/os-release.html
+fn parse_os_release(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+
+            (
+                key.to_string(),
+                value.replace("\\\"", "\"").replace("\\\\", "\\"),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_os_release() {
+        let release = parse_os_release(
+            r#"# Some comment
+NAME="Ubuntu"
+VERSION_ID="24.04"
+PRETTY_NAME="Ubuntu 24.04.1 LTS"
+ID=ubuntu
+VARIANT='Server \"Edition\"'
+"#,
+        );
+
+        assert_eq!(release.get("NAME").unwrap(), "Ubuntu");
+        assert_eq!(release.get("ID").unwrap(), "ubuntu");
+        assert_eq!(release.get("VERSION_ID").unwrap(), "24.04");
+        assert_eq!(release.get("PRETTY_NAME").unwrap(), "Ubuntu 24.04.1 LTS");
+        assert_eq!(release.get("VARIANT").unwrap(), "Server \"Edition\"");
+        assert_eq!(release.len(), 5);
+    }
+}
@@ -0,0 +1,124 @@
+use std::{fs, path::Path, process::Command};
+
+use mx_agent_api::{Package, PackageManager};
+use tracing::warn;
+
+pub fn collect() -> Vec<Package> {
+    let mut packages = Vec::new();
+
+    if let Ok(status) = fs::read_to_string("/var/lib/dpkg/status") {
+        packages.extend(parse_dpkg_status(&status));
+    }
+
+    // The RPM database is a SQLite or BerkeleyDB file depending on the distro so we let `rpm` read it
+    if Path::new("/var/lib/rpm").exists() || Path::new("/usr/lib/sysimage/rpm").exists() {
+        match Command::new("rpm")
+            .args([
+                "-qa",
+                "--queryformat",
+                "%{NAME}\t%{VERSION}-%{RELEASE}\t%{ARCH}\n",
+            ])
+            .output()
+        {
+            Ok(output) if output.status.success() => {
+                packages.extend(parse_rpm_output(&String::from_utf8_lossy(&output.stdout)))
+            }
+            Ok(output) => warn!("`rpm -qa` exited with {}", output.status),
+            Err(err) => warn!("Error running `rpm -qa`: {err}"),
+        }
+    }
+
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages
+}
+
+// The dpkg status file is a series of RFC 822 style paragraphs, one per package.
+fn parse_dpkg_status(status: &str) -> Vec<Package> {
+    status
+        .split("\n\n")
+        .filter_map(|paragraph| {
+            let field = |name: &str| {
+                paragraph
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                    .map(|v| v.trim().to_string())
+            };
+
+            // Removed packages which still have config files are also listed
+            if !field("Status")?.ends_with(" installed") {
+                return None;
+            }
+
+            Some(Package {
+                name: field("Package")?,
+                version: field("Version")?,
+                arch: field("Architecture"),
+                manager: PackageManager::Dpkg,
+            })
+        })
+        .collect()
+}
+
+fn parse_rpm_output(output: &str) -> Vec<Package> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            Some(Package {
+                name: parts.next()?.to_string(),
+                version: parts.next()?.to_string(),
+                arch: parts
+                    .next()
+                    .filter(|arch| *arch != "(none)")
+                    .map(|arch| arch.to_string()),
+                manager: PackageManager::Rpm,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_dpkg_status() {
+        let packages = parse_dpkg_status(
+            "Package: bash
+Status: install ok installed
+Priority: required
+Architecture: amd64
+Version: 5.2.21-2ubuntu4
+Description: GNU Bourne Again SHell
+ Bash is an sh-compatible command language interpreter.
+
+Package: nano
+Status: deinstall ok config-files
+Architecture: amd64
+Version: 7.2-2build1
+
+Package: tzdata
+Status: install ok installed
+Architecture: all
+Version: 2024a-3ubuntu1.1
+",
+        );
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "bash");
+        assert_eq!(packages[0].version, "5.2.21-2ubuntu4");
+        assert_eq!(packages[0].arch.as_deref(), Some("amd64"));
+        assert_eq!(packages[1].name, "tzdata");
+    }
+
+    #[test]
+    fn test_parse_rpm_output() {
+        let packages = parse_rpm_output(
+            "bash\t5.2.26-3.fc40\tx86_64\ngpg-pubkey\t8d8e4e3c-6502e3ef\t(none)\n",
+        );
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].version, "5.2.26-3.fc40");
+        assert_eq!(packages[1].arch, None);
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt},
+    path::Path,
+};
+
+use mx_policy::{Ensure, LinuxFile};
+use nix::unistd::{chown, Gid, Group, Uid, User};
+
+use super::resolve;
+
+const DEFAULT_MODE: u32 = 0o644;
+
+pub fn drift(root: &Path, file: &LinuxFile) -> Result<Vec<String>, String> {
+    let path = resolve(root, &file.path)?;
+    let metadata = match fs::symlink_metadata(&path) {
+        Ok(metadata) => Some(metadata),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => return Err(format!("error reading {path:?}: {err}")),
+    };
+
+    let metadata = match (file.ensure, metadata) {
+        (Ensure::Absent, Some(_)) => return Ok(vec!["exists".into()]),
+        (Ensure::Absent, None) => return Ok(vec![]),
+        (Ensure::Present, None) => return Ok(vec!["missing".into()]),
+        (Ensure::Present, Some(metadata)) => metadata,
+    };
+    if !metadata.is_file() {
+        return Err(format!("{:?} is not a regular file", file.path));
+    }
+
+    let mut drift = Vec::new();
+    if let Some(content) = &file.content {
+        let current = fs::read(&path).map_err(|err| format!("error reading {path:?}: {err}"))?;
+        if current != content.as_bytes() {
+            drift.push("content differs".into());
+        }
+    }
+    if let Some(mode) = &file.mode {
+        let (current, mode) = (metadata.mode() & 0o7777, parse_mode(mode)?);
+        if current != mode {
+            drift.push(format!("mode {current:04o} -> {mode:04o}"));
+        }
+    }
+    if let Some(owner) = &file.owner {
+        if metadata.uid() != uid(owner)?.as_raw() {
+            drift.push(format!("owner {} -> {owner}", metadata.uid()));
+        }
+    }
+    if let Some(group) = &file.group {
+        if metadata.gid() != gid(group)?.as_raw() {
+            drift.push(format!("group {} -> {group}", metadata.gid()));
+        }
+    }
+
+    Ok(drift)
+}
+
+pub fn apply(root: &Path, file: &LinuxFile) -> Result<(), String> {
+    let path = resolve(root, &file.path)?;
+
+    if file.ensure == Ensure::Absent {
+        return match fs::remove_file(&path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(format!("error removing {path:?}: {err}"))
+            }
+            _ => Ok(()),
+        };
+    }
+
+    let existing = fs::metadata(&path).ok();
+    let mode = match &file.mode {
+        Some(mode) => parse_mode(mode)?,
+        None => existing
+            .as_ref()
+            .map(|m| m.mode() & 0o7777)
+            .unwrap_or(DEFAULT_MODE),
+    };
+    let owner = file.owner.as_deref().map(uid).transpose()?;
+    let group = file.group.as_deref().map(gid).transpose()?;
+
+    let needs_write = match (&file.content, &existing) {
+        (Some(content), Some(_)) => fs::read(&path).ok().as_deref() != Some(content.as_bytes()),
+        (_, None) => true,
+        (None, Some(_)) => false,
+    };
+
+    if needs_write {
+        // Write and set the permissions of a temporary file first so the file is never partially written or has the wrong permissions
+        let parent = path.parent().ok_or("file has no parent directory")?;
+        fs::create_dir_all(parent).map_err(|err| format!("error creating {parent:?}: {err}"))?;
+        let tmp = parent.join(format!(
+            ".{}.mattrax",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ));
+
+        let mut f = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp)
+            .map_err(|err| format!("error creating {tmp:?}: {err}"))?;
+        f.write_all(file.content.as_deref().unwrap_or_default().as_bytes())
+            .and_then(|_| f.sync_all())
+            .map_err(|err| format!("error writing {tmp:?}: {err}"))?;
+        set_attributes(&tmp, mode, owner, group)?;
+        fs::rename(&tmp, &path).map_err(|err| format!("error renaming {tmp:?}: {err}"))?;
+    } else {
+        set_attributes(&path, mode, owner, group)?;
+    }
+
+    Ok(())
+}
+
+fn set_attributes(
+    path: &Path,
+    mode: u32,
+    owner: Option<Uid>,
+    group: Option<Gid>,
+) -> Result<(), String> {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .map_err(|err| format!("error setting permissions of {path:?}: {err}"))?;
+    if owner.is_some() || group.is_some() {
+        chown(path, owner, group)
+            .map_err(|err| format!("error setting owner of {path:?}: {err}"))?;
+    }
+    Ok(())
+}
+
+fn parse_mode(mode: &str) -> Result<u32, String> {
+    u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| format!("invalid mode {mode:?}"))
+}
+
+fn uid(name: &str) -> Result<Uid, String> {
+    User::from_name(name)
+        .map_err(|err| format!("error looking up user {name:?}: {err}"))?
+        .map(|user| user.uid)
+        .ok_or_else(|| format!("user {name:?} doesn't exist"))
+}
+
+fn gid(name: &str) -> Result<Gid, String> {
+    Group::from_name(name)
+        .map_err(|err| format!("error looking up group {name:?}: {err}"))?
+        .map(|group| group.gid)
+        .ok_or_else(|| format!("group {name:?} doesn't exist"))
+}
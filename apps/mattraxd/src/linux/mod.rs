@@ -0,0 +1,414 @@
+//! Enforce the declarative [`LinuxConfig`] from policies.
+//!
+//! Every item is checked for drift and only changed if it differs, so applying the same configuration again is a no-op.
+//! Paths are resolved relative to the engine's root so it can be tested against a temporary directory.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use mx_agent_api::{ComplianceStatus, ItemCompliance, ItemKind};
+use mx_policy::{Ensure, LinuxConfig};
+use tracing::{debug, info, warn};
+
+mod files;
+mod packages;
+mod services;
+mod sysctl;
+
+pub use packages::PackageBackend;
+pub use services::ServiceBackend;
+
+pub struct Engine {
+    root: PathBuf,
+    packages: Option<Box<dyn PackageBackend + Send>>,
+    services: Box<dyn ServiceBackend + Send>,
+    dry_run: bool,
+}
+
+impl Engine {
+    /// Create an engine which manages the running system.
+    pub fn system(dry_run: bool) -> Self {
+        Self::new(
+            PathBuf::from("/"),
+            packages::detect(),
+            Box::new(services::Systemd),
+            dry_run,
+        )
+    }
+
+    pub fn new(
+        root: PathBuf,
+        packages: Option<Box<dyn PackageBackend + Send>>,
+        services: Box<dyn ServiceBackend + Send>,
+        dry_run: bool,
+    ) -> Self {
+        Self {
+            root,
+            packages,
+            services,
+            dry_run,
+        }
+    }
+
+    /// Bring the device in line with the configuration, returning the compliance of every item.
+    ///
+    /// Packages are applied first so files can configure them and services are applied last so they start with their configuration.
+    pub fn apply(&self, config: &LinuxConfig) -> Vec<ItemCompliance> {
+        let mut items = Vec::new();
+
+        for package in &config.packages {
+            items.push(self.enforce(
+                ItemKind::Package,
+                &package.name,
+                || {
+                    let backend = self.package_backend(&package.name)?;
+                    let installed = backend.is_installed(&package.name)?;
+                    Ok(match (package.ensure, installed) {
+                        (Ensure::Present, false) => vec!["not installed".into()],
+                        (Ensure::Absent, true) => vec!["installed".into()],
+                        _ => vec![],
+                    })
+                },
+                || {
+                    let backend = self.package_backend(&package.name)?;
+                    match package.ensure {
+                        Ensure::Present => backend.install(&package.name),
+                        Ensure::Absent => backend.remove(&package.name),
+                    }
+                },
+            ));
+        }
+
+        for file in &config.files {
+            items.push(self.enforce(
+                ItemKind::File,
+                &file.path,
+                || files::drift(&self.root, file),
+                || files::apply(&self.root, file),
+            ));
+        }
+
+        for (key, value) in &config.sysctl {
+            items.push(self.enforce(
+                ItemKind::Sysctl,
+                key,
+                || sysctl::drift(&self.root, key, value),
+                || sysctl::apply(&self.root, key, value),
+            ));
+        }
+
+        for service in &config.services {
+            items.push(self.enforce(
+                ItemKind::Service,
+                &service.unit,
+                || {
+                    let enabled = self.services.is_enabled(&service.unit)?;
+                    Ok(match (enabled, service.enabled) {
+                        (false, true) => vec!["disabled -> enabled".into()],
+                        (true, false) => vec!["enabled -> disabled".into()],
+                        _ => vec![],
+                    })
+                },
+                || self.services.set_enabled(&service.unit, service.enabled),
+            ));
+        }
+
+        items
+    }
+
+    fn package_backend(&self, name: &str) -> Result<&(dyn PackageBackend + Send), String> {
+        // Names are passed as arguments to the package manager
+        if name.starts_with('-')
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-' | '_' | ':'))
+        {
+            return Err(format!("invalid package name {name:?}"));
+        }
+
+        self.packages
+            .as_deref()
+            .ok_or_else(|| "no supported package manager was found".into())
+    }
+
+    fn enforce(
+        &self,
+        kind: ItemKind,
+        id: &str,
+        drift: impl Fn() -> Result<Vec<String>, String>,
+        apply: impl FnOnce() -> Result<(), String>,
+    ) -> ItemCompliance {
+        let mut item = ItemCompliance {
+            kind,
+            id: id.to_string(),
+            status: ComplianceStatus::Compliant,
+            drift: Vec::new(),
+            error: None,
+        };
+
+        match drift() {
+            Ok(found) if found.is_empty() => {}
+            Ok(found) => {
+                item.drift = found;
+
+                if self.dry_run {
+                    item.status = ComplianceStatus::Drifted;
+                } else {
+                    // Check again to confirm the change actually stuck
+                    match apply().and_then(|_| drift()) {
+                        Ok(remaining) if remaining.is_empty() => {
+                            item.status = ComplianceStatus::Remediated
+                        }
+                        Ok(remaining) => {
+                            item.status = ComplianceStatus::Failed;
+                            item.error = Some(format!(
+                                "still drifted after applying: {}",
+                                remaining.join(", ")
+                            ));
+                        }
+                        Err(err) => {
+                            item.status = ComplianceStatus::Failed;
+                            item.error = Some(err);
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                item.status = ComplianceStatus::Failed;
+                item.error = Some(err);
+            }
+        }
+
+        match item.status {
+            ComplianceStatus::Compliant => debug!("{kind:?} {id:?} is compliant"),
+            ComplianceStatus::Remediated => {
+                info!("{kind:?} {id:?} remediated: {}", item.drift.join(", "))
+            }
+            ComplianceStatus::Drifted => {
+                info!("{kind:?} {id:?} has drifted: {}", item.drift.join(", "))
+            }
+            ComplianceStatus::Failed => warn!(
+                "{kind:?} {id:?} failed: {}",
+                item.error.as_deref().unwrap_or_default()
+            ),
+        }
+
+        item
+    }
+}
+
+/// Resolve an absolute path from the configuration within `root`.
+fn resolve(root: &Path, path: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(path)
+        .strip_prefix("/")
+        .map_err(|_| format!("{path:?} must be an absolute path"))?;
+
+    if relative
+        .components()
+        .any(|c| !matches!(c, std::path::Component::Normal(_)))
+    {
+        return Err(format!("{path:?} must not contain `.` or `..`"));
+    }
+
+    Ok(root.join(relative))
+}
+
+/// Run a command, returning an error containing stderr if it fails.
+fn run_command(program: &str, args: &[&str]) -> Result<Output, String> {
+    let output = Command::new(program)
+        .args(args)
+        .env("DEBIAN_FRONTEND", "noninteractive")
+        .output()
+        .map_err(|err| format!("error running {program:?}: {err}"))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "{program} {} failed with {}: {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashSet,
+        fs,
+        os::unix::fs::PermissionsExt,
+        sync::{Arc, Mutex},
+    };
+
+    use mx_policy::{LinuxFile, LinuxPackage, LinuxService};
+
+    use super::*;
+
+    #[derive(Default, Clone)]
+    struct Fake(Arc<Mutex<HashSet<String>>>);
+
+    impl PackageBackend for Fake {
+        fn is_installed(&self, name: &str) -> Result<bool, String> {
+            Ok(self.0.lock().unwrap().contains(name))
+        }
+
+        fn install(&self, name: &str) -> Result<(), String> {
+            self.0.lock().unwrap().insert(name.into());
+            Ok(())
+        }
+
+        fn remove(&self, name: &str) -> Result<(), String> {
+            self.0.lock().unwrap().remove(name);
+            Ok(())
+        }
+    }
+
+    impl ServiceBackend for Fake {
+        fn is_enabled(&self, unit: &str) -> Result<bool, String> {
+            Ok(self.0.lock().unwrap().contains(unit))
+        }
+
+        fn set_enabled(&self, unit: &str, enabled: bool) -> Result<(), String> {
+            match enabled {
+                true => self.0.lock().unwrap().insert(unit.into()),
+                false => self.0.lock().unwrap().remove(unit),
+            };
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        fs::create_dir_all(root.join("proc/sys/net/ipv4")).unwrap();
+        fs::write(root.join("proc/sys/net/ipv4/ip_forward"), "0\n").unwrap();
+        fs::create_dir_all(root.join("etc")).unwrap();
+        fs::write(root.join("etc/old.conf"), "remove me").unwrap();
+
+        let (packages, services) = (Fake::default(), Fake::default());
+        packages.install("telnet").unwrap();
+        let engine = |dry_run| {
+            Engine::new(
+                root.to_path_buf(),
+                Some(Box::new(packages.clone())),
+                Box::new(services.clone()),
+                dry_run,
+            )
+        };
+
+        let config = LinuxConfig {
+            files: vec![
+                LinuxFile {
+                    path: "/etc/motd".into(),
+                    ensure: Ensure::Present,
+                    content: Some("Managed by Mattrax\n".into()),
+                    owner: None,
+                    group: None,
+                    mode: Some("0600".into()),
+                },
+                LinuxFile {
+                    path: "/etc/old.conf".into(),
+                    ensure: Ensure::Absent,
+                    content: None,
+                    owner: None,
+                    group: None,
+                    mode: None,
+                },
+                LinuxFile {
+                    path: "/etc/../shadow".into(),
+                    ensure: Ensure::Present,
+                    content: None,
+                    owner: None,
+                    group: None,
+                    mode: None,
+                },
+            ],
+            services: vec![LinuxService {
+                unit: "sshd.service".into(),
+                enabled: true,
+            }],
+            sysctl: [("net.ipv4.ip_forward".to_string(), "1".to_string())].into(),
+            packages: vec![
+                LinuxPackage {
+                    name: "vim".into(),
+                    ensure: Ensure::Present,
+                },
+                LinuxPackage {
+                    name: "telnet".into(),
+                    ensure: Ensure::Absent,
+                },
+            ],
+        };
+        let statuses = |items: Vec<ItemCompliance>| {
+            items
+                .into_iter()
+                .map(|item| (item.id, item.status))
+                .collect::<Vec<_>>()
+        };
+
+        // A dry run detects drift without changing anything
+        let items = statuses(engine(true).apply(&config));
+        assert!(items.contains(&("vim".into(), ComplianceStatus::Drifted)));
+        assert!(items.contains(&("/etc/motd".into(), ComplianceStatus::Drifted)));
+        assert!(items.contains(&("/etc/../shadow".into(), ComplianceStatus::Failed)));
+        assert!(!root.join("etc/motd").exists());
+        assert!(packages.is_installed("telnet").unwrap());
+
+        let items = statuses(engine(false).apply(&config));
+        assert_eq!(
+            items,
+            vec![
+                ("vim".into(), ComplianceStatus::Remediated),
+                ("telnet".into(), ComplianceStatus::Remediated),
+                ("/etc/motd".into(), ComplianceStatus::Remediated),
+                ("/etc/old.conf".into(), ComplianceStatus::Remediated),
+                ("/etc/../shadow".into(), ComplianceStatus::Failed),
+                ("net.ipv4.ip_forward".into(), ComplianceStatus::Remediated),
+                ("sshd.service".into(), ComplianceStatus::Remediated),
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("etc/motd")).unwrap(),
+            "Managed by Mattrax\n"
+        );
+        assert_eq!(
+            fs::metadata(root.join("etc/motd"))
+                .unwrap()
+                .permissions()
+                .mode()
+                & 0o7777,
+            0o600
+        );
+        assert!(!root.join("etc/old.conf").exists());
+        assert_eq!(
+            fs::read_to_string(root.join("proc/sys/net/ipv4/ip_forward")).unwrap(),
+            "1"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("etc/sysctl.d/90-mattrax-net.ipv4.ip_forward.conf"))
+                .unwrap(),
+            "net.ipv4.ip_forward = 1\n"
+        );
+        assert!(services.is_enabled("sshd.service").unwrap());
+
+        // Applying again is a no-op
+        let items = engine(false).apply(&config);
+        assert!(items
+            .iter()
+            .filter(|item| item.id != "/etc/../shadow")
+            .all(|item| item.status == ComplianceStatus::Compliant));
+
+        // A sysctl set at runtime but lost from `sysctl.d` is persisted again
+        fs::remove_file(root.join("etc/sysctl.d/90-mattrax-net.ipv4.ip_forward.conf")).unwrap();
+        let items = statuses(engine(false).apply(&config));
+        assert!(items.contains(&("net.ipv4.ip_forward".into(), ComplianceStatus::Remediated)));
+        assert!(root
+            .join("etc/sysctl.d/90-mattrax-net.ipv4.ip_forward.conf")
+            .exists());
+    }
+}
@@ -0,0 +1,65 @@
+use std::{path::Path, process::Command};
+
+use super::run_command;
+
+pub trait PackageBackend {
+    fn is_installed(&self, name: &str) -> Result<bool, String>;
+
+    fn install(&self, name: &str) -> Result<(), String>;
+
+    fn remove(&self, name: &str) -> Result<(), String>;
+}
+
+/// Find the package manager for the running distro.
+pub fn detect() -> Option<Box<dyn PackageBackend + Send>> {
+    if Path::new("/usr/bin/apt-get").exists() {
+        Some(Box::new(Apt))
+    } else if Path::new("/usr/bin/dnf").exists() {
+        Some(Box::new(Dnf))
+    } else {
+        None
+    }
+}
+
+pub struct Apt;
+
+impl PackageBackend for Apt {
+    fn is_installed(&self, name: &str) -> Result<bool, String> {
+        // Exits non-zero if dpkg has never heard of the package
+        let output = Command::new("dpkg-query")
+            .args(["-W", "-f=${Status}", name])
+            .output()
+            .map_err(|err| format!("error running \"dpkg-query\": {err}"))?;
+
+        Ok(output.status.success()
+            && String::from_utf8_lossy(&output.stdout).ends_with(" installed"))
+    }
+
+    fn install(&self, name: &str) -> Result<(), String> {
+        run_command("apt-get", &["install", "-y", name]).map(|_| ())
+    }
+
+    fn remove(&self, name: &str) -> Result<(), String> {
+        run_command("apt-get", &["remove", "-y", name]).map(|_| ())
+    }
+}
+
+pub struct Dnf;
+
+impl PackageBackend for Dnf {
+    fn is_installed(&self, name: &str) -> Result<bool, String> {
+        Command::new("rpm")
+            .args(["-q", name])
+            .output()
+            .map(|output| output.status.success())
+            .map_err(|err| format!("error running \"rpm\": {err}"))
+    }
+
+    fn install(&self, name: &str) -> Result<(), String> {
+        run_command("dnf", &["install", "-y", name]).map(|_| ())
+    }
+
+    fn remove(&self, name: &str) -> Result<(), String> {
+        run_command("dnf", &["remove", "-y", name]).map(|_| ())
+    }
+}
@@ -0,0 +1,32 @@
+use std::process::Command;
+
+use super::run_command;
+
+pub trait ServiceBackend {
+    fn is_enabled(&self, unit: &str) -> Result<bool, String>;
+
+    fn set_enabled(&self, unit: &str, enabled: bool) -> Result<(), String>;
+}
+
+pub struct Systemd;
+
+impl ServiceBackend for Systemd {
+    fn is_enabled(&self, unit: &str) -> Result<bool, String> {
+        // Exits non-zero for both disabled and unknown units so we check the output
+        let output = Command::new("systemctl")
+            .args(["is-enabled", "--", unit])
+            .output()
+            .map_err(|err| format!("error running \"systemctl\": {err}"))?;
+
+        match String::from_utf8_lossy(&output.stdout).trim() {
+            "enabled" | "enabled-runtime" => Ok(true),
+            "" => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+            _ => Ok(false),
+        }
+    }
+
+    fn set_enabled(&self, unit: &str, enabled: bool) -> Result<(), String> {
+        let action = if enabled { "enable" } else { "disable" };
+        run_command("systemctl", &[action, "--now", "--", unit]).map(|_| ())
+    }
+}
@@ -0,0 +1,58 @@
+use std::{fs, path::Path};
+
+use super::resolve;
+use crate::file;
+
+// Keys can use either `.` or `/` as the separator, the same as `sysctl(8)`
+fn path(key: &str) -> String {
+    if key.contains('/') {
+        format!("/proc/sys/{key}")
+    } else {
+        format!("/proc/sys/{}", key.replace('.', "/"))
+    }
+}
+
+// Values are also written to `sysctl.d` so they are applied again after a reboot
+fn persisted_path(key: &str) -> String {
+    format!("/etc/sysctl.d/90-mattrax-{}.conf", key.replace('/', "."))
+}
+
+fn persisted(key: &str, value: &str) -> String {
+    format!("{key} = {}\n", normalise(value))
+}
+
+// Multi-value keys are tab separated by the kernel
+fn normalise(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn drift(root: &Path, key: &str, value: &str) -> Result<Vec<String>, String> {
+    let path = resolve(root, &path(key))?;
+    let current = fs::read_to_string(&path).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => format!("unknown sysctl key {key:?}"),
+        _ => format!("error reading {path:?}: {err}"),
+    })?;
+
+    let mut drift = Vec::new();
+    let (current, normalised) = (normalise(&current), normalise(value));
+    if current != normalised {
+        drift.push(format!("{current} -> {normalised}"));
+    }
+
+    let persisted_file = resolve(root, &persisted_path(key))?;
+    if fs::read_to_string(&persisted_file).ok() != Some(persisted(key, value)) {
+        drift.push(format!("not persisted to {}", persisted_path(key)));
+    }
+    Ok(drift)
+}
+
+pub fn apply(root: &Path, key: &str, value: &str) -> Result<(), String> {
+    let path = resolve(root, &path(key))?;
+    fs::write(&path, value).map_err(|err| format!("error writing {path:?}: {err}"))?;
+
+    let path = resolve(root, &persisted_path(key))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| format!("error creating {dir:?}: {err}"))?;
+    }
+    file::write_atomic(&path, persisted(key, value))
+}
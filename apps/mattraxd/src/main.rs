@@ -1,11 +1,34 @@
 use std::path::PathBuf;
 
 use clap::Parser;
-use mx_utils::file_logger;
+use mx_utils::{
+    file_logger::{FileLogger, Logger, Rotation},
+    telemetry::Telemetry,
+};
+use tracing::error;
+use tracing_subscriber::filter::LevelFilter;
 
+mod actions;
+mod checkin;
 mod cli;
+mod client;
+mod config;
+mod ctl;
+mod diagnostics;
+mod file;
+mod identity;
+mod inventory;
+mod linux;
+mod outbox;
+mod scripts;
+mod status;
+mod update;
 
-fn main() {
+const LOG_FILE_BYTES: u64 = 10 * 1024 * 1024;
+const MAX_LOG_BYTES: u64 = 50 * 1024 * 1024;
+
+#[tokio::main]
+async fn main() {
     let cli = cli::Cli::parse();
 
     let data_dir = cli.data_dir.clone().unwrap_or_else(|| {
@@ -16,18 +39,70 @@ fn main() {
         }
     });
 
+    let mut telemetry = Telemetry::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+        .git_hash(env!("GIT_HASH"));
+    if let Ok(Some(config)) = config::Config::load(&data_dir) {
+        telemetry = telemetry.device_id(config.device_id);
+    }
+
     // Only some commands need file-based logging
-    // TODO: `matches!(cli.command, Commands::Serve { .. })`
-    let _guard = if false {
-        Some(file_logger::setup(&data_dir, env!("CARGO_PKG_NAME")))
+    let (logger, _telemetry) = if matches!(cli.command, cli::Commands::Serve(_)) {
+        let level = if cfg!(debug_assertions) {
+            LevelFilter::DEBUG
+        } else {
+            LevelFilter::INFO
+        };
+
+        // Devices can have very little disk space so the logs are capped by size
+        let logger = FileLogger::new(data_dir.join("logs"))
+            .rotation(Rotation::Size(LOG_FILE_BYTES))
+            .max_files(None)
+            .max_total_bytes(Some(MAX_LOG_BYTES))
+            .target_level(env!("CARGO_PKG_NAME"), level)
+            .telemetry(telemetry)
+            .init();
+        match logger {
+            Ok(logger) => (Some(logger), None),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
     } else {
-        tracing_subscriber::fmt().init();
-        None
+        match telemetry.init() {
+            Ok(guard) => (None, Some(guard)),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
     };
 
     std::panic::set_hook(Box::new(move |panic| tracing::error!("{panic}")));
 
-    match cli.command {
-        cli::Commands::Test(cmd) => cmd.run(),
+    let result = match cli.command {
+        cli::Commands::Checkin(cmd) => cmd.run(&data_dir).await,
+        cli::Commands::Ctl(cmd) => cmd.run(&data_dir).await,
+        cli::Commands::Diagnostics(cmd) => cmd.run(&data_dir).await,
+        cli::Commands::Enroll(cmd) => cmd.run(&data_dir).await,
+        cli::Commands::Inventory(cmd) => {
+            cmd.run();
+            Ok(())
+        }
+        cli::Commands::Serve(cmd) => {
+            cmd.run(&data_dir, logger.as_ref().map(Logger::filter))
+                .await
+        }
+        cli::Commands::Status(cmd) => cmd.run(&data_dir),
+        cli::Commands::Test(cmd) => {
+            cmd.run();
+            Ok(())
+        }
+        cli::Commands::Update(cmd) => cmd.run(&data_dir).await,
+    };
+
+    if let Err(err) = result {
+        error!("{err}");
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,302 @@
+//! A durable queue for reports sent to Mattrax, so they aren't lost while the device is offline.
+//!
+//! The queue is an append-only log of JSON lines which is replayed when it's opened.
+//! Each entry has a random id which is sent as the request's idempotency key, so the server can ignore a request it already applied
+//! when the response was lost.
+//! Every record is synced to disk before returning so a crash can at worst leave a partially written last line, which is ignored.
+
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use nix::fcntl::{Flock, FlockArg};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::client::Client;
+
+const DIR: &str = "outbox";
+const LOG_FILE: &str = "log.jsonl";
+/// Locked while the outbox is open. The log is replaced when it's compacted so it can't hold the lock itself.
+const LOCK_FILE: &str = "lock";
+
+/// Oldest entries are dropped once the queued bodies exceed this.
+const MAX_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    /// Random so it stays unique after the log is compacted or deleted.
+    pub id: String,
+    /// Only the latest entry for a key is kept.
+    pub key: String,
+    pub path: String,
+    pub body: serde_json::Value,
+    /// Unix timestamp (in seconds).
+    pub queued_at: u64,
+}
+
+impl Entry {
+    fn size(&self) -> usize {
+        self.body.to_string().len()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+enum Record {
+    Push(Entry),
+    Ack { id: String },
+}
+
+pub struct Outbox {
+    dir: PathBuf,
+    _lock: Flock<File>,
+    file: File,
+    entries: VecDeque<Entry>,
+    // The number of records in the log, used to decide when to compact it.
+    records: usize,
+}
+
+impl Outbox {
+    /// Open the outbox. Only one process can have it open at a time.
+    pub fn open(data_dir: &Path) -> Result<Self, String> {
+        let dir = data_dir.join(DIR);
+        fs::create_dir_all(&dir).map_err(|err| format!("error creating {dir:?}: {err}"))?;
+
+        let path = dir.join(LOCK_FILE);
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|err| format!("error opening {path:?}: {err}"))?;
+        let lock = Flock::lock(lock, FlockArg::LockExclusiveNonblock).map_err(|(_, err)| {
+            format!("error locking {path:?}, is another instance of mattraxd running? {err}")
+        })?;
+
+        let path = dir.join(LOG_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .read(true)
+            .open(&path)
+            .map_err(|err| format!("error opening {path:?}: {err}"))?;
+
+        let (entries, records, torn) = replay(&path)?;
+        let mut this = Self {
+            dir,
+            _lock: lock,
+            file,
+            entries,
+            records,
+        };
+
+        if torn {
+            warn!("Discarding partially written record at the end of the outbox");
+            this.compact()?;
+        }
+
+        Ok(this)
+    }
+
+    /// Read the queued entries without locking the outbox.
+    pub fn pending(data_dir: &Path) -> Result<Vec<Entry>, String> {
+        Ok(replay(&data_dir.join(DIR).join(LOG_FILE))?.0.into())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Queue a request. Any queued entry with the same key is replaced.
+    pub fn push<T: Serialize + ?Sized>(
+        &mut self,
+        key: &str,
+        path: &str,
+        body: &T,
+    ) -> Result<(), String> {
+        let entry = Entry {
+            id: format!("{:032x}", rand::random::<u128>()),
+            key: key.to_string(),
+            path: path.to_string(),
+            body: serde_json::to_value(body)
+                .map_err(|err| format!("error encoding outbox entry: {err}"))?,
+            queued_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+
+        self.append(&Record::Push(entry.clone()))?;
+        self.entries.retain(|e| e.key != entry.key);
+        self.entries.push_back(entry);
+
+        let mut size: usize = self.entries.iter().map(Entry::size).sum();
+        while size > MAX_BYTES && self.entries.len() > 1 {
+            let Some(oldest) = self.entries.front().cloned() else {
+                break;
+            };
+            warn!("Outbox is full, dropping {:?}", oldest.key);
+            self.ack(&oldest.id)?;
+            size -= oldest.size();
+        }
+
+        Ok(())
+    }
+
+    /// Send everything in the queue in order. Stops at the first request which fails so it can be retried later.
+    ///
+    /// Requests which the server rejects are dropped as retrying won't help.
+    pub async fn drain(&mut self, client: &Client) -> Result<usize, String> {
+        let mut sent = 0;
+
+        while let Some(entry) = self.entries.front().cloned() {
+            if client.send(&entry.path, &entry.body, &entry.id).await? {
+                debug!("Sent {:?} from the outbox", entry.key);
+                sent += 1;
+            } else {
+                warn!("Server rejected {:?}, dropping it", entry.key);
+            }
+            self.ack(&entry.id)?;
+        }
+
+        // Nothing left so the log can be truncated
+        if (self.records > 0 && self.entries.is_empty())
+            || self.records > 2 * self.entries.len() + 64
+        {
+            self.compact()?;
+        }
+
+        Ok(sent)
+    }
+
+    fn ack(&mut self, id: &str) -> Result<(), String> {
+        self.append(&Record::Ack { id: id.to_string() })?;
+        self.entries.retain(|e| e.id != id);
+        Ok(())
+    }
+
+    fn append(&mut self, record: &Record) -> Result<(), String> {
+        let mut line = serde_json::to_vec(record).unwrap();
+        line.push(b'\n');
+
+        self.file
+            .write_all(&line)
+            .and_then(|_| self.file.sync_data())
+            .map_err(|err| format!("error writing outbox: {err}"))?;
+        self.records += 1;
+        Ok(())
+    }
+
+    /// Rewrite the log with only the queued entries.
+    fn compact(&mut self) -> Result<(), String> {
+        let (path, tmp) = (
+            self.dir.join(LOG_FILE),
+            self.dir.join(format!("{LOG_FILE}.tmp")),
+        );
+
+        let mut contents = Vec::new();
+        for entry in &self.entries {
+            serde_json::to_writer(&mut contents, &Record::Push(entry.clone())).unwrap();
+            contents.push(b'\n');
+        }
+
+        let mut file =
+            File::create(&tmp).map_err(|err| format!("error creating {tmp:?}: {err}"))?;
+        file.write_all(&contents)
+            .and_then(|_| file.sync_all())
+            .map_err(|err| format!("error writing {tmp:?}: {err}"))?;
+        fs::rename(&tmp, &path).map_err(|err| format!("error renaming {tmp:?}: {err}"))?;
+
+        self.file = OpenOptions::new()
+            .append(true)
+            .read(true)
+            .open(&path)
+            .map_err(|err| format!("error opening {path:?}: {err}"))?;
+        self.records = self.entries.len();
+        Ok(())
+    }
+}
+
+/// Returns the queued entries, the number of records and whether the last record was partially written.
+fn replay(path: &Path) -> Result<(VecDeque<Entry>, usize, bool), String> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Default::default()),
+        Err(err) => return Err(format!("error reading {path:?}: {err}")),
+    };
+
+    let mut entries = VecDeque::<Entry>::new();
+    let mut records = 0;
+    for line in contents.split(|b| *b == b'\n').filter(|l| !l.is_empty()) {
+        match serde_json::from_slice::<Record>(line) {
+            Ok(Record::Push(entry)) => {
+                entries.retain(|e| e.key != entry.key);
+                entries.push_back(entry);
+            }
+            Ok(Record::Ack { id }) => entries.retain(|e| e.id != id),
+            // Only the last line can be partially written
+            Err(_) => return Ok((entries, records, true)),
+        }
+        records += 1;
+    }
+
+    Ok((entries, records, false))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_outbox() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let mut outbox = Outbox::open(dir).unwrap();
+        outbox.push("inventory", "/agent/inventory", &1).unwrap();
+        outbox.push("a", "/agent/a", &"a").unwrap();
+        outbox.push("inventory", "/agent/inventory", &2).unwrap();
+        let id = outbox.entries[0].id.clone();
+        outbox.ack(&id).unwrap();
+        assert!(Outbox::open(dir).is_err(), "outbox should be locked");
+        drop(outbox);
+
+        // Simulate a crash part way through writing a record
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(dir.join(DIR).join(LOG_FILE))
+            .unwrap();
+        file.write_all(br#"{"op":"push","id":"9","#).unwrap();
+
+        let outbox = Outbox::open(dir).unwrap();
+        assert_eq!(
+            outbox
+                .entries
+                .iter()
+                .map(|e| (e.key.as_str(), e.body.clone()))
+                .collect::<Vec<_>>(),
+            vec![("inventory", serde_json::json!(2))]
+        );
+        drop(outbox);
+        assert_eq!(Outbox::pending(dir).unwrap().len(), 1);
+
+        // Ids aren't reused once the log is compacted
+        let mut outbox = Outbox::open(dir).unwrap();
+        let before = outbox.entries[0].id.clone();
+        outbox.entries.clear();
+        outbox.compact().unwrap();
+        outbox.push("inventory", "/agent/inventory", &3).unwrap();
+        assert_ne!(outbox.entries[0].id, before);
+
+        // Compacting replaces the log but the outbox stays locked
+        assert!(Outbox::open(dir).is_err(), "outbox should be locked");
+        drop(outbox);
+        assert_eq!(Outbox::pending(dir).unwrap().len(), 1);
+    }
+}
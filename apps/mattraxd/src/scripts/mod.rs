@@ -0,0 +1,224 @@
+//! Run the scripts from the policies assigned to the device.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use mx_agent_api::{DeployedPolicy, ScriptResult};
+use mx_policy::{Platform, Script, Shell, Trigger};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, warn};
+
+use crate::file;
+
+mod runner;
+
+pub use runner::Limits;
+
+const STATE_FILE: &str = "scripts.json";
+
+/// Why the scripts are being run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Checkin,
+    /// The first check-in after the agent started.
+    Startup,
+}
+
+/// Keeps track of the scripts which only run once.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    /// Hash of the script to when it was run (Unix timestamp in seconds).
+    completed: BTreeMap<String, u64>,
+}
+
+impl State {
+    fn load(data_dir: &Path) -> Result<Self, String> {
+        let path = data_dir.join(STATE_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents =
+            fs::read_to_string(&path).map_err(|err| format!("error reading {path:?}: {err}"))?;
+        serde_json::from_str(&contents).map_err(|err| format!("error decoding {path:?}: {err}"))
+    }
+
+    fn save(&self, data_dir: &Path) -> Result<(), String> {
+        file::write_atomic(
+            &data_dir.join(STATE_FILE),
+            serde_json::to_string_pretty(self).unwrap(),
+        )
+    }
+}
+
+/// Identifies a script by its contents so modifying a script will cause it to run again.
+pub fn hash(script: &Script) -> String {
+    let shell = match script.shell {
+        Shell::Powershell => "powershell",
+        Shell::Bash => "bash",
+        Shell::Zsh => "zsh",
+    };
+
+    Sha256::digest(format!("{shell}\0{}", script.run))
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+fn should_run(script: &Script, hash: &str, state: &State, event: Event) -> bool {
+    if let Some(supported) = &script.supported {
+        if !supported.contains(&Platform::Linux) {
+            return false;
+        }
+    }
+
+    match script.trigger.unwrap_or_default() {
+        Trigger::Once | Trigger::EnrollmentComplete => !state.completed.contains_key(hash),
+        Trigger::Checkin => true,
+        Trigger::Startup => event == Event::Startup,
+        // TODO: Support these triggers
+        Trigger::Login | Trigger::Logout | Trigger::NetworkStateChange => false,
+    }
+}
+
+/// Run the scripts from a policy which are triggered by `event`.
+pub async fn run(
+    data_dir: &Path,
+    policy: &DeployedPolicy,
+    event: Event,
+    limits: &Limits,
+) -> Result<Vec<ScriptResult>, String> {
+    let mut state = State::load(data_dir)?;
+    let work_dir = data_dir.join("scripts");
+    let mut results = Vec::new();
+
+    for script in &policy.scripts {
+        let hash = hash(script);
+        if !should_run(script, &hash, &state, event) {
+            debug!("Skipping script {hash} from policy {:?}", policy.policy_id);
+            continue;
+        }
+
+        info!("Running script {hash} from policy {:?}", policy.policy_id);
+        let result = runner::run(script.shell, &script.run, hash.clone(), &work_dir, limits).await;
+        if result.is_success() {
+            info!("Script {hash} completed in {}ms", result.duration_ms);
+        } else {
+            warn!(
+                "Script {hash} failed with exit code {:?}{}{}",
+                result.exit_code,
+                if result.timed_out { " (timed out)" } else { "" },
+                result
+                    .error
+                    .as_ref()
+                    .map(|err| format!(": {err}"))
+                    .unwrap_or_default()
+            );
+        }
+
+        // A failed script is tried again on the next check-in
+        if result.is_success() {
+            state.completed.insert(
+                hash,
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+            );
+            // Save as we go so a crash doesn't cause scripts to run again
+            state.save(data_dir)?;
+        }
+
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+/// Run a script immediately, regardless of its trigger or whether it has run before.
+pub async fn run_now(data_dir: &Path, script: &Script, limits: &Limits) -> ScriptResult {
+    let hash = hash(script);
+    info!("Running script {hash}");
+    runner::run(
+        script.shell,
+        &script.run,
+        hash,
+        &data_dir.join("scripts"),
+        limits,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_should_run() {
+        let script = |trigger, supported| Script {
+            shell: Shell::Bash,
+            supported,
+            trigger,
+            run: "true".into(),
+        };
+        let mut state = State::default();
+
+        let once = script(None, None);
+        let hash = hash(&once);
+        assert!(should_run(&once, &hash, &state, Event::Checkin));
+        state.completed.insert(hash.clone(), 0);
+        assert!(!should_run(&once, &hash, &state, Event::Checkin));
+
+        let checkin = script(Some(Trigger::Checkin), None);
+        state.completed.insert(super::hash(&checkin), 0);
+        assert!(should_run(
+            &checkin,
+            &super::hash(&checkin),
+            &state,
+            Event::Checkin
+        ));
+
+        let startup = script(Some(Trigger::Startup), None);
+        assert!(!should_run(&startup, "", &state, Event::Checkin));
+        assert!(should_run(&startup, "", &state, Event::Startup));
+
+        let windows = script(Some(Trigger::Checkin), Some(vec![Platform::Windows]));
+        assert!(!should_run(&windows, "", &state, Event::Checkin));
+    }
+
+    #[tokio::test]
+    async fn test_failed_once_script_runs_again() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let policy = |run: &str| DeployedPolicy {
+            deploy_id: "deploy".into(),
+            policy_id: "policy".into(),
+            name: "Policy".into(),
+            scripts: vec![Script {
+                shell: Shell::Bash,
+                supported: None,
+                trigger: Some(Trigger::Once),
+                run: run.into(),
+            }],
+            linux: None,
+        };
+        let run = |policy| async move {
+            super::run(dir, &policy, Event::Checkin, &Limits::default())
+                .await
+                .unwrap()
+                .len()
+        };
+
+        assert_eq!(run(policy("exit 1")).await, 1);
+        assert_eq!(run(policy("exit 1")).await, 1);
+
+        assert_eq!(run(policy("true")).await, 1);
+        assert_eq!(run(policy("true")).await, 0);
+        assert_eq!(State::load(dir).unwrap().completed.len(), 1);
+    }
+}
@@ -0,0 +1,281 @@
+//! Run a single script in a child process with a timeout, resource limits and capped output.
+
+use std::{
+    io,
+    os::unix::{fs::PermissionsExt, process::ExitStatusExt},
+    path::Path,
+    pin::pin,
+    process::Stdio,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use mx_agent_api::ScriptResult;
+use mx_policy::Shell;
+use nix::{
+    sys::{
+        resource::{getrlimit, setrlimit, Resource},
+        signal::{killpg, Signal},
+    },
+    unistd::Pid,
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    process::Command,
+};
+
+// How long to wait for output after the script exits. Only matters if a process escaped the process group.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Wall clock time after which the script is killed.
+    pub timeout: Duration,
+    /// Maximum bytes captured from each of stdout and stderr.
+    pub max_output_bytes: usize,
+    pub max_cpu_seconds: u64,
+    pub max_file_size_bytes: u64,
+    pub max_open_files: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10 * 60),
+            max_output_bytes: 64 * 1024,
+            max_cpu_seconds: 10 * 60,
+            max_file_size_bytes: 4 * 1024 * 1024 * 1024,
+            max_open_files: 4096,
+        }
+    }
+}
+
+/// Run a script. Any processes left behind by the script are killed once it exits.
+///
+/// The script is written into `work_dir` as the interpreter may not support reading it from an argument or stdin.
+pub async fn run(
+    shell: Shell,
+    source: &str,
+    hash: String,
+    work_dir: &Path,
+    limits: &Limits,
+) -> ScriptResult {
+    let started_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let start = Instant::now();
+
+    let mut result = ScriptResult {
+        hash,
+        exit_code: None,
+        stdout: String::new(),
+        stderr: String::new(),
+        truncated: false,
+        timed_out: false,
+        error: None,
+        started_at,
+        duration_ms: 0,
+    };
+
+    if let Err(err) = execute(shell, source, work_dir, limits, &mut result).await {
+        result.error = Some(err);
+    }
+
+    result.duration_ms = start.elapsed().as_millis() as u64;
+    result
+}
+
+async fn execute(
+    shell: Shell,
+    source: &str,
+    work_dir: &Path,
+    limits: &Limits,
+    result: &mut ScriptResult,
+) -> Result<(), String> {
+    let (program, args, extension): (_, &[&str], _) = match shell {
+        Shell::Bash => ("bash", &[], "sh"),
+        Shell::Zsh => ("zsh", &[], "zsh"),
+        Shell::Powershell => ("pwsh", &["-NoProfile", "-NonInteractive", "-File"], "ps1"),
+    };
+
+    std::fs::create_dir_all(work_dir)
+        .and_then(|_| std::fs::set_permissions(work_dir, std::fs::Permissions::from_mode(0o700)))
+        .map_err(|err| format!("error creating {work_dir:?}: {err}"))?;
+    let path = work_dir.join(format!(
+        "script-{}-{}.{extension}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&path, source).map_err(|err| format!("error writing {path:?}: {err}"))?;
+
+    let output = spawn(program, args, &path, limits, result).await;
+    let _ = std::fs::remove_file(&path);
+    output
+}
+
+async fn spawn(
+    program: &str,
+    args: &[&str],
+    path: &Path,
+    limits: &Limits,
+    result: &mut ScriptResult,
+) -> Result<(), String> {
+    let rlimits = [
+        (Resource::RLIMIT_CPU, limits.max_cpu_seconds),
+        (Resource::RLIMIT_FSIZE, limits.max_file_size_bytes),
+        (Resource::RLIMIT_NOFILE, limits.max_open_files),
+        (Resource::RLIMIT_CORE, 0),
+    ];
+
+    let mut cmd = Command::new(program);
+    cmd.args(args)
+        .arg(path)
+        .current_dir("/")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // So we can kill everything the script started
+        .process_group(0)
+        .kill_on_drop(true);
+
+    // SAFETY: `getrlimit` and `setrlimit` are async-signal-safe and we don't allocate.
+    unsafe {
+        cmd.pre_exec(move || {
+            for (resource, limit) in rlimits {
+                // Only root can raise the hard limit
+                let (_, hard) = getrlimit(resource)?;
+                let limit = limit.min(hard);
+                setrlimit(resource, limit, limit)?;
+            }
+            Ok(())
+        });
+    }
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|err| format!("error starting {program:?}: {err}"))?;
+    let pid = child.id().map(|pid| Pid::from_raw(pid as i32));
+
+    let (mut stdout, mut stderr) = (Capture::default(), Capture::default());
+    let (stdout_pipe, stderr_pipe) = (child.stdout.take(), child.stderr.take());
+    let status = {
+        let mut reading = pin!(async {
+            tokio::join!(
+                stdout.read_from(stdout_pipe, limits.max_output_bytes),
+                stderr.read_from(stderr_pipe, limits.max_output_bytes),
+            )
+        });
+        let mut deadline = pin!(tokio::time::sleep(limits.timeout));
+        let mut done_reading = false;
+
+        let status = loop {
+            tokio::select! {
+                _ = &mut reading, if !done_reading => done_reading = true,
+                status = child.wait() => break Some(status),
+                _ = &mut deadline => break None,
+            }
+        };
+
+        if let Some(pid) = pid {
+            let _ = killpg(pid, Signal::SIGKILL);
+        }
+        if !done_reading {
+            let _ = tokio::time::timeout(DRAIN_TIMEOUT, reading).await;
+        }
+
+        status
+    };
+
+    result.stdout = String::from_utf8_lossy(&stdout.buf).to_string();
+    result.stderr = String::from_utf8_lossy(&stderr.buf).to_string();
+    result.truncated = stdout.truncated || stderr.truncated;
+
+    match status {
+        Some(status) => {
+            let status = status.map_err(|err| format!("error waiting for script: {err}"))?;
+            result.exit_code = status.code();
+            if let Some(signal) = status.signal() {
+                return Err(format!("script was terminated by signal {signal}"));
+            }
+        }
+        None => {
+            result.timed_out = true;
+            let _ = child.wait().await;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Capture {
+    buf: Vec<u8>,
+    truncated: bool,
+}
+
+impl Capture {
+    // We keep draining the pipe after the limit so the script doesn't block on a full pipe.
+    async fn read_from(&mut self, reader: Option<impl AsyncRead + Unpin>, limit: usize) {
+        let Some(mut reader) = reader else {
+            return;
+        };
+
+        let mut chunk = [0; 8192];
+        loop {
+            match reader.read(&mut chunk).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    let remaining = limit.saturating_sub(self.buf.len());
+                    if n > remaining {
+                        self.truncated = true;
+                    }
+                    self.buf.extend_from_slice(&chunk[..n.min(remaining)]);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn run_bash(source: &str, limits: &Limits) -> ScriptResult {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        run(Shell::Bash, source, "test".into(), dir, limits).await
+    }
+
+    #[tokio::test]
+    async fn test_exit_code_and_output() {
+        let result = run_bash("echo hello; echo oops >&2; exit 3", &Limits::default()).await;
+
+        assert_eq!(result.error, None);
+        assert_eq!(result.exit_code, Some(3));
+        assert_eq!(result.stdout, "hello\n");
+        assert_eq!(result.stderr, "oops\n");
+        assert!(!result.is_success());
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        let limits = Limits {
+            timeout: Duration::from_millis(500),
+            max_output_bytes: 16,
+            ..Default::default()
+        };
+
+        let result = run_bash("head -c 100000 /dev/zero | tr '\\0' a", &limits).await;
+        assert!(result.truncated);
+        assert_eq!(result.stdout.len(), 16);
+
+        let result = run_bash("sleep 30 & sleep 30", &limits).await;
+        assert!(result.timed_out);
+        assert!(result.duration_ms < 5000);
+    }
+}
@@ -0,0 +1,63 @@
+//! The agent's connection status, shown by `mattraxd status`.
+
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::file;
+
+const FILE: &str = "status.json";
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Status {
+    /// Unix timestamp (in seconds) of the last successful check-in.
+    pub last_checkin: Option<u64>,
+    /// Unix timestamp (in seconds) of the last check-in attempt.
+    pub last_attempt: Option<u64>,
+    /// The error from the last check-in attempt if it failed.
+    pub last_error: Option<String>,
+}
+
+impl Status {
+    pub fn load(data_dir: &Path) -> Result<Self, String> {
+        let path = data_dir.join(FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents =
+            fs::read_to_string(&path).map_err(|err| format!("error reading {path:?}: {err}"))?;
+        serde_json::from_str(&contents).map_err(|err| format!("error decoding {path:?}: {err}"))
+    }
+
+    /// Record the outcome of a check-in.
+    pub fn record(data_dir: &Path, result: &Result<(), String>) -> Result<(), String> {
+        let mut status = Self::load(data_dir).unwrap_or_default();
+        let now = now();
+        status.last_attempt = Some(now);
+        match result {
+            Ok(()) => {
+                status.last_checkin = Some(now);
+                status.last_error = None;
+            }
+            Err(err) => status.last_error = Some(err.clone()),
+        }
+
+        file::write_atomic(
+            &data_dir.join(FILE),
+            serde_json::to_string_pretty(&status).unwrap(),
+        )
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
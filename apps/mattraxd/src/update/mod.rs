@@ -0,0 +1,578 @@
+//! Update the agent binary from signed release manifests.
+//!
+//! The manifest is signed by the Mattrax release key, which is built into the binary, so a compromised server can't push arbitrary binaries.
+//! The running executable is atomically replaced and the previous one is kept until the new version has checked in successfully.
+//! If it doesn't, or it keeps crashing, the previous version is restored and the release won't be installed again.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::{
+        fs::{OpenOptionsExt, PermissionsExt},
+        process::CommandExt,
+    },
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ed25519_dalek::{Signature, VerifyingKey};
+use mx_agent_api::{Artifact, ReleaseManifest, SignedManifest};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::{client::Client, config::Config, file, status};
+
+pub const DEFAULT_CHANNEL: &str = "stable";
+
+const STATE_FILE: &str = "update.json";
+const MAX_BINARY_BYTES: u64 = 256 * 1024 * 1024;
+/// How many times the new version can start without checking in before it's rolled back.
+const MAX_BOOTS: u32 = 3;
+/// How long the new version has to check in after it starts before it's rolled back.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Comma separated base64 encoded Ed25519 public keys, set when building release binaries.
+/// Multiple keys allow the release key to be rotated.
+const PUBLIC_KEYS: Option<&str> = option_env!("MATTRAXD_UPDATE_PUBLIC_KEYS");
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct State {
+    pending: Option<Pending>,
+    /// Versions which were rolled back so they aren't installed again.
+    #[serde(default)]
+    rejected: Vec<String>,
+}
+
+/// An installed update which hasn't passed the health check yet.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Pending {
+    version: String,
+    exe: PathBuf,
+    backup: PathBuf,
+    /// Unix timestamp (in seconds). Only informational, the health check is timed from when the new version starts.
+    installed_at: u64,
+    boots: u32,
+}
+
+impl State {
+    fn load(data_dir: &Path) -> Result<Self, String> {
+        let path = data_dir.join(STATE_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents =
+            fs::read_to_string(&path).map_err(|err| format!("error reading {path:?}: {err}"))?;
+        serde_json::from_str(&contents).map_err(|err| format!("error decoding {path:?}: {err}"))
+    }
+
+    fn save(&self, data_dir: &Path) -> Result<(), String> {
+        file::write_atomic(
+            &data_dir.join(STATE_FILE),
+            serde_json::to_string_pretty(self).unwrap(),
+        )
+    }
+}
+
+/// An update which was installed over the executable.
+pub struct Installed {
+    pub version: String,
+    /// The path of the executable, resolved before it was replaced, to [`restart`] into.
+    pub exe: PathBuf,
+}
+
+/// Whether this build can update itself.
+pub fn is_supported() -> bool {
+    PUBLIC_KEYS.is_some()
+}
+
+/// Check the device's channel for a new release and install it.
+pub async fn check(
+    data_dir: &Path,
+    client: &Client,
+    config: &Config,
+) -> Result<Option<Installed>, String> {
+    let channel = config.channel.as_deref().unwrap_or(DEFAULT_CHANNEL);
+    let Some(signed) = client.release_manifest(channel).await? else {
+        return Ok(None);
+    };
+
+    Updater::new(data_dir, &config.device_id)?
+        .update(&signed, channel)
+        .await
+}
+
+pub struct Updater {
+    data_dir: PathBuf,
+    trusted_keys: Vec<VerifyingKey>,
+    exe: PathBuf,
+    version: Version,
+    target: String,
+    device_id: String,
+}
+
+impl Updater {
+    /// Create an updater for the running executable.
+    pub fn new(data_dir: &Path, device_id: &str) -> Result<Self, String> {
+        let keys = PUBLIC_KEYS.ok_or("This build of mattraxd doesn't support updates")?;
+        let trusted_keys = keys
+            .split(',')
+            .map(|key| {
+                STANDARD
+                    .decode(key.trim())
+                    .ok()
+                    .and_then(|key| key.try_into().ok())
+                    .and_then(|key| VerifyingKey::from_bytes(&key).ok())
+                    .ok_or_else(|| format!("invalid update public key {key:?}"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            data_dir: data_dir.to_path_buf(),
+            trusted_keys,
+            exe: std::env::current_exe()
+                .map_err(|err| format!("error getting current executable: {err}"))?,
+            version: Version::parse(env!("CARGO_PKG_VERSION")).unwrap(),
+            target: format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS),
+            device_id: device_id.to_string(),
+        })
+    }
+
+    /// Install the release if it's newer than the running version and the device is part of the rollout.
+    ///
+    /// Returns the installed version. The new version only runs after [`restart`].
+    pub async fn update(
+        &self,
+        signed: &SignedManifest,
+        channel: &str,
+    ) -> Result<Option<Installed>, String> {
+        let manifest = self.verify(signed)?;
+        let Some(artifact) = self.select(&manifest, channel)? else {
+            return Ok(None);
+        };
+
+        info!(
+            "Downloading mattraxd {} from {:?}",
+            manifest.version, artifact.url
+        );
+        let binary = download(artifact).await?;
+        self.install(&manifest.version, &binary)?;
+
+        info!("Installed mattraxd {}", manifest.version);
+        Ok(Some(Installed {
+            version: manifest.version,
+            exe: self.exe.clone(),
+        }))
+    }
+
+    fn verify(&self, signed: &SignedManifest) -> Result<ReleaseManifest, String> {
+        let signature = STANDARD
+            .decode(&signed.signature)
+            .ok()
+            .and_then(|sig| Signature::from_slice(&sig).ok())
+            .ok_or("invalid manifest signature")?;
+
+        if !self.trusted_keys.iter().any(|key| {
+            key.verify_strict(signed.manifest.as_bytes(), &signature)
+                .is_ok()
+        }) {
+            return Err("manifest is not signed by a trusted key".into());
+        }
+
+        serde_json::from_str(&signed.manifest)
+            .map_err(|err| format!("error decoding manifest: {err}"))
+    }
+
+    fn select<'a>(
+        &self,
+        manifest: &'a ReleaseManifest,
+        channel: &str,
+    ) -> Result<Option<&'a Artifact>, String> {
+        // Otherwise a signed manifest from a pre-release channel could be served to every device
+        if manifest.channel != channel {
+            return Err(format!(
+                "manifest is for channel {:?} not {channel:?}",
+                manifest.channel
+            ));
+        }
+
+        let version = Version::parse(&manifest.version)
+            .map_err(|err| format!("invalid version {:?}: {err}", manifest.version))?;
+        if version <= self.version
+            || State::load(&self.data_dir)?
+                .rejected
+                .contains(&manifest.version)
+        {
+            return Ok(None);
+        }
+
+        // Each device gets a stable bucket per release so increasing the rollout only adds devices
+        let hash = Sha256::digest(format!("{}\0{}", self.device_id, manifest.version));
+        let bucket = u16::from_be_bytes([hash[0], hash[1]]) % 100;
+        if bucket >= manifest.rollout as u16 {
+            info!(
+                "Not yet installing mattraxd {} due to staged rollout",
+                manifest.version
+            );
+            return Ok(None);
+        }
+
+        manifest
+            .artifacts
+            .iter()
+            .find(|artifact| artifact.target == self.target)
+            .map(Some)
+            .ok_or_else(|| format!("release has no binary for {:?}", self.target))
+    }
+
+    fn install(&self, version: &str, binary: &[u8]) -> Result<(), String> {
+        let dir = self
+            .exe
+            .parent()
+            .ok_or("executable has no parent directory")?;
+        let name = self.exe.file_name().unwrap_or_default().to_string_lossy();
+        // Everything is in the same directory as the executable so the renames are atomic
+        let (new, backup) = (
+            dir.join(format!(".{name}.new")),
+            dir.join(format!(".{name}.previous")),
+        );
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o755)
+            .open(&new)
+            .map_err(|err| format!("error creating {new:?}: {err}"))?;
+        file.write_all(binary)
+            .and_then(|_| file.sync_all())
+            .map_err(|err| format!("error writing {new:?}: {err}"))?;
+        drop(file);
+        fs::set_permissions(&new, fs::Permissions::from_mode(0o755))
+            .map_err(|err| format!("error setting permissions of {new:?}: {err}"))?;
+
+        // Catch binaries which can't run on this system before they replace the working one
+        let status = Command::new(&new)
+            .arg("--version")
+            .output()
+            .map_err(|err| format!("error running new binary: {err}"))?
+            .status;
+        if !status.success() {
+            let _ = fs::remove_file(&new);
+            return Err(format!("new binary failed to run: {status}"));
+        }
+
+        let _ = fs::remove_file(&backup);
+        fs::hard_link(&self.exe, &backup)
+            .or_else(|_| fs::copy(&self.exe, &backup).map(|_| ()))
+            .map_err(|err| format!("error backing up {:?}: {err}", self.exe))?;
+
+        // Save first so if we crash after the swap the update is still checked
+        let mut state = State::load(&self.data_dir)?;
+        state.pending = Some(Pending {
+            version: version.to_string(),
+            exe: self.exe.clone(),
+            backup,
+            installed_at: status::now(),
+            boots: 0,
+        });
+        state.save(&self.data_dir)?;
+
+        fs::rename(&new, &self.exe).map_err(|err| format!("error replacing {:?}: {err}", self.exe))
+    }
+}
+
+async fn download(artifact: &Artifact) -> Result<Vec<u8>, String> {
+    if artifact.size > MAX_BINARY_BYTES {
+        return Err(format!("binary is too large ({} bytes)", artifact.size));
+    }
+
+    let resp = reqwest::get(&artifact.url)
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|err| format!("error downloading update: {err}"))?;
+    let binary = resp
+        .bytes()
+        .await
+        .map_err(|err| format!("error downloading update: {err}"))?;
+
+    if binary.len() as u64 != artifact.size {
+        return Err(format!(
+            "downloaded {} bytes but expected {}",
+            binary.len(),
+            artifact.size
+        ));
+    }
+    let hash = Sha256::digest(&binary)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    if !hash.eq_ignore_ascii_case(&artifact.sha256) {
+        return Err(format!(
+            "checksum mismatch, got {hash} expected {}",
+            artifact.sha256
+        ));
+    }
+
+    Ok(binary.to_vec())
+}
+
+/// Replace the current process with the executable at `exe`, keeping the same arguments.
+///
+/// The path must be resolved before the executable is replaced, as afterwards [`std::env::current_exe`] returns
+/// the deleted file on Linux.
+pub fn restart(exe: &Path) -> String {
+    format!("error restarting: {}", restart_command(exe).exec())
+}
+
+fn restart_command(exe: &Path) -> Command {
+    let mut command = Command::new(exe);
+    command.args(std::env::args_os().skip(1));
+    command
+}
+
+/// Called when the agent starts. Rolls back an update which keeps crashing before it passes its health check.
+///
+/// Returns the executable to [`restart`] into if it was rolled back.
+pub fn startup(data_dir: &Path) -> Result<Option<PathBuf>, String> {
+    let mut state = State::load(data_dir)?;
+    let Some(pending) = &mut state.pending else {
+        return Ok(None);
+    };
+
+    pending.boots += 1;
+    if pending.boots > MAX_BOOTS {
+        warn!(
+            "mattraxd {} keeps restarting without checking in, rolling back",
+            pending.version
+        );
+        return rollback(data_dir).map(Some);
+    }
+
+    state.save(data_dir)?;
+    Ok(None)
+}
+
+/// When the installed update must have checked in by, or `None` if there is no update waiting for its health check.
+///
+/// This is measured from when the process `started` so time the device spent turned off doesn't count.
+pub fn health_check_deadline(data_dir: &Path, started: Instant) -> Option<Instant> {
+    is_pending(data_dir).then(|| started + HEALTH_CHECK_TIMEOUT)
+}
+
+/// Roll back an update which didn't check in before its [`health_check_deadline`].
+///
+/// Returns the executable to [`restart`] into.
+pub fn fail_health_check(data_dir: &Path) -> Result<PathBuf, String> {
+    if let Ok(State {
+        pending: Some(pending),
+        ..
+    }) = State::load(data_dir)
+    {
+        warn!(
+            "mattraxd {} didn't check in within {}s, rolling back",
+            pending.version,
+            HEALTH_CHECK_TIMEOUT.as_secs()
+        );
+    }
+    rollback(data_dir)
+}
+
+/// Whether there is an installed update waiting for its health check.
+pub fn is_pending(data_dir: &Path) -> bool {
+    State::load(data_dir)
+        .map(|state| state.pending.is_some())
+        .unwrap_or(false)
+}
+
+/// Mark the installed update as healthy, removing the previous version.
+pub fn confirm(data_dir: &Path) -> Result<(), String> {
+    let mut state = State::load(data_dir)?;
+    let Some(pending) = state.pending.take() else {
+        return Ok(());
+    };
+
+    let _ = fs::remove_file(&pending.backup);
+    state.save(data_dir)?;
+    info!("mattraxd {} passed its health check", pending.version);
+    Ok(())
+}
+
+/// Restore the previous version and prevent the update from being installed again.
+///
+/// Returns the path of the restored executable.
+pub fn rollback(data_dir: &Path) -> Result<PathBuf, String> {
+    let mut state = State::load(data_dir)?;
+    let Some(pending) = state.pending.take() else {
+        return Err("there is no update to roll back".into());
+    };
+
+    fs::rename(&pending.backup, &pending.exe)
+        .map_err(|err| format!("error restoring {:?}: {err}", pending.backup))?;
+    state.rejected.push(pending.version);
+    state.save(data_dir)?;
+    Ok(pending.exe)
+}
+
+#[cfg(test)]
+mod test {
+    use ed25519_dalek::{Signer, SigningKey};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    const OLD: &str = "#!/bin/sh\necho mattraxd 0.0.1\n";
+    const NEW: &str = "#!/bin/sh\necho mattraxd 99.0.0\n";
+
+    // Serves `body` in response to every request
+    async fn serve(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let _ = stream.read(&mut [0; 4096]).await;
+                let _ = stream
+                    .write_all(
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                            body.len()
+                        )
+                        .as_bytes(),
+                    )
+                    .await;
+            }
+        });
+        format!("http://{addr}/mattraxd")
+    }
+
+    // Runs the executable the same way `restart` would, except as a child process
+    fn run(exe: &Path) -> String {
+        let output = restart_command(exe).output().unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn sign(key: &SigningKey, manifest: &ReleaseManifest) -> SignedManifest {
+        let manifest = serde_json::to_string(manifest).unwrap();
+        SignedManifest {
+            signature: STANDARD.encode(key.sign(manifest.as_bytes()).to_bytes()),
+            manifest,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let exe = dir.join("mattraxd");
+        fs::write(&exe, OLD).unwrap();
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let updater = Updater {
+            data_dir: dir.to_path_buf(),
+            trusted_keys: vec![key.verifying_key()],
+            exe: exe.clone(),
+            version: Version::new(0, 0, 1),
+            target: "x86_64-linux".into(),
+            device_id: "device".into(),
+        };
+        let mut manifest = ReleaseManifest {
+            version: "99.0.0".into(),
+            channel: "stable".into(),
+            rollout: 100,
+            artifacts: vec![Artifact {
+                target: "x86_64-linux".into(),
+                url: serve(NEW).await,
+                size: NEW.len() as u64,
+                sha256: Sha256::digest(NEW)
+                    .iter()
+                    .map(|b| format!("{b:02x}"))
+                    .collect(),
+            }],
+        };
+
+        // Untrusted key, wrong channel and tampered binary
+        let untrusted = sign(&SigningKey::from_bytes(&[8; 32]), &manifest);
+        assert!(updater.update(&untrusted, "stable").await.is_err());
+        assert!(updater
+            .update(&sign(&key, &manifest), "beta")
+            .await
+            .is_err());
+        manifest.artifacts[0].sha256 = "00".repeat(32);
+        assert!(updater
+            .update(&sign(&key, &manifest), "stable")
+            .await
+            .is_err());
+        assert_eq!(fs::read_to_string(&exe).unwrap(), OLD);
+
+        manifest.artifacts[0].sha256 = Sha256::digest(NEW)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        let signed = sign(&key, &manifest);
+        let installed = updater.update(&signed, "stable").await.unwrap().unwrap();
+        assert_eq!(installed.version, "99.0.0");
+        assert_eq!(fs::read_to_string(&exe).unwrap(), NEW);
+        assert!(is_pending(dir));
+        // The path still runs the new binary after it was renamed over
+        assert_eq!(run(&installed.exe), "mattraxd 99.0.0\n");
+
+        // The new version keeps crashing
+        for _ in 0..MAX_BOOTS {
+            assert_eq!(startup(dir).unwrap(), None);
+        }
+        let restored = startup(dir).unwrap().unwrap();
+        assert_eq!(restored, exe);
+        assert_eq!(fs::read_to_string(&exe).unwrap(), OLD);
+        assert_eq!(run(&restored), "mattraxd 0.0.1\n");
+        assert!(updater.update(&signed, "stable").await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_health_check_deadline() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let (exe, backup) = (dir.join("mattraxd"), dir.join(".mattraxd.previous"));
+        fs::write(&exe, NEW).unwrap();
+        fs::write(&backup, OLD).unwrap();
+
+        let started = Instant::now();
+        assert_eq!(health_check_deadline(dir, started), None);
+
+        State {
+            pending: Some(Pending {
+                version: "99.0.0".into(),
+                exe: exe.clone(),
+                backup,
+                // Installed long ago but the device was turned off since
+                installed_at: 0,
+                boots: 0,
+            }),
+            rejected: vec![],
+        }
+        .save(dir)
+        .unwrap();
+        assert_eq!(startup(dir).unwrap(), None);
+        assert_eq!(
+            health_check_deadline(dir, started),
+            Some(started + HEALTH_CHECK_TIMEOUT)
+        );
+
+        assert_eq!(fail_health_check(dir).unwrap(), exe);
+        assert_eq!(fs::read_to_string(&exe).unwrap(), OLD);
+        assert_eq!(health_check_deadline(dir, started), None);
+        assert_eq!(State::load(dir).unwrap().rejected, vec!["99.0.0"]);
+    }
+}
@@ -48,9 +48,8 @@ pub struct Manifest {
 }
 
 impl Manifest {
-    pub fn from_file(path: impl AsRef<Path>) -> Self {
-        dbg!(path.as_ref());
-        plist::from_file(path).unwrap()
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, plist::Error> {
+        plist::from_file(path)
     }
 }
 
@@ -63,6 +62,23 @@ pub struct PreferenceBase {
     pub pfm_title: Option<String>,
     pub pfm_name: Option<String>,
     pub pfm_supervised: Option<bool>,
+    /// Whether the key must be set.
+    pub pfm_require: Option<Require>,
+    /// The smallest value allowed, for numbers.
+    pub pfm_range_min: Option<Value>,
+    /// The largest value allowed, for numbers.
+    pub pfm_range_max: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Require {
+    /// The key must always be set.
+    Always,
+    /// The key must be set when its parent dictionary is.
+    AlwaysNested,
+    /// The key is only required when the payload is pushed by an MDM.
+    Push,
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -83,7 +99,14 @@ pub enum Preference {
         #[serde(default)]
         pfm_subkeys: Vec<Preference>,
     },
-    Integer(PreferenceBase),
+    Integer {
+        #[serde(flatten)]
+        base: PreferenceBase,
+        #[serde(default)]
+        pfm_range_list: Vec<Value>,
+        #[serde(default)]
+        pfm_range_list_titles: Vec<String>,
+    },
     Real(PreferenceBase),
     Float(PreferenceBase),
     String {
@@ -109,7 +132,7 @@ impl std::ops::Deref for Preference {
             Preference::Date(p) => p,
             Preference::Data(p) => p,
             Preference::Dictionary { base, .. } => base,
-            Preference::Integer(p) => p,
+            Preference::Integer { base, .. } => base,
             Preference::Real(p) => p,
             Preference::Float(p) => p,
             Preference::String { base, .. } => base,
@@ -128,7 +151,7 @@ impl Preference {
             Preference::Date(_) => "date",
             Preference::Data(_) => "data",
             Preference::Dictionary { .. } => "dictionary",
-            Preference::Integer(_) => "integer",
+            Preference::Integer { .. } => "integer",
             Preference::Real(_) => "real",
             Preference::Float(_) => "float",
             Preference::String { .. } => "string",
@@ -0,0 +1,793 @@
+//! Group Policy definitions from ADMX files, and the ADML files holding their strings and presentations.
+//!
+//! ADMX-backed CSP nodes reference their policy with an [`AdmxBacked`], which is resolved using [`PolicyDefinitions::policy`].
+//! The policy's elements together with the controls of its presentation describe the form for configuring it.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use easy_xml::{de::Error, XmlDeserialize, XmlElement};
+use easy_xml_derive::XmlDeserialize;
+
+use crate::AdmxBacked;
+
+/// Parse an ADMX file.
+pub fn from_str(s: &str) -> Result<PolicyDefinitions, Error> {
+    easy_xml::de::from_str::<RawPolicyDefinitions>(s).map(Into::into)
+}
+
+/// Parse an ADML file.
+pub fn resources_from_str(s: &str) -> Result<PolicyDefinitionResources, Error> {
+    easy_xml::de::from_str::<RawPolicyDefinitionResources>(s).map(Into::into)
+}
+
+#[derive(Debug)]
+pub struct PolicyDefinitions {
+    /// The namespace of the policies and categories defined by the file.
+    pub target: Namespace,
+    /// Namespaces of other files which are referenced with their prefix, such as `windows:WindowsComponents`.
+    pub using: Vec<Namespace>,
+    pub categories: Vec<Category>,
+    pub policies: Vec<Policy>,
+}
+
+impl PolicyDefinitions {
+    /// The policy which backs a CSP node, if it's defined in this file.
+    pub fn policy(&self, backed: &AdmxBacked) -> Option<&Policy> {
+        if backed.prefix() != self.target.prefix {
+            return None;
+        }
+
+        self.policies
+            .iter()
+            .find(|policy| policy.name == backed.name)
+    }
+}
+
+#[derive(XmlDeserialize, Debug, Clone)]
+pub struct Namespace {
+    #[easy_xml(rename = "prefix", attribute)]
+    pub prefix: String,
+    #[easy_xml(rename = "namespace", attribute)]
+    pub namespace: String,
+}
+
+#[derive(XmlDeserialize, Debug)]
+pub struct Category {
+    #[easy_xml(rename = "name", attribute)]
+    pub name: String,
+    #[easy_xml(rename = "displayName", attribute)]
+    pub display_name: String,
+    #[easy_xml(rename = "explainText", attribute)]
+    pub explain_text: Option<String>,
+    #[easy_xml(rename = "parentCategory")]
+    pub parent_category: Option<Reference>,
+}
+
+/// A reference to a definition by name, prefixed by its namespace if it's in another file.
+#[derive(XmlDeserialize, Debug)]
+pub struct Reference {
+    #[easy_xml(rename = "ref", attribute)]
+    pub reference: String,
+}
+
+#[derive(XmlDeserialize, Debug)]
+pub struct Policy {
+    #[easy_xml(rename = "name", attribute)]
+    pub name: String,
+    #[easy_xml(rename = "class", attribute)]
+    pub class: PolicyClass,
+    /// A reference to the string table of the ADML file, such as `$(string.AxISURLZonePolicies)`.
+    #[easy_xml(rename = "displayName", attribute)]
+    pub display_name: String,
+    #[easy_xml(rename = "explainText", attribute)]
+    pub explain_text: Option<String>,
+    /// A reference to the presentation table of the ADML file, such as `$(presentation.AxISURLZonePolicies)`.
+    #[easy_xml(rename = "presentation", attribute)]
+    pub presentation: Option<String>,
+    /// The registry key the policy is stored under.
+    #[easy_xml(rename = "key", attribute)]
+    pub key: String,
+    /// The registry value set when the policy is enabled or disabled.
+    #[easy_xml(rename = "valueName", attribute)]
+    pub value_name: Option<String>,
+    #[easy_xml(rename = "parentCategory")]
+    pub parent_category: Option<Reference>,
+    #[easy_xml(rename = "supportedOn")]
+    pub supported_on: Option<Reference>,
+    #[easy_xml(rename = "enabledValue")]
+    pub enabled_value: Option<Value>,
+    #[easy_xml(rename = "disabledValue")]
+    pub disabled_value: Option<Value>,
+    #[easy_xml(rename = "elements")]
+    elements: Option<Elements>,
+}
+
+impl Policy {
+    /// The values which are configured when the policy is enabled.
+    pub fn elements(&self) -> &[PolicyElement] {
+        self.elements.as_ref().map_or(&[], |elements| &elements.0)
+    }
+
+    /// The elements of the policy with the controls used to configure them, in the order they are presented.
+    ///
+    /// Elements without a control are returned after those with one.
+    pub fn fields<'a>(
+        &'a self,
+        resources: &'a PolicyDefinitionResources,
+    ) -> Vec<(&'a PolicyElement, Option<&'a PresentationControl>)> {
+        let controls = self
+            .presentation
+            .as_deref()
+            .and_then(|presentation| resources.presentation(presentation))
+            .map_or(&[][..], |presentation| &presentation.controls);
+
+        let mut fields = controls
+            .iter()
+            .filter_map(|control| {
+                let id = control.ref_id.as_deref()?;
+                let element = self.elements().iter().find(|element| element.id == id)?;
+                Some((element, Some(control)))
+            })
+            .collect::<Vec<_>>();
+        for element in self.elements() {
+            if !fields.iter().any(|(field, _)| field.id == element.id) {
+                fields.push((element, None));
+            }
+        }
+        fields
+    }
+}
+
+#[derive(XmlDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyClass {
+    Machine,
+    User,
+    Both,
+}
+
+/// A registry value written by a policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Decimal(u32),
+    LongDecimal(u64),
+    String(String),
+    /// The registry value is deleted.
+    Delete,
+}
+
+impl XmlDeserialize for Value {
+    fn deserialize(element: &XmlElement) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        let mut value = None;
+        for_each_child(element, |name, element| {
+            value = Some(match name {
+                "decimal" => Self::Decimal(parse(&Attribute::deserialize(element)?.value)?),
+                "longDecimal" => Self::LongDecimal(parse(&Attribute::deserialize(element)?.value)?),
+                "string" => Self::String(Text::deserialize(element)?.text),
+                "delete" => Self::Delete,
+                _ => return Err(Error::BadXml),
+            });
+            Ok(())
+        })?;
+        value.ok_or(Error::BadXml)
+    }
+}
+
+/// A value which is configured when the policy is enabled.
+#[derive(Debug)]
+pub struct PolicyElement {
+    /// The identifier of the element within the policy, referenced by its presentation.
+    pub id: String,
+    /// The registry key of the value, if it's not the key of the policy.
+    pub key: Option<String>,
+    pub value_name: Option<String>,
+    pub required: bool,
+    pub kind: ElementKind,
+}
+
+#[derive(Debug)]
+pub enum ElementKind {
+    /// One of a list of items.
+    Enum {
+        items: Vec<EnumItem>,
+    },
+    Decimal {
+        min: u32,
+        max: u32,
+        store_as_text: bool,
+    },
+    LongDecimal {
+        min: u64,
+        max: u64,
+        store_as_text: bool,
+    },
+    Text {
+        max_length: u32,
+        expandable: bool,
+    },
+    /// Multiple lines of text, stored as a multi-string.
+    MultiText {
+        max_length: u32,
+        /// The maximum number of strings, or `0` if there is no limit.
+        max_strings: u32,
+    },
+    /// A list of values, each stored as its own registry value under the key of the element.
+    List {
+        value_prefix: Option<String>,
+        /// Whether existing values are kept instead of being replaced by the list.
+        additive: bool,
+        /// Whether each entry includes the name of its registry value.
+        explicit_value: bool,
+        expandable: bool,
+    },
+    Boolean {
+        true_value: Option<Value>,
+        false_value: Option<Value>,
+    },
+}
+
+#[derive(XmlDeserialize, Debug)]
+pub struct EnumItem {
+    #[easy_xml(rename = "displayName", attribute)]
+    pub display_name: String,
+    #[easy_xml(rename = "value")]
+    pub value: Value,
+}
+
+/// The attributes and children of every kind of element, as which are present depends on the kind.
+#[derive(XmlDeserialize)]
+struct RawElement {
+    #[easy_xml(rename = "id", attribute)]
+    id: String,
+    #[easy_xml(rename = "key", attribute)]
+    key: Option<String>,
+    #[easy_xml(rename = "valueName", attribute)]
+    value_name: Option<String>,
+    #[easy_xml(rename = "required", attribute)]
+    required: Option<String>,
+    #[easy_xml(rename = "minValue", attribute)]
+    min_value: Option<String>,
+    #[easy_xml(rename = "maxValue", attribute)]
+    max_value: Option<String>,
+    #[easy_xml(rename = "storeAsText", attribute)]
+    store_as_text: Option<String>,
+    #[easy_xml(rename = "maxLength", attribute)]
+    max_length: Option<String>,
+    #[easy_xml(rename = "maxStrings", attribute)]
+    max_strings: Option<String>,
+    #[easy_xml(rename = "expandable", attribute)]
+    expandable: Option<String>,
+    #[easy_xml(rename = "valuePrefix", attribute)]
+    value_prefix: Option<String>,
+    #[easy_xml(rename = "additive", attribute)]
+    additive: Option<String>,
+    #[easy_xml(rename = "explicitValue", attribute)]
+    explicit_value: Option<String>,
+    #[easy_xml(rename = "item")]
+    items: Vec<EnumItem>,
+    #[easy_xml(rename = "trueValue")]
+    true_value: Option<Value>,
+    #[easy_xml(rename = "falseValue")]
+    false_value: Option<Value>,
+}
+
+impl RawElement {
+    /// Convert to a [`PolicyElement`], using the defaults from the ADMX schema for missing attributes.
+    fn into_element(self, name: &str) -> Result<PolicyElement, Error> {
+        let kind = match name {
+            "enum" => ElementKind::Enum { items: self.items },
+            "decimal" => ElementKind::Decimal {
+                min: parse_or(&self.min_value, 0)?,
+                max: parse_or(&self.max_value, 9999)?,
+                store_as_text: parse_bool(&self.store_as_text)?,
+            },
+            "longDecimal" => ElementKind::LongDecimal {
+                min: parse_or(&self.min_value, 0)?,
+                max: parse_or(&self.max_value, 9999)?,
+                store_as_text: parse_bool(&self.store_as_text)?,
+            },
+            "text" => ElementKind::Text {
+                max_length: parse_or(&self.max_length, 1023)?,
+                expandable: parse_bool(&self.expandable)?,
+            },
+            "multiText" => ElementKind::MultiText {
+                max_length: parse_or(&self.max_length, 1023)?,
+                max_strings: parse_or(&self.max_strings, 0)?,
+            },
+            "list" => ElementKind::List {
+                value_prefix: self.value_prefix,
+                additive: parse_bool(&self.additive)?,
+                explicit_value: parse_bool(&self.explicit_value)?,
+                expandable: parse_bool(&self.expandable)?,
+            },
+            "boolean" => ElementKind::Boolean {
+                true_value: self.true_value,
+                false_value: self.false_value,
+            },
+            _ => return Err(Error::BadXml),
+        };
+
+        Ok(PolicyElement {
+            id: self.id,
+            key: self.key,
+            value_name: self.value_name,
+            required: parse_bool(&self.required)?,
+            kind,
+        })
+    }
+}
+
+/// The `elements` of a policy, which are a mix of element kinds in the order they were defined.
+#[derive(Debug)]
+struct Elements(Vec<PolicyElement>);
+
+impl XmlDeserialize for Elements {
+    fn deserialize(element: &XmlElement) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        let mut elements = Vec::new();
+        for_each_child(element, |name, element| {
+            elements.push(RawElement::deserialize(element)?.into_element(name)?);
+            Ok(())
+        })?;
+        Ok(Self(elements))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PolicyDefinitionResources {
+    pub strings: BTreeMap<String, String>,
+    pub presentations: BTreeMap<String, Presentation>,
+}
+
+impl PolicyDefinitionResources {
+    /// Resolve a reference such as `$(string.AxISURLZonePolicies)`. Anything else is returned as is.
+    pub fn string<'a>(&'a self, reference: &'a str) -> Option<&'a str> {
+        match resource_id(reference, "string") {
+            Some(id) => self.strings.get(id).map(String::as_str),
+            None => Some(reference),
+        }
+    }
+
+    /// Resolve a reference such as `$(presentation.AxISURLZonePolicies)`.
+    pub fn presentation(&self, reference: &str) -> Option<&Presentation> {
+        self.presentations
+            .get(resource_id(reference, "presentation")?)
+    }
+}
+
+/// The id from a reference to a resource, like `$(string.id)`.
+fn resource_id<'a>(reference: &'a str, table: &str) -> Option<&'a str> {
+    reference
+        .trim()
+        .strip_prefix("$(")?
+        .strip_suffix(')')?
+        .strip_prefix(table)?
+        .strip_prefix('.')
+}
+
+/// The controls for configuring the elements of a policy.
+#[derive(Debug)]
+pub struct Presentation {
+    pub id: String,
+    pub controls: Vec<PresentationControl>,
+}
+
+impl XmlDeserialize for Presentation {
+    fn deserialize(element: &XmlElement) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        let mut controls = Vec::new();
+        for_each_child(element, |name, element| {
+            controls.push(RawControl::deserialize(element)?.into_control(name)?);
+            Ok(())
+        })?;
+
+        Ok(Self {
+            id: Id::deserialize(element)?.id,
+            controls,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct PresentationControl {
+    /// The id of the element the control configures. Text between controls doesn't have one.
+    pub ref_id: Option<String>,
+    pub label: String,
+    pub kind: ControlKind,
+}
+
+#[derive(Debug)]
+pub enum ControlKind {
+    Text,
+    DecimalTextBox {
+        default: Option<u32>,
+    },
+    LongDecimalTextBox {
+        default: Option<u64>,
+    },
+    TextBox {
+        default: Option<String>,
+    },
+    MultiTextBox,
+    CheckBox {
+        default_checked: bool,
+    },
+    ComboBox {
+        default: Option<String>,
+        suggestions: Vec<String>,
+    },
+    DropdownList {
+        /// The index of the item selected by default.
+        default_item: Option<usize>,
+    },
+    ListBox,
+}
+
+/// The attributes and children of every kind of control, as which are present depends on the kind.
+#[derive(XmlDeserialize)]
+struct RawControl {
+    #[easy_xml(rename = "refId", attribute)]
+    ref_id: Option<String>,
+    #[easy_xml(rename = "defaultValue", attribute)]
+    default_value: Option<String>,
+    #[easy_xml(rename = "defaultChecked", attribute)]
+    default_checked: Option<String>,
+    #[easy_xml(rename = "defaultItem", attribute)]
+    default_item: Option<String>,
+    #[easy_xml(text)]
+    text: String,
+    #[easy_xml(rename = "label")]
+    label: Option<String>,
+    /// The default of a `textBox`, which unlike other controls is an element.
+    #[easy_xml(rename = "defaultValue")]
+    default_value_element: Option<String>,
+    #[easy_xml(rename = "default")]
+    default: Option<String>,
+    #[easy_xml(rename = "suggestion")]
+    suggestions: Vec<String>,
+}
+
+impl RawControl {
+    fn into_control(self, name: &str) -> Result<PresentationControl, Error> {
+        let kind = match name {
+            "text" => ControlKind::Text,
+            "decimalTextBox" => ControlKind::DecimalTextBox {
+                default: parse_optional(&self.default_value)?,
+            },
+            "longDecimalTextBox" => ControlKind::LongDecimalTextBox {
+                default: parse_optional(&self.default_value)?,
+            },
+            "textBox" => ControlKind::TextBox {
+                default: self.default_value_element,
+            },
+            "multiTextBox" => ControlKind::MultiTextBox,
+            "checkBox" => ControlKind::CheckBox {
+                default_checked: parse_bool(&self.default_checked)?,
+            },
+            "comboBox" => ControlKind::ComboBox {
+                default: self.default,
+                suggestions: self.suggestions,
+            },
+            "dropdownList" => ControlKind::DropdownList {
+                default_item: parse_optional(&self.default_item)?,
+            },
+            "listBox" => ControlKind::ListBox,
+            _ => return Err(Error::BadXml),
+        };
+
+        Ok(PresentationControl {
+            ref_id: self.ref_id,
+            label: self.label.unwrap_or(self.text).trim().to_string(),
+            kind,
+        })
+    }
+}
+
+#[derive(XmlDeserialize)]
+#[easy_xml(root)]
+struct RawPolicyDefinitions {
+    #[easy_xml(rename = "policyNamespaces")]
+    policy_namespaces: PolicyNamespaces,
+    #[easy_xml(rename = "categories")]
+    categories: Option<Categories>,
+    #[easy_xml(rename = "policies")]
+    policies: Option<Policies>,
+}
+
+#[derive(XmlDeserialize)]
+struct PolicyNamespaces {
+    #[easy_xml(rename = "target")]
+    target: Namespace,
+    #[easy_xml(rename = "using")]
+    using: Vec<Namespace>,
+}
+
+#[derive(XmlDeserialize)]
+struct Categories {
+    #[easy_xml(rename = "category")]
+    categories: Vec<Category>,
+}
+
+#[derive(XmlDeserialize)]
+struct Policies {
+    #[easy_xml(rename = "policy")]
+    policies: Vec<Policy>,
+}
+
+impl From<RawPolicyDefinitions> for PolicyDefinitions {
+    fn from(raw: RawPolicyDefinitions) -> Self {
+        Self {
+            target: raw.policy_namespaces.target,
+            using: raw.policy_namespaces.using,
+            categories: raw.categories.map_or_else(Vec::new, |c| c.categories),
+            policies: raw.policies.map_or_else(Vec::new, |p| p.policies),
+        }
+    }
+}
+
+#[derive(XmlDeserialize)]
+#[easy_xml(root)]
+struct RawPolicyDefinitionResources {
+    #[easy_xml(rename = "resources")]
+    resources: Resources,
+}
+
+#[derive(XmlDeserialize)]
+struct Resources {
+    #[easy_xml(rename = "stringTable")]
+    string_table: Option<StringTable>,
+    #[easy_xml(rename = "presentationTable")]
+    presentation_table: Option<PresentationTable>,
+}
+
+#[derive(XmlDeserialize)]
+struct StringTable {
+    #[easy_xml(rename = "string")]
+    strings: Vec<StringResource>,
+}
+
+#[derive(XmlDeserialize)]
+struct StringResource {
+    #[easy_xml(rename = "id", attribute)]
+    id: String,
+    #[easy_xml(text)]
+    text: String,
+}
+
+#[derive(XmlDeserialize)]
+struct PresentationTable {
+    #[easy_xml(rename = "presentation")]
+    presentations: Vec<Presentation>,
+}
+
+impl From<RawPolicyDefinitionResources> for PolicyDefinitionResources {
+    fn from(raw: RawPolicyDefinitionResources) -> Self {
+        let resources = raw.resources;
+        Self {
+            strings: resources
+                .string_table
+                .into_iter()
+                .flat_map(|table| table.strings)
+                .map(|string| (string.id, string.text))
+                .collect(),
+            presentations: resources
+                .presentation_table
+                .into_iter()
+                .flat_map(|table| table.presentations)
+                .map(|presentation| (presentation.id.clone(), presentation))
+                .collect(),
+        }
+    }
+}
+
+#[derive(XmlDeserialize)]
+struct Id {
+    #[easy_xml(rename = "id", attribute)]
+    id: String,
+}
+
+#[derive(XmlDeserialize)]
+struct Attribute {
+    #[easy_xml(rename = "value", attribute)]
+    value: String,
+}
+
+#[derive(XmlDeserialize)]
+struct Text {
+    #[easy_xml(text)]
+    text: String,
+}
+
+/// Call `f` with the local name of each child element, in document order.
+fn for_each_child(
+    element: &XmlElement,
+    mut f: impl FnMut(&str, &XmlElement) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let XmlElement::Node(node) = element else {
+        return Err(Error::BadXml);
+    };
+
+    for element in &node.borrow().elements {
+        if let XmlElement::Node(child) = element {
+            let name = child.borrow().name.local_name.clone();
+            f(&name, element)?;
+        }
+    }
+    Ok(())
+}
+
+fn parse<T: FromStr>(value: &str) -> Result<T, Error> {
+    value.trim().parse().map_err(|_| Error::BadXml)
+}
+
+fn parse_optional<T: FromStr>(value: &Option<String>) -> Result<Option<T>, Error> {
+    value.as_deref().map(parse).transpose()
+}
+
+fn parse_or<T: FromStr>(value: &Option<String>, default: T) -> Result<T, Error> {
+    Ok(parse_optional(value)?.unwrap_or(default))
+}
+
+/// Booleans in ADMX files are `true` or `false`, and default to `false`.
+fn parse_bool(value: &Option<String>) -> Result<bool, Error> {
+    match value.as_deref().map(str::trim) {
+        Some("true" | "1") => Ok(true),
+        Some("false" | "0") | None => Ok(false),
+        Some(_) => Err(Error::BadXml),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ADMX: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<policyDefinitions revision="1.0" schemaVersion="1.0">
+  <policyNamespaces>
+    <target prefix="ActiveXInstallService" namespace="Microsoft.Policies.ActiveXInstallService" />
+    <using prefix="windows" namespace="Microsoft.Policies.Windows" />
+  </policyNamespaces>
+  <resources minRequiredRevision="1.0" />
+  <categories>
+    <category name="AxInstSv" displayName="$(string.AxInstSv)">
+      <parentCategory ref="windows:WindowsComponents" />
+    </category>
+  </categories>
+  <policies>
+    <policy name="AxISURLZonePolicies" class="Machine" displayName="$(string.AxISURLZonePolicies)" explainText="$(string.AxISURLZonePolicies_Explain)" presentation="$(presentation.AxISURLZonePolicies)" key="SOFTWARE\Policies\Microsoft\AxInstaller\AxISURLZonePolicies">
+      <parentCategory ref="AxInstSv" />
+      <supportedOn ref="windows:SUPPORTED_Windows7" />
+      <elements>
+        <enum id="InstallTrustedOCX" valueName="InstallTrustedOCX" required="true">
+          <item displayName="$(string.TrustedZoneDisable)">
+            <value><decimal value="0" /></value>
+          </item>
+          <item displayName="$(string.TrustedZoneSilentInstall)">
+            <value><decimal value="2" /></value>
+          </item>
+        </enum>
+        <decimal id="Timeout" valueName="Timeout" minValue="1" maxValue="60" />
+        <boolean id="IgnoreErrors" valueName="IgnoreErrors">
+          <trueValue><string>yes</string></trueValue>
+          <falseValue><delete /></falseValue>
+        </boolean>
+      </elements>
+    </policy>
+  </policies>
+</policyDefinitions>"#;
+
+    const ADML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<policyDefinitionResources revision="1.0" schemaVersion="1.0">
+  <displayName />
+  <description />
+  <resources>
+    <stringTable>
+      <string id="AxInstSv">ActiveX Installer Service</string>
+      <string id="AxISURLZonePolicies">Establish ActiveX installation policy for sites in Trusted zones</string>
+    </stringTable>
+    <presentationTable>
+      <presentation id="AxISURLZonePolicies">
+        <text>Installation policy for sites in the Trusted zone</text>
+        <decimalTextBox refId="Timeout" defaultValue="30">Timeout</decimalTextBox>
+        <dropdownList refId="InstallTrustedOCX" noSort="true" defaultItem="1">Trusted Zone Sites</dropdownList>
+      </presentation>
+    </presentationTable>
+  </resources>
+</policyDefinitionResources>"#;
+
+    #[test]
+    fn test_admx() {
+        let definitions = from_str(ADMX).unwrap();
+        let resources = resources_from_str(ADML).unwrap();
+        assert_eq!(definitions.target.prefix, "ActiveXInstallService");
+        assert_eq!(definitions.using[0].prefix, "windows");
+        assert_eq!(
+            definitions.categories[0]
+                .parent_category
+                .as_ref()
+                .map(|c| c.reference.as_str()),
+            Some("windows:WindowsComponents")
+        );
+
+        let backed = AdmxBacked {
+            area: "ActiveXInstallService~AT~WindowsComponents~AxInstSv".into(),
+            name: "AxISURLZonePolicies".into(),
+            file: "ActiveXInstallService.admx".into(),
+        };
+        let policy = definitions.policy(&backed).unwrap();
+        assert_eq!(policy.class, PolicyClass::Machine);
+        assert_eq!(
+            resources.string(&policy.display_name),
+            Some("Establish ActiveX installation policy for sites in Trusted zones")
+        );
+
+        let elements = policy.elements();
+        assert_eq!(elements.len(), 3);
+        assert!(elements[0].required);
+        let ElementKind::Enum { items } = &elements[0].kind else {
+            panic!("expected an enum, found {:?}", elements[0].kind);
+        };
+        assert_eq!(items[1].value, Value::Decimal(2));
+        assert!(matches!(
+            elements[1].kind,
+            ElementKind::Decimal {
+                min: 1,
+                max: 60,
+                store_as_text: false
+            }
+        ));
+        let ElementKind::Boolean {
+            true_value,
+            false_value,
+        } = &elements[2].kind
+        else {
+            panic!("expected a boolean, found {:?}", elements[2].kind);
+        };
+        assert_eq!(true_value, &Some(Value::String("yes".into())));
+        assert_eq!(false_value, &Some(Value::Delete));
+
+        // Fields are in the order of the presentation, and the text between controls is skipped
+        let fields = policy.fields(&resources);
+        let ids = fields
+            .iter()
+            .map(|(element, _)| element.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["Timeout", "InstallTrustedOCX", "IgnoreErrors"]);
+        let (_, Some(control)) = fields[1] else {
+            panic!("expected a control");
+        };
+        assert_eq!(control.label, "Trusted Zone Sites");
+        assert!(matches!(
+            control.kind,
+            ControlKind::DropdownList {
+                default_item: Some(1)
+            }
+        ));
+        assert!(fields[2].1.is_none());
+    }
+
+    #[test]
+    fn test_resources() {
+        let resources = PolicyDefinitionResources {
+            strings: [(
+                "AxInstSv".to_string(),
+                "ActiveX Installer Service".to_string(),
+            )]
+            .into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            resources.string("$(string.AxInstSv)"),
+            Some("ActiveX Installer Service")
+        );
+        assert_eq!(resources.string("$(string.Missing)"), None);
+        assert_eq!(resources.string("Plain text"), Some("Plain text"));
+        assert!(resources.presentation("$(string.AxInstSv)").is_none());
+    }
+}
@@ -2,7 +2,7 @@ use easy_xml_derive::XmlDeserialize;
 
 use crate::msft;
 
-#[derive(XmlDeserialize, Debug)]
+#[derive(XmlDeserialize, Debug, PartialEq)]
 #[easy_xml(root)]
 pub struct MgmtTree {
     #[easy_xml(rename = "VerDTD")]
@@ -11,7 +11,32 @@ pub struct MgmtTree {
     pub nodes: Vec<Node>,
 }
 
-#[derive(XmlDeserialize, Debug)]
+impl Default for MgmtTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MgmtTree {
+    pub fn new() -> Self {
+        Self {
+            ver_dtd: "1.2".into(),
+            nodes: Vec::new(),
+        }
+    }
+
+    pub fn node(mut self, node: Node) -> Self {
+        self.nodes.push(node);
+        self
+    }
+
+    /// Fill in what depends on the rest of the node, such as the default of a range.
+    pub(crate) fn resolve(&mut self) {
+        self.nodes.iter_mut().for_each(Node::resolve);
+    }
+}
+
+#[derive(XmlDeserialize, Debug, PartialEq)]
 pub struct Node {
     #[easy_xml(rename = "NodeName")]
     pub node_name: String,
@@ -23,7 +48,162 @@ pub struct Node {
     pub properties: DFProperties,
 }
 
-#[derive(XmlDeserialize, Debug)]
+/// Building nodes, such as to describe a custom CSP.
+///
+/// ```ignore
+/// let tree = MgmtTree::new().node(
+///     Node::new("Mattrax", DFFormatVariant::Node)
+///         .path("./Device/Vendor")
+///         .child(
+///             Node::new("SyncInterval", DFFormatVariant::Int)
+///                 .access([Command::Get, Command::Replace])
+///                 .default_value("60")
+///                 .allowed_values(AllowedValues::Range("[5-1440]".parse()?)),
+///         ),
+/// );
+/// ```
+impl Node {
+    /// A node which only permits `Get`. Leaf nodes have a MIME type of `text/plain`.
+    pub fn new(name: impl Into<String>, format: DFFormatVariant) -> Self {
+        let is_interior = matches!(format, DFFormatVariant::Node);
+        let mut access_type = AccessType::default();
+        access_type.permit(crate::Command::Get);
+
+        Self {
+            node_name: name.into(),
+            path: None,
+            children: Vec::new(),
+            properties: DFProperties {
+                access_type,
+                default_value: None,
+                description: None,
+                df_format: format.into(),
+                occurrence: Some(
+                    if is_interior {
+                        OccurrenceVariant::One
+                    } else {
+                        OccurrenceVariant::ZeroOrOne
+                    }
+                    .into(),
+                ),
+                scope: Some(ScopeVariant::Permanent.into()),
+                df_title: None,
+                df_type: DFType {
+                    mime: (!is_interior).then(|| "text/plain".into()),
+                    ddf_name: is_interior.then(String::new),
+                },
+                case_sense: None,
+                allowed_values: None,
+                applicability: None,
+                gp_mapping: None,
+                conflict_resolution: None,
+                deprecated: None,
+            },
+        }
+    }
+
+    /// A node whose name is chosen by the server, such as the profile name in `VPNv2/{ProfileName}`.
+    pub fn dynamic(title: impl Into<String>) -> Self {
+        Self::new("", DFFormatVariant::Node)
+            .title(title)
+            .access([
+                crate::Command::Add,
+                crate::Command::Delete,
+                crate::Command::Get,
+            ])
+            .occurrence(OccurrenceVariant::ZeroOrMore)
+            .scope(ScopeVariant::Dynamic)
+    }
+
+    /// The path of the parent of a root node, such as `./Device/Vendor/MSFT`.
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Replace the permitted commands.
+    pub fn access(mut self, commands: impl IntoIterator<Item = crate::Command>) -> Self {
+        self.properties.access_type = AccessType::default();
+        for command in commands {
+            self.properties.access_type.permit(command);
+        }
+        self
+    }
+
+    pub fn default_value(mut self, value: impl Into<String>) -> Self {
+        self.properties.default_value = Some(value.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.properties.description = Some(description.into());
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.properties.df_title = Some(title.into());
+        self
+    }
+
+    pub fn occurrence(mut self, occurrence: OccurrenceVariant) -> Self {
+        self.properties.occurrence = Some(occurrence.into());
+        self
+    }
+
+    pub fn scope(mut self, scope: ScopeVariant) -> Self {
+        self.properties.scope = Some(scope.into());
+        self
+    }
+
+    pub fn mime(mut self, mime: impl Into<String>) -> Self {
+        self.properties.df_type = DFType {
+            mime: Some(mime.into()),
+            ddf_name: None,
+        };
+        self
+    }
+
+    pub fn case_sense(mut self, case_sense: CaseSenseVariant) -> Self {
+        self.properties.case_sense = Some(CaseSense {
+            variant: case_sense,
+        });
+        self
+    }
+
+    pub fn allowed_values(mut self, allowed_values: msft::AllowedValues) -> Self {
+        self.properties.allowed_values = Some(allowed_values);
+        self
+    }
+
+    pub fn applicability(mut self, applicability: msft::Applicability) -> Self {
+        self.properties.applicability = Some(applicability);
+        self
+    }
+
+    pub fn conflict_resolution(mut self, conflict_resolution: msft::ConflictResolution) -> Self {
+        self.properties.conflict_resolution = Some(conflict_resolution);
+        self
+    }
+
+    pub fn child(mut self, child: Node) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    fn resolve(&mut self) {
+        let properties = &mut self.properties;
+        if let Some(msft::AllowedValues::Range(range)) = &mut properties.allowed_values {
+            range.default = properties
+                .default_value
+                .as_deref()
+                .and_then(|value| value.trim().parse().ok());
+        }
+
+        self.children.iter_mut().for_each(Node::resolve);
+    }
+}
+
+#[derive(XmlDeserialize, Debug, PartialEq)]
 pub struct DFProperties {
     #[easy_xml(rename = "AccessType")]
     pub access_type: AccessType,
@@ -40,14 +220,31 @@ pub struct DFProperties {
     #[easy_xml(rename = "DFTitle")]
     pub df_title: Option<String>,
     #[easy_xml(rename = "DFType")]
-    pub df_type: String,
+    pub df_type: DFType,
     #[easy_xml(rename = "CaseSense")]
-    pub case_sense: Option<String>,
-    #[easy_xml(rename = "AllowedValues")]
+    pub case_sense: Option<CaseSense>,
+    #[easy_xml(prefix = "MSFT", rename = "AllowedValues")]
     pub allowed_values: Option<msft::AllowedValues>,
+    #[easy_xml(prefix = "MSFT", rename = "Applicability")]
+    pub applicability: Option<msft::Applicability>,
+    #[easy_xml(prefix = "MSFT", rename = "GpMapping")]
+    pub gp_mapping: Option<msft::GpMapping>,
+    #[easy_xml(prefix = "MSFT", rename = "ConflictResolution")]
+    pub conflict_resolution: Option<msft::ConflictResolution>,
+    #[easy_xml(prefix = "MSFT", rename = "Deprecated")]
+    pub deprecated: Option<msft::Deprecated>,
+}
+
+#[derive(XmlDeserialize, Debug, Default, PartialEq)]
+pub struct DFType {
+    /// The MIME type of the node's value, or its URN for interior nodes such as `com.microsoft/11/MDM/Policy`.
+    #[easy_xml(rename = "MIME")]
+    pub mime: Option<String>,
+    #[easy_xml(rename = "DDFName")]
+    pub ddf_name: Option<String>,
 }
 
-#[derive(XmlDeserialize, Debug)]
+#[derive(XmlDeserialize, Debug, Default, PartialEq)]
 pub struct AccessType {
     #[easy_xml(rename = "Add")]
     pub add: Option<Element>,
@@ -64,6 +261,18 @@ pub struct AccessType {
 }
 
 impl AccessType {
+    /// Permit a command, such as for a node built with [`Node::new`].
+    pub fn permit(&mut self, command: crate::Command) {
+        let access = match command {
+            crate::Command::Add => &mut self.add,
+            crate::Command::Replace => &mut self.replace,
+            crate::Command::Delete => &mut self.delete,
+            crate::Command::Get => &mut self.get,
+            crate::Command::Exec => &mut self.exec,
+        };
+        *access = Some(Element);
+    }
+
     pub fn len(&self) -> u8 {
         let mut count = 0;
         if self.add.is_some() {
@@ -92,12 +301,18 @@ impl AccessType {
     }
 }
 
-#[derive(XmlDeserialize, Debug)]
+#[derive(XmlDeserialize, Debug, PartialEq)]
 pub struct DFFormat {
     #[easy_xml(rename = "b64|bin|bool|chr|int|node|null|xml|date|time|float")]
     variant: DFFormatVariant,
 }
 
+impl From<DFFormatVariant> for DFFormat {
+    fn from(variant: DFFormatVariant) -> Self {
+        Self { variant }
+    }
+}
+
 impl std::ops::Deref for DFFormat {
     type Target = DFFormatVariant;
 
@@ -106,7 +321,7 @@ impl std::ops::Deref for DFFormat {
     }
 }
 
-#[derive(XmlDeserialize, Debug)]
+#[derive(XmlDeserialize, Debug, PartialEq)]
 pub enum DFFormatVariant {
     #[easy_xml(rename = "b64")]
     Base64,
@@ -150,12 +365,18 @@ impl ToString for DFFormatVariant {
     }
 }
 
-#[derive(XmlDeserialize, Debug)]
+#[derive(XmlDeserialize, Debug, PartialEq)]
 pub struct Occurrence {
     #[easy_xml(rename = "One|ZeroOrOne|ZeroOrMore|OneOrMore|ZeroOrN|OneOrN")]
     variant: OccurrenceVariant,
 }
 
+impl From<OccurrenceVariant> for Occurrence {
+    fn from(variant: OccurrenceVariant) -> Self {
+        Self { variant }
+    }
+}
+
 impl std::ops::Deref for Occurrence {
     type Target = OccurrenceVariant;
 
@@ -164,7 +385,7 @@ impl std::ops::Deref for Occurrence {
     }
 }
 
-#[derive(XmlDeserialize, Debug)]
+#[derive(XmlDeserialize, Debug, PartialEq)]
 pub enum OccurrenceVariant {
     One,
     ZeroOrOne,
@@ -174,12 +395,18 @@ pub enum OccurrenceVariant {
     OneOrN(#[easy_xml(text)] i32),
 }
 
-#[derive(XmlDeserialize, Debug)]
+#[derive(XmlDeserialize, Debug, PartialEq)]
 pub struct Scope {
     #[easy_xml(rename = "Permanent|Dynamic")]
     pub variant: ScopeVariant,
 }
 
+impl From<ScopeVariant> for Scope {
+    fn from(variant: ScopeVariant) -> Self {
+        Self { variant }
+    }
+}
+
 impl std::ops::Deref for Scope {
     type Target = ScopeVariant;
 
@@ -188,18 +415,42 @@ impl std::ops::Deref for Scope {
     }
 }
 
-#[derive(XmlDeserialize, Debug)]
+#[derive(XmlDeserialize, Debug, PartialEq)]
 pub enum ScopeVariant {
     Permanent,
     Dynamic,
 }
 
-#[derive(XmlDeserialize, Debug)]
+/// Whether the value of a node is case sensitive.
+#[derive(XmlDeserialize, Debug, PartialEq)]
+pub struct CaseSense {
+    #[easy_xml(rename = "CS|CIS")]
+    pub variant: CaseSenseVariant,
+}
+
+impl std::ops::Deref for CaseSense {
+    type Target = CaseSenseVariant;
+
+    fn deref(&self) -> &Self::Target {
+        &self.variant
+    }
+}
+
+#[derive(XmlDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseSenseVariant {
+    /// Case sensitive.
+    CS,
+    /// Case insensitive.
+    CIS,
+}
+
+#[derive(XmlDeserialize, Debug, Default, PartialEq)]
 pub struct Element;
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::OsEdition;
 
     #[test]
     fn parse_test() {
@@ -207,6 +458,44 @@ mod test {
         dbg!(doc);
     }
 
+    #[test]
+    fn test_msft_properties() {
+        let doc = easy_xml::de::from_str::<MgmtTree>(Education_AreaDDF).unwrap();
+        let properties = &doc.nodes[0].children[0].properties;
+
+        assert_eq!(properties.df_type.mime.as_deref(), Some("text/plain"));
+        assert_eq!(
+            doc.nodes[0].properties.df_type.mime.as_deref(),
+            Some("com.microsoft/11/MDM/Policy")
+        );
+
+        let applicability = properties.applicability.as_ref().unwrap();
+        assert_eq!(
+            applicability.os_build_version.as_deref(),
+            Some("10.0.19041")
+        );
+        assert_eq!(applicability.csp_version.as_deref(), Some("10.0"));
+        assert!(applicability.allows(OsEdition::CORE));
+        assert!(applicability.allows(OsEdition::ENTERPRISE));
+        // `DefaultPrinterName` isn't available on Windows Home
+        let applicability = doc.nodes[0].children[1].properties.applicability.as_ref();
+        assert!(!applicability.unwrap().allows(OsEdition::CORE));
+
+        let gp_mapping = properties.gp_mapping.as_ref().unwrap();
+        assert_eq!(gp_mapping.gp_english_name, "AllowGraphingCalculator");
+        assert_eq!(
+            gp_mapping.gp_area_path,
+            "Programs~AT~WindowsComponents~Calculator"
+        );
+        assert_eq!(gp_mapping.gp_element, None);
+
+        assert_eq!(
+            properties.conflict_resolution,
+            Some(msft::ConflictResolution::LowestValueMostSecure)
+        );
+        assert!(properties.deprecated.is_none());
+    }
+
     const Education_AreaDDF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
 <!DOCTYPE MgmtTree PUBLIC " -//OMA//DTD-DM-DDF 1.2//EN"
   "http://www.openmobilealliance.org/tech/DTD/DM_DDF-V1_2.dtd"
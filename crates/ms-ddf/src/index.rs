@@ -0,0 +1,344 @@
+use std::collections::BTreeMap;
+
+use crate::{AccessType, AllowedValues, Applicability, DFFormatVariant, MgmtTree, Node};
+
+/// Whether a node configures the device or the user it's delivered for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Context {
+    Device,
+    User,
+}
+
+/// An index of the nodes of one or more DDF files by their OMA-URI.
+///
+/// CSPs like Policy are split across many files, which are merged together when they share a path.
+#[derive(Debug, Default)]
+pub struct DdfIndex<'a> {
+    device: Entry<'a>,
+    user: Entry<'a>,
+}
+
+#[derive(Debug, Default)]
+struct Entry<'a> {
+    /// The definition of the node. Segments of the root path of a file such as `Vendor` have none.
+    node: Option<&'a Node>,
+    children: BTreeMap<&'a str, Entry<'a>>,
+    /// The child with a name chosen by the management server, such as the profile name in `VPNv2/{ProfileName}`.
+    dynamic: Option<Box<Entry<'a>>>,
+}
+
+impl<'a> Entry<'a> {
+    fn insert(&mut self, node: &'a Node) {
+        let entry = if node.node_name.is_empty() {
+            &mut **self.dynamic.get_or_insert_with(Default::default)
+        } else {
+            self.children.entry(&node.node_name).or_default()
+        };
+
+        // The first definition wins if a node is defined twice
+        entry.node.get_or_insert(node);
+        for child in &node.children {
+            entry.insert(child);
+        }
+    }
+}
+
+impl<'a> DdfIndex<'a> {
+    pub fn new(trees: impl IntoIterator<Item = &'a MgmtTree>) -> Self {
+        let mut index = Self::default();
+        for tree in trees {
+            index.insert(tree);
+        }
+        index
+    }
+
+    pub fn insert(&mut self, tree: &'a MgmtTree) {
+        for node in &tree.nodes {
+            let (context, segments) = split_uri(node.path.as_deref().unwrap_or("."));
+            let mut entry = match context {
+                Context::Device => &mut self.device,
+                Context::User => &mut self.user,
+            };
+            for segment in segments {
+                entry = entry.children.entry(segment).or_default();
+            }
+            entry.insert(node);
+        }
+    }
+
+    /// Find the definition of a node from its OMA-URI, such as `./Device/Vendor/MSFT/Policy/Config/Education/EnableEduThemes`.
+    ///
+    /// URIs without `./Device` or `./User` are for the device. Dynamic segments match any name.
+    pub fn lookup(&self, uri: &str) -> Option<ResolvedNode<'a>> {
+        let (context, segments) = split_uri(uri);
+        let mut entry = match context {
+            Context::Device => &self.device,
+            Context::User => &self.user,
+        };
+
+        let mut path = context.root().to_string();
+        let mut dynamic = Vec::new();
+        let mut applicability = None;
+        for segment in segments {
+            entry = match (entry.children.get(segment), &entry.dynamic) {
+                (Some(child), _) => {
+                    path.push('/');
+                    path.push_str(segment);
+                    child
+                }
+                (None, Some(child)) => {
+                    path.push_str(&format!("/{{{}}}", dynamic_title(child)));
+                    dynamic.push(segment.to_string());
+                    child
+                }
+                (None, None) => return None,
+            };
+
+            if let Some(node_applicability) = entry
+                .node
+                .and_then(|node| node.properties.applicability.as_ref())
+            {
+                applicability = Some(node_applicability);
+            }
+        }
+
+        Some(ResolvedNode {
+            node: entry.node?,
+            context,
+            path,
+            dynamic,
+            applicability,
+        })
+    }
+
+    /// Every node in the index, with dynamic segments in their path written as `{DFTitle}`.
+    pub fn nodes(&self) -> Vec<ResolvedNode<'a>> {
+        fn visit<'a>(
+            entry: &Entry<'a>,
+            context: Context,
+            path: String,
+            applicability: Option<&'a Applicability>,
+            nodes: &mut Vec<ResolvedNode<'a>>,
+        ) {
+            let applicability = entry
+                .node
+                .and_then(|node| node.properties.applicability.as_ref())
+                .or(applicability);
+            if let Some(node) = entry.node {
+                nodes.push(ResolvedNode {
+                    node,
+                    context,
+                    path: path.clone(),
+                    dynamic: Vec::new(),
+                    applicability,
+                });
+            }
+
+            for (name, child) in &entry.children {
+                visit(
+                    child,
+                    context,
+                    format!("{path}/{name}"),
+                    applicability,
+                    nodes,
+                );
+            }
+            if let Some(child) = &entry.dynamic {
+                let path = format!("{path}/{{{}}}", dynamic_title(child));
+                visit(child, context, path, applicability, nodes);
+            }
+        }
+
+        let mut nodes = Vec::new();
+        for context in [Context::Device, Context::User] {
+            let entry = match context {
+                Context::Device => &self.device,
+                Context::User => &self.user,
+            };
+            visit(entry, context, context.root().into(), None, &mut nodes);
+        }
+        nodes
+    }
+}
+
+impl Context {
+    fn root(self) -> &'static str {
+        match self {
+            Self::Device => "./Device",
+            Self::User => "./User",
+        }
+    }
+}
+
+/// A node found in a [`DdfIndex`].
+#[derive(Debug, Clone)]
+pub struct ResolvedNode<'a> {
+    pub node: &'a Node,
+    pub context: Context,
+    /// The path of the node, with dynamic segments written as `{DFTitle}`.
+    pub path: String,
+    /// The names given to each dynamic segment of the URI.
+    pub dynamic: Vec<String>,
+    /// The applicability of the node, which is inherited from its closest ancestor with one.
+    pub applicability: Option<&'a Applicability>,
+}
+
+impl<'a> ResolvedNode<'a> {
+    pub fn access_type(&self) -> &'a AccessType {
+        &self.node.properties.access_type
+    }
+
+    pub fn format(&self) -> &'a DFFormatVariant {
+        &self.node.properties.df_format
+    }
+
+    pub fn allowed_values(&self) -> Option<&'a AllowedValues> {
+        self.node.properties.allowed_values.as_ref()
+    }
+
+    /// Whether the node is created by the management server instead of always existing.
+    pub fn is_dynamic(&self) -> bool {
+        matches!(
+            self.node.properties.scope.as_deref(),
+            Some(crate::ScopeVariant::Dynamic)
+        )
+    }
+}
+
+fn dynamic_title<'a>(entry: &Entry<'a>) -> &'a str {
+    entry
+        .node
+        .and_then(|node| node.properties.df_title.as_deref())
+        .unwrap_or_default()
+}
+
+/// Split an OMA-URI into its context and the segments after `./Device` or `./User`.
+fn split_uri(uri: &str) -> (Context, impl Iterator<Item = &str>) {
+    let uri = uri.trim();
+    let uri = uri.strip_prefix("./").unwrap_or(uri);
+    let uri = uri.strip_prefix('.').unwrap_or(uri);
+
+    let (context, rest) = match uri.split_once('/').unwrap_or((uri, "")) {
+        ("Device", rest) => (Context::Device, rest),
+        ("User", rest) => (Context::User, rest),
+        _ => (Context::Device, uri),
+    };
+    (
+        context,
+        rest.split('/').filter(|segment| !segment.is_empty()),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DDF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<MgmtTree xmlns:MSFT="http://schemas.microsoft.com/MobileDevice/DM">
+  <VerDTD>1.2</VerDTD>
+  <Node>
+    <NodeName>VPNv2</NodeName>
+    <Path>./User/Vendor/MSFT</Path>
+    <DFProperties>
+      <AccessType><Get /></AccessType>
+      <DFFormat><node /></DFFormat>
+      <Occurrence><One /></Occurrence>
+      <Scope><Permanent /></Scope>
+      <DFType><MIME /></DFType>
+      <MSFT:Applicability>
+        <MSFT:OsBuildVersion>10.0.10240</MSFT:OsBuildVersion>
+        <MSFT:CspVersion>1.0</MSFT:CspVersion>
+      </MSFT:Applicability>
+    </DFProperties>
+    <Node>
+      <NodeName></NodeName>
+      <DFProperties>
+        <AccessType><Add /><Delete /><Get /></AccessType>
+        <DFFormat><node /></DFFormat>
+        <Occurrence><ZeroOrMore /></Occurrence>
+        <Scope><Dynamic /></Scope>
+        <DFTitle>ProfileName</DFTitle>
+        <DFType><DDFName /></DFType>
+      </DFProperties>
+      <Node>
+        <NodeName>RememberCredentials</NodeName>
+        <DFProperties>
+          <AccessType><Add /><Delete /><Get /><Replace /></AccessType>
+          <DFFormat><bool /></DFFormat>
+          <Occurrence><ZeroOrOne /></Occurrence>
+          <Scope><Dynamic /></Scope>
+          <DFType><MIME>text/plain</MIME></DFType>
+        </DFProperties>
+      </Node>
+    </Node>
+  </Node>
+</MgmtTree>"#;
+
+    #[test]
+    fn test_lookup() {
+        let tree = crate::from_str(DDF).unwrap();
+        let index = DdfIndex::new([&tree]);
+
+        let node = index
+            .lookup("./User/Vendor/MSFT/VPNv2/Contoso/RememberCredentials")
+            .unwrap();
+        assert_eq!(node.context, Context::User);
+        assert_eq!(
+            node.path,
+            "./User/Vendor/MSFT/VPNv2/{ProfileName}/RememberCredentials"
+        );
+        assert_eq!(node.dynamic, ["Contoso"]);
+        assert!(matches!(node.format(), DFFormatVariant::Bool));
+        assert!(node.access_type().replace.is_some());
+        assert!(node.is_dynamic());
+        assert_eq!(
+            node.applicability
+                .and_then(|a| a.os_build_version.as_deref()),
+            Some("10.0.10240")
+        );
+
+        assert!(index.lookup("./User/Vendor/MSFT/VPNv2/Contoso").is_some());
+        // Intermediate segments of the root path aren't nodes
+        assert!(index.lookup("./User/Vendor/MSFT").is_none());
+        assert!(index.lookup("./Device/Vendor/MSFT/VPNv2").is_none());
+        assert!(index
+            .lookup("./User/Vendor/MSFT/VPNv2/Contoso/Missing")
+            .is_none());
+
+        let paths = index
+            .nodes()
+            .into_iter()
+            .map(|node| node.path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                "./User/Vendor/MSFT/VPNv2",
+                "./User/Vendor/MSFT/VPNv2/{ProfileName}",
+                "./User/Vendor/MSFT/VPNv2/{ProfileName}/RememberCredentials",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_uri() {
+        let split = |uri| {
+            let (context, segments) = split_uri(uri);
+            (context, segments.collect::<Vec<_>>())
+        };
+        assert_eq!(
+            split("./Device/Vendor/MSFT/Policy"),
+            (Context::Device, vec!["Vendor", "MSFT", "Policy"])
+        );
+        assert_eq!(
+            split("./User/Vendor/MSFT/"),
+            (Context::User, vec!["Vendor", "MSFT"])
+        );
+        assert_eq!(
+            split("./Vendor/MSFT"),
+            (Context::Device, vec!["Vendor", "MSFT"])
+        );
+        assert_eq!(split("."), (Context::Device, vec![]));
+        assert_eq!(split("./DevDetail"), (Context::Device, vec!["DevDetail"]));
+    }
+}
@@ -1,5 +1,16 @@
+pub mod admx;
 mod ddf_v2;
+mod index;
 mod msft;
+mod os_edition;
+mod parse;
+mod ser;
+mod validate;
 
 pub use ddf_v2::*;
+pub use index::*;
 pub use msft::*;
+pub use os_edition::OsEdition;
+pub use parse::{from_bytes, from_bytes_lenient, from_str, from_str_lenient, Error};
+pub use ser::to_string;
+pub use validate::*;
@@ -1,91 +1,627 @@
+use std::{fmt, str::FromStr};
+
 use easy_xml::{XmlDeserialize, XmlElement};
 use easy_xml_derive::XmlDeserialize;
 
-#[derive(XmlDeserialize, Debug)]
+use crate::OsEdition;
+
+/// The values a node accepts, from `MSFT:AllowedValues`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AllowedValues {
+    /// One of the listed values.
+    Enum(Vec<EnumValue>),
+    /// A bitmask of the listed values.
+    Flag(Vec<EnumValue>),
+    Range(Range),
+    /// Multiple values joined by a delimiter, each of which must be allowed by `item`.
+    List {
+        delimiter: char,
+        item: Box<AllowedValues>,
+    },
+    /// The node is backed by a Group Policy defined in an ADMX file.
+    Admx(AdmxBacked),
+    /// A regular expression the value must match.
+    Regex(String),
+    /// An XML schema the value must conform to.
+    Xsd(String),
+    /// A security descriptor, optionally with an example.
+    Sddl(Option<String>),
+    /// A JSON document, with an example.
+    Json(String),
+    /// Any value.
+    None,
+}
+
+impl AllowedValues {
+    /// The constraint on each value, looking through any list.
+    pub fn item(&self) -> &AllowedValues {
+        match self {
+            Self::List { item, .. } => item.item(),
+            values => values,
+        }
+    }
+}
+
+/// The `ValueType` of each variant of [`AllowedValues`].
+pub(crate) const VALUE_TYPES: &[&str] = &[
+    "ENUM", "Flag", "Range", "ADMX", "RegEx", "XSD", "SDDL", "JSON", "None",
+];
+
+/// The raw `MSFT:AllowedValues` element, as the meaning of its children depends on `ValueType`.
+#[derive(XmlDeserialize)]
 #[easy_xml(prefix = "MSFT")]
-pub struct AllowedValues {
+struct RawAllowedValues {
     #[easy_xml(rename = "ValueType", attribute)]
-    pub value_type: ValueType,
-    #[easy_xml(flatten)]
-    pub values: AllowedValueGroupedNodes,
+    value_type: String,
+    #[easy_xml(prefix = "MSFT", rename = "Enum")]
+    enums: Vec<EnumValue>,
+    #[easy_xml(prefix = "MSFT", rename = "Value")]
+    value: Option<String>,
+    #[easy_xml(prefix = "MSFT", rename = "ValueDescription")]
+    value_description: Option<String>,
+    #[easy_xml(prefix = "MSFT", rename = "List")]
+    list: Option<List>,
+    #[easy_xml(prefix = "MSFT", rename = "AdmxBacked")]
+    admx: Option<AdmxBacked>,
 }
 
-#[derive(Debug)]
-pub enum AllowedValueGroupedNodes {
-    Enum(Vec<ValueAndDescriptionGroup>),
-    Value(ValueAndDescriptionGroup),
-    Admx(AdmxBacked),
+impl TryFrom<RawAllowedValues> for AllowedValues {
+    type Error = String;
+
+    fn try_from(raw: RawAllowedValues) -> Result<Self, Self::Error> {
+        let value = || {
+            raw.value
+                .clone()
+                .ok_or_else(|| format!("missing value for {:?}", raw.value_type))
+        };
+
+        let values = match raw.value_type.as_str() {
+            "ENUM" => Self::Enum(raw.enums.clone()),
+            "Flag" => Self::Flag(raw.enums.clone()),
+            "Range" => {
+                let mut range = value()?.parse::<Range>()?;
+                range.description = raw.value_description.clone();
+                Self::Range(range)
+            }
+            "ADMX" => Self::Admx(raw.admx.clone().ok_or("missing AdmxBacked for \"ADMX\"")?),
+            // Some DDFs have an empty pattern, which doesn't constrain the value
+            "RegEx" => raw.value.clone().map_or(Self::None, Self::Regex),
+            "XSD" => Self::Xsd(value()?),
+            "SDDL" => Self::Sddl(raw.value.clone()),
+            "JSON" => Self::Json(value()?),
+            "None" => Self::None,
+            value_type => return Err(format!("unknown value type {value_type:?}")),
+        };
+
+        Ok(match raw.list {
+            Some(list) => Self::List {
+                delimiter: list.delimiter()?,
+                item: Box::new(values),
+            },
+            None => values,
+        })
+    }
+}
+
+impl AllowedValues {
+    /// Parse a `MSFT:AllowedValues` element, with why it's invalid.
+    pub(crate) fn from_element(element: &XmlElement) -> Result<Self, String> {
+        RawAllowedValues::deserialize(element)
+            .map_err(|_| "malformed allowed values".to_string())?
+            .try_into()
+    }
 }
 
-impl XmlDeserialize for AllowedValueGroupedNodes {
-    fn deserialize(element: &easy_xml::XmlElement) -> Result<Self, easy_xml::de::Error>
+impl XmlDeserialize for AllowedValues {
+    fn deserialize(element: &XmlElement) -> Result<Self, easy_xml::de::Error>
     where
         Self: Sized,
     {
-        let XmlElement::Node(node) = element else {
-            return Err(easy_xml::de::Error::BadXml);
-        };
+        Self::from_element(element).map_err(invalid)
+    }
+}
+
+/// Why an `MSFT:AllowedValues` element is invalid, if it is. This always deserializes so the message isn't lost.
+pub(crate) struct AllowedValuesError(pub(crate) Option<String>);
 
-        let node = node.borrow();
+impl XmlDeserialize for AllowedValuesError {
+    fn deserialize(element: &XmlElement) -> Result<Self, easy_xml::de::Error>
+    where
+        Self: Sized,
+    {
+        Ok(Self(AllowedValues::from_element(element).err()))
+    }
+}
 
-        let first_name = {
-            let mut first_node = None;
+/// `easy_xml` errors can't hold a message, so [`crate::from_str`] finds out why an element is invalid when the document fails to parse.
+fn invalid(_message: String) -> easy_xml::de::Error {
+    easy_xml::de::Error::BadXml
+}
 
-            for element in &node.elements {
-                if let XmlElement::Node(node) = element {
-                    first_node = Some(node.borrow());
-                    break;
-                };
-            }
+#[derive(XmlDeserialize, Debug, Clone, PartialEq)]
+#[easy_xml(prefix = "MSFT")]
+pub struct EnumValue {
+    #[easy_xml(prefix = "MSFT", rename = "Value")]
+    pub value: String,
+    #[easy_xml(prefix = "MSFT", rename = "ValueDescription")]
+    pub description: Option<String>,
+}
+
+#[derive(XmlDeserialize)]
+#[easy_xml(prefix = "MSFT")]
+pub(crate) struct List {
+    #[easy_xml(rename = "Delimiter", attribute)]
+    pub(crate) delimiter: String,
+}
+
+impl List {
+    /// The delimiter is a character or a code point written as `0xF000` or `\xF000`.
+    /// Without one the values are separated by `U+F000` like other multi-string values.
+    pub(crate) fn delimiter(&self) -> Result<char, String> {
+        let delimiter = self.delimiter.as_str();
+        let code_point = delimiter
+            .strip_prefix("0x")
+            .or_else(|| delimiter.strip_prefix("\\x"));
 
-            let Some(first_node) = first_node else {
-                return Err(easy_xml::de::Error::BadXml);
-            };
+        match (code_point, delimiter.chars().count()) {
+            (Some(hex), _) => u32::from_str_radix(hex, 16)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| format!("invalid list delimiter {delimiter:?}")),
+            (None, 0) => Ok('\u{F000}'),
+            (None, 1) => Ok(delimiter.chars().next().unwrap()),
+            (None, _) => Err(format!("invalid list delimiter {delimiter:?}")),
+        }
+    }
+}
 
-            first_node.name.local_name.clone()
+/// An inclusive range of integers, written as `[0-100]`, `[5]` or `[(-1)-2147483647]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub min: i64,
+    pub max: i64,
+    /// The node's `DefaultValue`, if it has one.
+    ///
+    /// This is filled in by [`crate::from_str`] and the other parse functions, but not when deserializing with `easy_xml` directly.
+    pub default: Option<i64>,
+    pub description: Option<String>,
+}
+
+impl Range {
+    pub fn contains(&self, value: i64) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+}
+
+impl FromStr for Range {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid range {s:?}");
+        let inner = s
+            .trim()
+            .strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .ok_or_else(invalid)?;
+
+        let (min, rest) = parse_bound(inner).ok_or_else(invalid)?;
+        let max = match rest.strip_prefix('-') {
+            Some(rest) => match parse_bound(rest) {
+                Some((max, "")) => max,
+                _ => return Err(invalid()),
+            },
+            None if rest.is_empty() => min,
+            None => return Err(invalid()),
         };
+        if min > max {
+            return Err(invalid());
+        }
 
-        Ok(match first_name.as_str() {
-            "Enum" => Self::Enum(
-                node.elements
-                    .iter()
-                    .filter(|e| matches!(e, XmlElement::Node(_)))
-                    .map(ValueAndDescriptionGroup::deserialize)
-                    .collect::<Result<Vec<_>, _>>()?,
-            ),
-            "AdmxBacked" => Self::Admx(AdmxBacked::deserialize(&node.elements[0])?),
-            "Value" | "ValueDescription" => {
-                Self::Value(ValueAndDescriptionGroup::deserialize(element)?)
-            }
-            _ => return Err(easy_xml::de::Error::BadXml),
+        Ok(Self {
+            min,
+            max,
+            default: None,
+            description: None,
         })
     }
 }
 
-#[derive(XmlDeserialize, Debug)]
-pub enum ValueType {
-    ENUM,
-    ADMX,
-    Range,
-    None,
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bound = |n: i64| {
+            if n < 0 {
+                format!("({n})")
+            } else {
+                n.to_string()
+            }
+        };
+
+        if self.min == self.max {
+            write!(f, "[{}]", bound(self.min))
+        } else {
+            write!(f, "[{}-{}]", bound(self.min), bound(self.max))
+        }
+    }
 }
 
-#[derive(XmlDeserialize, Debug)]
-#[easy_xml(prefix = "MSFT")]
-pub struct ValueAndDescriptionGroup {
-    #[easy_xml(prefix = "MSFT", rename = "Value")]
-    pub value: String,
-    #[easy_xml(prefix = "MSFT", rename = "ValueDescription")]
-    pub value_description: Option<String>,
+/// Parse an integer from the start of `s`. Negative numbers are wrapped in parentheses so the sign isn't mistaken for the range separator.
+fn parse_bound(s: &str) -> Option<(i64, &str)> {
+    let (number, rest) = match s.strip_prefix('(') {
+        Some(s) => {
+            let (number, rest) = s.split_once(')')?;
+            (number, rest)
+        }
+        None => {
+            let end = s
+                .char_indices()
+                .find(|(i, c)| !(c.is_ascii_digit() || (*i == 0 && *c == '-')))
+                .map_or(s.len(), |(i, _)| i);
+            s.split_at(end)
+        }
+    };
+    Some((number.trim().parse().ok()?, rest))
 }
 
-#[derive(XmlDeserialize, Debug)]
+/// A reference to the Group Policy which backs the node, resolved with [`crate::admx`].
+#[derive(XmlDeserialize, Debug, Clone, PartialEq)]
 #[easy_xml(prefix = "MSFT")]
 pub struct AdmxBacked {
-    #[easy_xml(rename = "Area")]
+    /// The category path of the policy, such as `ActiveXInstallService~AT~WindowsComponents~AxInstSv`.
+    #[easy_xml(rename = "Area", attribute)]
     pub area: String,
-    #[easy_xml(rename = "Nrea")]
+    /// The name of the policy within the ADMX file.
+    #[easy_xml(rename = "Name", attribute)]
     pub name: String,
-    #[easy_xml(rename = "Frea")]
+    /// The ADMX file the policy is defined in, such as `ActiveXInstallService.admx`.
+    #[easy_xml(rename = "File", attribute)]
     pub file: String,
 }
+
+impl AdmxBacked {
+    /// The target namespace prefix of the ADMX file.
+    pub fn prefix(&self) -> &str {
+        self.area
+            .split_once("~AT~")
+            .map_or("", |(prefix, _)| prefix)
+    }
+
+    /// The categories of the policy, from the root category to the one containing it.
+    pub fn categories(&self) -> impl Iterator<Item = &str> {
+        let categories = self.area.split_once("~AT~").map_or("", |(_, path)| path);
+        categories
+            .split('~')
+            .filter(|category| !category.is_empty())
+    }
+}
+
+/// What a device requires for the node to apply.
+///
+/// Microsoft intends children to inherit the applicability of their parent, but this only holds what is set on the node itself.
+/// Consumers should use the closest ancestor's when a node has none.
+#[derive(XmlDeserialize, Debug, PartialEq)]
+#[easy_xml(prefix = "MSFT")]
+pub struct Applicability {
+    /// The first build the node was released in, such as `10.0.19041`.
+    #[easy_xml(prefix = "MSFT", rename = "OsBuildVersion")]
+    pub os_build_version: Option<String>,
+    #[easy_xml(prefix = "MSFT", rename = "CspVersion")]
+    pub csp_version: Option<String>,
+    #[easy_xml(prefix = "MSFT", rename = "EditionAllowList")]
+    pub edition_allow_list: Option<EditionAllowList>,
+    #[easy_xml(prefix = "MSFT", rename = "RequiresAzureAd")]
+    pub requires_azure_ad: Option<crate::Element>,
+}
+
+impl Applicability {
+    /// Whether the node applies to an edition. Nodes without an allow list apply to every edition.
+    pub fn allows(&self, edition: OsEdition) -> bool {
+        self.edition_allow_list
+            .as_ref()
+            .is_none_or(|list| list.ids.iter().any(|id| id.matches(edition.id())))
+    }
+}
+
+/// The editions a node is allowed on, written as `0x4;0x1B;0x30;`.
+#[derive(Debug, PartialEq)]
+pub struct EditionAllowList {
+    /// The raw edition ids. This includes ids which aren't known to [`OsEdition`].
+    pub ids: Vec<EditionId>,
+}
+
+impl EditionAllowList {
+    /// The known editions which are allowed.
+    pub fn editions(&self) -> impl Iterator<Item = OsEdition> + '_ {
+        OsEdition::ALL
+            .iter()
+            .copied()
+            .filter(|edition| self.ids.iter().any(|id| id.matches(edition.id())))
+    }
+}
+
+/// An entry in an [`EditionAllowList`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditionId {
+    /// A single edition, written as `0x4`.
+    Id(u32),
+    /// Every edition whose id starts with these hex digits, written as `0x88*`. This is used for the Windows Holographic editions.
+    Prefix(u32),
+}
+
+impl EditionId {
+    pub fn matches(self, id: u32) -> bool {
+        match self {
+            Self::Id(expected) => id == expected,
+            Self::Prefix(prefix) => {
+                let mut id = id;
+                while id > prefix {
+                    id >>= 4;
+                }
+                id == prefix
+            }
+        }
+    }
+}
+
+impl fmt::Display for EditionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "0x{id:X}"),
+            Self::Prefix(prefix) => write!(f, "0x{prefix:X}*"),
+        }
+    }
+}
+
+impl std::str::FromStr for EditionAllowList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ids = s
+            .split(';')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                let id = id.trim_start_matches("0x").trim_start_matches("0X");
+                let hex = |hex| {
+                    u32::from_str_radix(hex, 16)
+                        .map_err(|err| format!("invalid edition {id:?}: {err}"))
+                };
+                Ok(match id.strip_suffix('*') {
+                    Some(prefix) => EditionId::Prefix(hex(prefix)?),
+                    None => EditionId::Id(hex(id)?),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { ids })
+    }
+}
+
+impl XmlDeserialize for EditionAllowList {
+    fn deserialize(element: &XmlElement) -> Result<Self, easy_xml::de::Error>
+    where
+        Self: Sized,
+    {
+        Text::deserialize(element)?.text.parse().map_err(invalid)
+    }
+}
+
+/// The Group Policy which the node configures.
+#[derive(XmlDeserialize, Debug, PartialEq)]
+#[easy_xml(prefix = "MSFT")]
+pub struct GpMapping {
+    #[easy_xml(rename = "GpEnglishName", attribute)]
+    pub gp_english_name: String,
+    /// The category of the policy, such as `Printing~AT~ControlPanel~CplPrinters`.
+    #[easy_xml(rename = "GpAreaPath", attribute)]
+    pub gp_area_path: String,
+    /// The element of the policy the node maps to, if it only maps to part of it.
+    #[easy_xml(rename = "GpElement", attribute)]
+    pub gp_element: Option<String>,
+}
+
+/// How the value is chosen when multiple sources configure the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    NoMerge,
+    LowestValueMostSecure,
+    HighestValueMostSecure,
+    LastWrite,
+    LowestValueMostSecureZeroHasNoLimits,
+    HighestValueMostSecureZeroHasNoLimits,
+}
+
+impl std::str::FromStr for ConflictResolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.trim() {
+            "NoMerge" => Self::NoMerge,
+            "LowestValueMostSecure" => Self::LowestValueMostSecure,
+            "HighestValueMostSecure" => Self::HighestValueMostSecure,
+            "LastWrite" => Self::LastWrite,
+            "LowestValueMostSecureZeroHasNoLimits" => Self::LowestValueMostSecureZeroHasNoLimits,
+            "HighestValueMostSecureZeroHasNoLimits" => Self::HighestValueMostSecureZeroHasNoLimits,
+            s => return Err(format!("unknown conflict resolution {s:?}")),
+        })
+    }
+}
+
+impl XmlDeserialize for ConflictResolution {
+    fn deserialize(element: &XmlElement) -> Result<Self, easy_xml::de::Error>
+    where
+        Self: Sized,
+    {
+        Text::deserialize(element)?.text.parse().map_err(invalid)
+    }
+}
+
+#[derive(XmlDeserialize, Debug, PartialEq)]
+#[easy_xml(prefix = "MSFT")]
+pub struct Deprecated {
+    /// The build from which the node should no longer be set.
+    #[easy_xml(rename = "OsBuildDeprecated", attribute)]
+    pub os_build_deprecated: Option<String>,
+}
+
+/// The text content of an element.
+#[derive(XmlDeserialize)]
+struct Text {
+    #[easy_xml(text)]
+    text: String,
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use super::*;
+    use crate::Node;
+
+    #[test]
+    fn test_range() {
+        let range = "[0-100]".parse::<Range>().unwrap();
+        assert_eq!((range.min, range.max), (0, 100));
+        assert!(range.contains(0) && range.contains(100) && !range.contains(101));
+
+        let range = "[(-1)-2147483647]".parse::<Range>().unwrap();
+        assert_eq!((range.min, range.max), (-1, 2147483647));
+        assert_eq!(range.to_string(), "[(-1)-2147483647]");
+
+        let range = "[5]".parse::<Range>().unwrap();
+        assert_eq!((range.min, range.max), (5, 5));
+        assert_eq!(range.to_string(), "[5]");
+
+        for invalid in ["0-100", "[100-0]", "[a-b]", "[1-]", "[6,17]", "[]"] {
+            assert!(invalid.parse::<Range>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_list_delimiter() {
+        let delimiter = |delimiter: &str| {
+            List {
+                delimiter: delimiter.into(),
+            }
+            .delimiter()
+        };
+        assert_eq!(delimiter(";"), Ok(';'));
+        assert_eq!(delimiter("0xF000"), Ok('\u{F000}'));
+        assert_eq!(delimiter("\\xF000"), Ok('\u{F000}'));
+        assert_eq!(delimiter(""), Ok('\u{F000}'));
+        assert!(delimiter(";;").is_err());
+    }
+
+    /// Every DDF file shipped with `apps/ingest` must parse.
+    #[test]
+    fn test_ddf_files() {
+        fn visit<'a>(node: &'a Node, values: &mut Vec<&'a AllowedValues>) {
+            values.extend(&node.properties.allowed_values);
+            for child in &node.children {
+                visit(child, values);
+            }
+        }
+
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../apps/ingest/ddf");
+        let mut values = Vec::new();
+        let trees = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "xml"))
+            .map(|path| {
+                let contents = fs::read(&path).unwrap();
+                crate::from_bytes(&contents).unwrap_or_else(|_| panic!("failed to parse {path:?}"))
+            })
+            .collect::<Vec<_>>();
+        for tree in &trees {
+            for node in &tree.nodes {
+                visit(node, &mut values);
+            }
+        }
+
+        let any = |f: fn(&AllowedValues) -> bool| values.iter().any(|v| f(v));
+        assert!(any(
+            |v| matches!(v, AllowedValues::Enum(values) if !values.is_empty())
+        ));
+        assert!(any(|v| matches!(v, AllowedValues::Range(_))));
+        assert!(any(|v| matches!(v, AllowedValues::Admx(_))));
+        assert!(any(|v| matches!(v, AllowedValues::Regex(_))));
+        assert!(any(|v| matches!(v, AllowedValues::Xsd(_))));
+        assert!(any(|v| matches!(
+            v,
+            AllowedValues::List {
+                delimiter: '\u{F000}',
+                ..
+            }
+        )));
+        assert!(any(|v| matches!(
+            v,
+            AllowedValues::List { delimiter: '|', .. }
+        )));
+
+        // Range defaults are filled in from the node's `DefaultValue`
+        assert!(any(|v| matches!(
+            v,
+            AllowedValues::Range(Range {
+                default: Some(_),
+                ..
+            })
+        )));
+    }
+
+    #[test]
+    fn test_admx_backed() {
+        let backed = AdmxBacked {
+            area: "ActiveXInstallService~AT~WindowsComponents~AxInstSv".into(),
+            name: "AxISURLZonePolicies".into(),
+            file: "ActiveXInstallService.admx".into(),
+        };
+        assert_eq!(backed.prefix(), "ActiveXInstallService");
+        assert_eq!(
+            backed.categories().collect::<Vec<_>>(),
+            ["WindowsComponents", "AxInstSv"]
+        );
+    }
+
+    #[test]
+    fn test_edition_allow_list() {
+        let list = "0x4;0x1B;0x65;0x88*;0xFFFF;"
+            .parse::<EditionAllowList>()
+            .unwrap();
+        assert_eq!(
+            list.ids,
+            [
+                EditionId::Id(0x4),
+                EditionId::Id(0x1B),
+                EditionId::Id(0x65),
+                EditionId::Prefix(0x88),
+                EditionId::Id(0xFFFF)
+            ]
+        );
+        assert_eq!(
+            list.editions().collect::<Vec<_>>(),
+            [
+                OsEdition::ENTERPRISE,
+                OsEdition::ENTERPRISE_N,
+                OsEdition::CORE,
+                OsEdition::HOLOGRAPHIC_BUSINESS
+            ]
+        );
+
+        let applicability = Applicability {
+            os_build_version: None,
+            csp_version: None,
+            edition_allow_list: Some(list),
+            requires_azure_ad: None,
+        };
+        assert!(applicability.allows(OsEdition::HOLOGRAPHIC_BUSINESS));
+        assert!(!applicability.allows(OsEdition::HOLOGRAPHIC));
+        assert!(EditionId::Prefix(0x88).matches(0x88A));
+        assert!(!EditionId::Prefix(0x88).matches(0x89));
+        assert!(!EditionId::Prefix(0x88).matches(0x8));
+        assert_eq!(EditionId::Prefix(0x88).to_string(), "0x88*");
+        assert_eq!(EditionId::Id(0x1B).to_string(), "0x1B");
+        assert_eq!(
+            "0x4;nope".parse::<EditionAllowList>().unwrap_err(),
+            "invalid edition \"nope\": invalid digit found in string"
+        );
+    }
+}
@@ -0,0 +1,885 @@
+//! Parsing DDF files, with the location of anything which is wrong.
+//!
+//! `easy_xml` only reports that a document is invalid, and it drops optional elements which are malformed instead of failing.
+//! So each node is first checked against the values the DDF DTD allows, and nodes are parsed on their own when any of them
+//! has a problem so only the malformed ones are skipped.
+
+use std::fmt;
+
+use crate::{msft, MgmtTree, Node};
+
+const NAMESPACE: &str = "http://schemas.microsoft.com/MobileDevice/DM";
+
+const FORMATS: &[&str] = &[
+    "b64", "bin", "bool", "chr", "int", "node", "null", "xml", "date", "time", "float",
+];
+const OCCURRENCES: &[&str] = &[
+    "One",
+    "ZeroOrOne",
+    "ZeroOrMore",
+    "OneOrMore",
+    "ZeroOrN",
+    "OneOrN",
+];
+const SCOPES: &[&str] = &["Permanent", "Dynamic"];
+const CASE_SENSES: &[&str] = &["CS", "CIS"];
+const ACCESS_TYPES: &[&str] = &["Add", "Copy", "Delete", "Exec", "Get", "Replace"];
+
+/// Why a DDF file couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    /// The path of the element, with nodes labelled by their name such as `MgmtTree/Node[Policy]/Node[Config]/DFProperties`.
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    /// The value which was unexpected, if any.
+    pub value: Option<String>,
+    pub message: String,
+}
+
+impl Error {
+    fn at(src: &str, offset: usize, path: String, message: impl Into<String>) -> Self {
+        let before = &src[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            path,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            value: None,
+            message: message.into(),
+        }
+    }
+
+    fn value(mut self, value: impl Into<String>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)?;
+        if let Some(value) = &self.value {
+            write!(f, " {value:?}")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Parse a DDF file.
+pub fn from_str(s: &str) -> Result<MgmtTree, Error> {
+    let (tree, errors) = from_str_lenient(s)?;
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(tree),
+    }
+}
+
+/// Parse a DDF file.
+pub fn from_bytes(bytes: &[u8]) -> Result<MgmtTree, Error> {
+    from_str(utf8(bytes)?)
+}
+
+/// Parse a DDF file, skipping any nodes which are malformed instead of failing.
+///
+/// An error is returned for each node which was skipped. The children of a malformed node are skipped with it.
+pub fn from_str_lenient(s: &str) -> Result<(MgmtTree, Vec<Error>), Error> {
+    let s = s.strip_prefix('\u{FEFF}').unwrap_or(s);
+    let document = Document::scan(s)?;
+    if document.ver_dtd.is_some() && document.nodes.iter().all(|span| is_valid(s, span)) {
+        if let Ok(mut tree) = easy_xml::de::from_str::<MgmtTree>(s) {
+            tree.resolve();
+            return Ok((tree, Vec::new()));
+        }
+    }
+
+    let mut errors = Vec::new();
+    let ver_dtd = document.ver_dtd.unwrap_or_else(|| {
+        errors.push(Error::at(s, 0, "MgmtTree".into(), "missing VerDTD"));
+        "1.2".into()
+    });
+    let mut tree = MgmtTree {
+        ver_dtd,
+        nodes: document
+            .nodes
+            .iter()
+            .filter_map(|span| parse_node(s, span, &mut errors))
+            .collect(),
+    };
+    tree.resolve();
+    Ok((tree, errors))
+}
+
+/// Parse a DDF file, skipping any nodes which are malformed. See [`from_str_lenient`].
+pub fn from_bytes_lenient(bytes: &[u8]) -> Result<(MgmtTree, Vec<Error>), Error> {
+    from_str_lenient(utf8(bytes)?)
+}
+
+fn utf8(bytes: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(bytes).map_err(|err| {
+        let valid = std::str::from_utf8(&bytes[..err.valid_up_to()]).unwrap_or_default();
+        Error::at(valid, valid.len(), String::new(), "invalid UTF-8")
+    })
+}
+
+fn parse_node(src: &str, span: &NodeSpan, errors: &mut Vec<Error>) -> Option<Node> {
+    if is_valid(src, span) {
+        if let Some(node) = parse_fragment(&src[span.start..span.end]) {
+            return Some(node);
+        }
+    }
+    if let Some(error) = check(src, span) {
+        errors.push(error);
+        return None;
+    }
+
+    // Parse the node without its children so a malformed child doesn't take its parent with it
+    let mut shell = String::new();
+    let mut last = span.start;
+    for child in &span.children {
+        shell.push_str(&src[last..child.start]);
+        last = child.end;
+    }
+    shell.push_str(&src[last..span.end]);
+
+    match parse_fragment(&shell) {
+        Some(mut node) => {
+            node.children = span
+                .children
+                .iter()
+                .filter_map(|child| parse_node(src, child, errors))
+                .collect();
+            Some(node)
+        }
+        None => {
+            errors.push(diagnose(src, span));
+            None
+        }
+    }
+}
+
+fn parse_fragment(node: &str) -> Option<Node> {
+    let xml =
+        format!(r#"<MgmtTree xmlns:MSFT="{NAMESPACE}"><VerDTD>1.2</VerDTD>{node}</MgmtTree>"#);
+    easy_xml::de::from_str::<MgmtTree>(&xml).ok()?.nodes.pop()
+}
+
+/// Why an `MSFT:AllowedValues` element is invalid, such as a range without a value.
+fn check_allowed_values(element: &str) -> Option<String> {
+    let xml = element.replacen(
+        "<MSFT:AllowedValues",
+        &format!(r#"<MSFT:AllowedValues xmlns:MSFT="{NAMESPACE}""#),
+        1,
+    );
+    easy_xml::de::from_str::<msft::AllowedValuesError>(&xml)
+        .ok()?
+        .0
+}
+
+/// Where each node is in a document.
+struct Document {
+    ver_dtd: Option<String>,
+    nodes: Vec<NodeSpan>,
+}
+
+#[derive(Debug)]
+struct NodeSpan {
+    start: usize,
+    end: usize,
+    /// The path of the parent element.
+    parent: String,
+    children: Vec<NodeSpan>,
+}
+
+impl Document {
+    /// Find the nodes of a document, checking it's well formed.
+    fn scan(src: &str) -> Result<Self, Error> {
+        let mut document = Document {
+            ver_dtd: None,
+            nodes: Vec::new(),
+        };
+        let mut elements = Vec::<(&str, String)>::new();
+        let mut nodes = Vec::<NodeSpan>::new();
+        let path = |elements: &[(&str, String)]| {
+            let labels = elements.iter().map(|(_, label)| label.as_str());
+            labels.collect::<Vec<_>>().join("/")
+        };
+
+        for token in Scanner::new(src, 0) {
+            let (name, offset, end) = match token? {
+                Token::Start {
+                    name,
+                    offset,
+                    end,
+                    empty,
+                    ..
+                } => {
+                    if elements.is_empty() && name != "MgmtTree" {
+                        return Err(
+                            Error::at(src, offset, String::new(), "expected MgmtTree").value(name)
+                        );
+                    }
+                    if name == "Node" {
+                        nodes.push(NodeSpan {
+                            start: offset,
+                            end: 0,
+                            parent: path(&elements),
+                            children: Vec::new(),
+                        });
+                    }
+                    elements.push((name, name.to_string()));
+                    if !empty {
+                        continue;
+                    }
+                    (name, offset, end)
+                }
+                Token::Text { text, .. } => {
+                    match elements.as_mut_slice() {
+                        [.., (_, label), ("NodeName", _)] => {
+                            *label = format!("Node[{}]", text.trim());
+                        }
+                        [(_, _), ("VerDTD", _)] => document.ver_dtd = Some(text.trim().into()),
+                        _ => {}
+                    }
+                    continue;
+                }
+                Token::End { name, offset, end } => (name, offset, end),
+            };
+
+            match elements.pop() {
+                Some((expected, _)) if expected == name => {}
+                Some((expected, _)) => {
+                    return Err(Error::at(
+                        src,
+                        offset,
+                        path(&elements),
+                        format!("expected </{expected}>"),
+                    )
+                    .value(name))
+                }
+                None => {
+                    return Err(
+                        Error::at(src, offset, String::new(), "unexpected end tag").value(name)
+                    )
+                }
+            }
+
+            if name == "Node" {
+                let mut span = nodes.pop().expect("a node was started");
+                span.end = end;
+                match nodes.last_mut() {
+                    Some(parent) => parent.children.push(span),
+                    None => document.nodes.push(span),
+                }
+            }
+        }
+
+        match elements.last() {
+            Some((name, _)) => Err(Error::at(
+                src,
+                src.len(),
+                path(&elements),
+                format!("unexpected end of file, expected </{name}>"),
+            )),
+            None => Ok(document),
+        }
+    }
+}
+
+/// An element being checked by [`diagnose`].
+struct Frame<'a> {
+    name: &'a str,
+    label: String,
+    offset: usize,
+    children: Vec<&'a str>,
+    value_type: Option<String>,
+}
+
+/// Whether a node and all of its children pass [`check`].
+fn is_valid(src: &str, span: &NodeSpan) -> bool {
+    check(src, span).is_none() && span.children.iter().all(|child| is_valid(src, child))
+}
+
+/// Find out why a node couldn't be parsed, ignoring its children.
+fn diagnose(src: &str, span: &NodeSpan) -> Error {
+    check(src, span)
+        .unwrap_or_else(|| Error::at(src, span.start, span.parent.clone(), "invalid node"))
+}
+
+/// Check a node against what the DDF DTD allows, ignoring its children.
+fn check(src: &str, span: &NodeSpan) -> Option<Error> {
+    let mut frames = Vec::<Frame>::new();
+    let path = |frames: &[Frame]| {
+        std::iter::once(span.parent.as_str())
+            .chain(frames.iter().map(|frame| frame.label.as_str()))
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+
+    for token in Scanner::new(src, span.start) {
+        let Ok(token) = token else {
+            break;
+        };
+        let offset = token.offset();
+        if offset >= span.end {
+            break;
+        }
+        if span
+            .children
+            .iter()
+            .any(|child| (child.start..child.end).contains(&offset))
+        {
+            continue;
+        }
+
+        let error = |frames: &[Frame], message: &str| {
+            Error::at(src, offset, path(frames), message.to_string())
+        };
+        let end = match &token {
+            Token::Start { end, .. } | Token::End { end, .. } => *end,
+            Token::Text { .. } => offset,
+        };
+        let (name, empty) = match token {
+            Token::Start {
+                name,
+                attributes,
+                empty,
+                ..
+            } => {
+                let allowed = match frames.last_mut() {
+                    Some(parent) => {
+                        parent.children.push(name);
+                        match parent.name {
+                            "DFFormat" => Some((FORMATS, "unknown format")),
+                            "Occurrence" => Some((OCCURRENCES, "unknown occurrence")),
+                            "Scope" => Some((SCOPES, "unknown scope")),
+                            "CaseSense" => Some((CASE_SENSES, "unknown case sensitivity")),
+                            "AccessType" => Some((ACCESS_TYPES, "unknown access type")),
+                            _ => None,
+                        }
+                    }
+                    None => None,
+                };
+                let attribute = |key: &str| {
+                    attributes
+                        .iter()
+                        .find(|(name, _)| *name == key)
+                        .map(|(_, value)| value.as_str())
+                };
+
+                frames.push(Frame {
+                    name,
+                    label: name.to_string(),
+                    offset,
+                    children: Vec::new(),
+                    value_type: None,
+                });
+                if let Some((allowed, message)) = allowed {
+                    if !allowed.contains(&name) {
+                        return Some(error(&frames, message).value(name));
+                    }
+                }
+                let required: &[&str] = match name {
+                    "MSFT:AdmxBacked" => &["Area", "Name", "File"],
+                    "MSFT:GpMapping" => &["GpEnglishName", "GpAreaPath"],
+                    _ => &[],
+                };
+                if let Some(missing) = required.iter().find(|key| attribute(key).is_none()) {
+                    return Some(error(&frames, &format!("missing {missing} attribute")));
+                }
+
+                match name {
+                    "MSFT:AllowedValues" => match attribute("ValueType") {
+                        Some(value_type) if msft::VALUE_TYPES.contains(&value_type) => {
+                            frames.last_mut().unwrap().value_type = Some(value_type.into());
+                        }
+                        value_type => {
+                            return Some(
+                                error(&frames, "unknown value type")
+                                    .value(value_type.unwrap_or_default()),
+                            );
+                        }
+                    },
+                    "MSFT:List" => {
+                        let delimiter = attribute("Delimiter").unwrap_or_default();
+                        let list = msft::List {
+                            delimiter: delimiter.into(),
+                        };
+                        if list.delimiter().is_err() {
+                            return Some(error(&frames, "invalid list delimiter").value(delimiter));
+                        }
+                    }
+                    _ => {}
+                }
+
+                if !empty {
+                    continue;
+                }
+                (name, true)
+            }
+            Token::Text { text, .. } => {
+                let value = text.trim();
+                let parent = frames.len().checked_sub(2).map(|i| frames[i].name);
+                let invalid = match frames.last().map(|frame| frame.name) {
+                    Some("NodeName") => {
+                        if let Some(node) = frames.len().checked_sub(2) {
+                            frames[node].label = format!("Node[{value}]");
+                        }
+                        None
+                    }
+                    Some("MSFT:ConflictResolution") => value
+                        .parse::<msft::ConflictResolution>()
+                        .is_err()
+                        .then_some("unknown conflict resolution"),
+                    Some("MSFT:EditionAllowList") => {
+                        if let Err(err) = value.parse::<msft::EditionAllowList>() {
+                            return Some(error(&frames, &err));
+                        }
+                        None
+                    }
+                    Some("ZeroOrN" | "OneOrN") => value
+                        .parse::<i32>()
+                        .is_err()
+                        .then_some("invalid number of occurrences"),
+                    Some("MSFT:Value")
+                        if parent == Some("MSFT:AllowedValues")
+                            && frames[frames.len() - 2].value_type.as_deref() == Some("Range") =>
+                    {
+                        value
+                            .parse::<msft::Range>()
+                            .is_err()
+                            .then_some("invalid range")
+                    }
+                    _ => None,
+                };
+                if let Some(message) = invalid {
+                    return Some(error(&frames, message).value(value));
+                }
+                continue;
+            }
+            Token::End { name, .. } => (name, false),
+        };
+
+        let Some(frame) = frames.pop() else {
+            break;
+        };
+        debug_assert!(frame.name == name || empty);
+        let required: &[&str] = match frame.name {
+            "Node" => &["NodeName", "DFProperties"],
+            "DFProperties" => &["AccessType", "DFFormat", "DFType"],
+            _ => &[],
+        };
+        let path = || {
+            let mut path = path(&frames);
+            path.push('/');
+            path.push_str(&frame.label);
+            path
+        };
+        if let Some(missing) = required.iter().find(|r| !frame.children.contains(r)) {
+            return Some(Error::at(
+                src,
+                frame.offset,
+                path(),
+                format!("missing {missing}"),
+            ));
+        }
+        if matches!(
+            frame.name,
+            "DFFormat" | "Occurrence" | "Scope" | "CaseSense"
+        ) && frame.children.len() != 1
+        {
+            return Some(Error::at(
+                src,
+                frame.offset,
+                path(),
+                format!("expected one value for {}", frame.name),
+            ));
+        }
+        if frame.name == "MSFT:AllowedValues" {
+            if let Some(message) = check_allowed_values(&src[frame.offset..end]) {
+                return Some(Error::at(src, frame.offset, path(), message));
+            }
+        }
+    }
+
+    None
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Start {
+        name: &'a str,
+        attributes: Vec<(&'a str, String)>,
+        offset: usize,
+        end: usize,
+        empty: bool,
+    },
+    End {
+        name: &'a str,
+        offset: usize,
+        end: usize,
+    },
+    Text {
+        text: String,
+        offset: usize,
+    },
+}
+
+impl Token<'_> {
+    fn offset(&self) -> usize {
+        match self {
+            Self::Start { offset, .. } | Self::End { offset, .. } | Self::Text { offset, .. } => {
+                *offset
+            }
+        }
+    }
+}
+
+/// A minimal XML tokenizer which keeps track of where each token is in the source.
+///
+/// Comments, processing instructions and the doctype are skipped.
+struct Scanner<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(src: &'a str, pos: usize) -> Self {
+        Self { src, pos }
+    }
+
+    fn fail(&mut self, offset: usize, message: &str) -> Option<Result<Token<'a>, Error>> {
+        let error = Error::at(self.src, offset, String::new(), message);
+        self.pos = self.src.len();
+        Some(Err(error))
+    }
+
+    /// Skip past `terminator`, returning the text before it.
+    fn skip_past(&mut self, start: usize, terminator: &str) -> Option<&'a str> {
+        let src = self.src;
+        let i = src[start..].find(terminator)?;
+        self.pos = start + i + terminator.len();
+        Some(&src[start..start + i])
+    }
+}
+
+impl<'a> Iterator for Scanner<'a> {
+    type Item = Result<Token<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let offset = self.pos;
+            let rest = &self.src[offset..];
+            if rest.is_empty() {
+                return None;
+            }
+
+            if rest.starts_with("<![CDATA[") {
+                let Some(text) = self.skip_past(offset + 9, "]]>") else {
+                    return self.fail(offset, "unterminated CDATA");
+                };
+                return Some(Ok(Token::Text {
+                    text: text.to_string(),
+                    offset,
+                }));
+            }
+
+            let skipped = if rest.starts_with("<!--") {
+                self.skip_past(offset, "-->")
+            } else if rest.starts_with("<?") {
+                self.skip_past(offset, "?>")
+            } else if rest.starts_with("<!") {
+                // A doctype may have an internal subset which contains `>`
+                match (rest.find('['), rest.find('>')) {
+                    (Some(bracket), Some(end)) if bracket < end => self.skip_past(offset, "]>"),
+                    _ => self.skip_past(offset, ">"),
+                }
+            } else if !rest.starts_with('<') {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.pos += end;
+                return Some(Ok(Token::Text {
+                    text: unescape(&rest[..end]),
+                    offset,
+                }));
+            } else {
+                return self.tag(offset);
+            };
+            if skipped.is_none() {
+                return self.fail(offset, "unterminated markup");
+            }
+        }
+    }
+}
+
+impl<'a> Scanner<'a> {
+    fn tag(&mut self, offset: usize) -> Option<Result<Token<'a>, Error>> {
+        let rest = &self.src[offset..];
+        let mut quote = None;
+        let mut close = None;
+        for (i, c) in rest.char_indices().skip(1) {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '>') => {
+                    close = Some(i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let Some(close) = close else {
+            return self.fail(offset, "unterminated tag");
+        };
+        self.pos = offset + close + 1;
+        let end = self.pos;
+
+        let tag = &rest[1..close];
+        if let Some(name) = tag.strip_prefix('/') {
+            return Some(Ok(Token::End {
+                name: name.trim(),
+                offset,
+                end,
+            }));
+        }
+
+        let (tag, empty) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let Some(attributes) = parse_attributes(&tag[name_end..]) else {
+            return self.fail(offset, "invalid attributes");
+        };
+        Some(Ok(Token::Start {
+            name: &tag[..name_end],
+            attributes,
+            offset,
+            end,
+            empty,
+        }))
+    }
+}
+
+fn parse_attributes(mut s: &str) -> Option<Vec<(&str, String)>> {
+    let mut attributes = Vec::new();
+    loop {
+        s = s.trim_start();
+        if s.is_empty() {
+            return Some(attributes);
+        }
+
+        let (name, rest) = s.split_once('=')?;
+        let rest = rest.trim_start();
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let (value, rest) = rest[1..].split_once(quote)?;
+        attributes.push((name.trim(), unescape(value)));
+        s = rest;
+    }
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        unescaped.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        let entity = rest[1..].split_once(';').map(|(entity, _)| entity);
+        let c = entity.and_then(|entity| match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => {
+                let code = match entity.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity.strip_prefix('#')?.parse().ok(),
+                };
+                code.and_then(char::from_u32)
+            }
+        });
+        match (c, entity) {
+            (Some(c), Some(entity)) => {
+                unescaped.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DDF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE MgmtTree PUBLIC " -//OMA//DTD-DM-DDF 1.2//EN"
+  "http://www.openmobilealliance.org/tech/DTD/DM_DDF-V1_2.dtd"
+  [<?oma-dm-ddf-ver supported-versions="1.2"?>]>
+<MgmtTree xmlns:MSFT="http://schemas.microsoft.com/MobileDevice/DM">
+  <VerDTD>1.2</VerDTD>
+  <Node>
+    <NodeName>Education</NodeName>
+    <Path>./Device/Vendor/MSFT/Policy/Config</Path>
+    <DFProperties>
+      <AccessType><Get /></AccessType>
+      <Description><![CDATA[Uses /<ProviderID>/ & more]]></Description>
+      <DFFormat><node /></DFFormat>
+      <DFType><DDFName /></DFType>
+    </DFProperties>
+    <Node>
+      <NodeName>EnableEduThemes</NodeName>
+      <DFProperties>
+        <AccessType><Get /><Replace /></AccessType>
+        <DFFormat>
+          <integer />
+        </DFFormat>
+        <DFType><MIME>text/plain</MIME></DFType>
+      </DFProperties>
+    </Node>
+    <Node>
+      <NodeName>PrinterNames</NodeName>
+      <DFProperties>
+        <AccessType><Get /></AccessType>
+        <DFFormat><chr /></DFFormat>
+        <DFType><MIME>text/plain</MIME></DFType>
+        <MSFT:AllowedValues ValueType="Range">
+          <MSFT:Value>[10-0]</MSFT:Value>
+        </MSFT:AllowedValues>
+      </DFProperties>
+    </Node>
+    <Node>
+      <NodeName>DefaultPrinterName</NodeName>
+      <DFProperties>
+        <DFFormat><chr /></DFFormat>
+        <DFType><MIME>text/plain</MIME></DFType>
+      </DFProperties>
+    </Node>
+  </Node>
+</MgmtTree>"#;
+
+    #[test]
+    fn test_scan() {
+        let document = Document::scan(DDF).unwrap();
+        assert_eq!(document.ver_dtd.as_deref(), Some("1.2"));
+        assert_eq!(document.nodes.len(), 1);
+
+        let education = &document.nodes[0];
+        assert!(DDF[education.start..education.end].starts_with("<Node>"));
+        assert!(DDF[education.start..education.end].ends_with("</Node>"));
+        assert_eq!(education.parent, "MgmtTree");
+        assert_eq!(education.children.len(), 3);
+        assert_eq!(education.children[0].parent, "MgmtTree/Node[Education]");
+
+        let error = Document::scan("<MgmtTree>\n  <Node>\n  </NodeName>\n</MgmtTree>")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "3:3: expected </Node> \"NodeName\" in MgmtTree"
+        );
+        assert!(Document::scan("<MgmtTree><Node>").is_err());
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let document = Document::scan(DDF).unwrap();
+        let nodes = &document.nodes[0].children;
+
+        let error = diagnose(DDF, &nodes[0]);
+        assert_eq!(
+            error.path,
+            "MgmtTree/Node[Education]/Node[EnableEduThemes]/DFProperties/DFFormat/integer"
+        );
+        assert_eq!((error.line, error.column), (21, 11));
+        assert_eq!(error.value.as_deref(), Some("integer"));
+        assert_eq!(error.message, "unknown format");
+
+        let error = diagnose(DDF, &nodes[1]);
+        assert_eq!(error.message, "invalid range");
+        assert_eq!(error.value.as_deref(), Some("[10-0]"));
+
+        let error = diagnose(DDF, &nodes[2]);
+        assert_eq!(
+            error.to_string(),
+            "39:7: missing AccessType in MgmtTree/Node[Education]/Node[DefaultPrinterName]/DFProperties"
+        );
+
+        // The parent is fine when its children are skipped
+        assert_eq!(diagnose(DDF, &document.nodes[0]).message, "invalid node");
+    }
+
+    #[test]
+    fn test_required_attributes() {
+        let ddf = DDF.replace(
+            "<DFType><DDFName /></DFType>",
+            r#"<DFType><DDFName /></DFType><MSFT:GpMapping GpEnglishName="EduThemes" />"#,
+        );
+        let document = Document::scan(&ddf).unwrap();
+        let error = check(&ddf, &document.nodes[0]).unwrap();
+        assert_eq!(error.message, "missing GpAreaPath attribute");
+        assert_eq!(
+            error.path,
+            "MgmtTree/Node[Education]/DFProperties/MSFT:GpMapping"
+        );
+    }
+
+    #[test]
+    fn test_check_allowed_values() {
+        assert_eq!(
+            check_allowed_values(
+                r#"<MSFT:AllowedValues ValueType="Range"><MSFT:ValueDescription>Any</MSFT:ValueDescription></MSFT:AllowedValues>"#
+            )
+            .as_deref(),
+            Some("missing value for \"Range\"")
+        );
+        assert_eq!(
+            check_allowed_values(r#"<MSFT:AllowedValues ValueType="ADMX" />"#).as_deref(),
+            Some("missing AdmxBacked for \"ADMX\"")
+        );
+        assert_eq!(
+            check_allowed_values(
+                r#"<MSFT:AllowedValues ValueType="Range"><MSFT:Value>[0-10]</MSFT:Value></MSFT:AllowedValues>"#
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(
+            unescape("a &lt;b&gt; &amp;amp; &#65;&#x42;"),
+            "a <b> &amp; AB"
+        );
+        assert_eq!(unescape("AT&T &unknown; &"), "AT&T &unknown; &");
+        assert_eq!(
+            parse_attributes(r#" a="1"  b='x &quot;y&quot;'"#),
+            Some(vec![("a", "1".into()), ("b", "x \"y\"".into())])
+        );
+        assert_eq!(parse_attributes(" a=1"), None);
+    }
+
+    #[test]
+    fn test_lenient() {
+        let (tree, errors) = from_str_lenient(DDF).unwrap();
+        assert_eq!(tree.nodes.len(), 1);
+        // Only the malformed children are skipped
+        assert!(tree.nodes[0].children.is_empty());
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].message, "unknown format");
+
+        assert_eq!(from_str(DDF).unwrap_err(), errors[0]);
+    }
+}
@@ -0,0 +1,421 @@
+//! Writing DDF files.
+//!
+//! This is written by hand instead of with `XmlSerialize` so the `MSFT` namespace is declared on the root
+//! and the elements are written in the order of the DDF DTD.
+
+use std::fmt::Write;
+
+use crate::{
+    AccessType, AdmxBacked, AllowedValues, Applicability, DFProperties, EnumValue, MgmtTree, Node,
+    OccurrenceVariant,
+};
+
+const HEADER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE MgmtTree PUBLIC " -//OMA//DTD-DM-DDF 1.2//EN"
+  "http://www.openmobilealliance.org/tech/DTD/DM_DDF-V1_2.dtd"
+  [<?oma-dm-ddf-ver supported-versions="1.2"?>]>
+"#;
+
+/// Write a DDF file.
+pub fn to_string(tree: &MgmtTree) -> String {
+    let mut writer = Writer {
+        out: HEADER.to_string(),
+        depth: 0,
+    };
+    writer.open(
+        "MgmtTree",
+        &[("xmlns:MSFT", "http://schemas.microsoft.com/MobileDevice/DM")],
+    );
+    writer.text("VerDTD", &[], &tree.ver_dtd);
+    for node in &tree.nodes {
+        write_node(&mut writer, node);
+    }
+    writer.close("MgmtTree");
+    writer.out
+}
+
+struct Writer {
+    out: String,
+    depth: usize,
+}
+
+impl Writer {
+    fn start(&mut self, name: &str, attributes: &[(&str, &str)]) {
+        self.out.push_str(&"  ".repeat(self.depth));
+        self.out.push('<');
+        self.out.push_str(name);
+        for (key, value) in attributes {
+            write!(self.out, " {key}=\"{}\"", escape(value, true)).unwrap();
+        }
+    }
+
+    fn open(&mut self, name: &str, attributes: &[(&str, &str)]) {
+        self.start(name, attributes);
+        self.out.push_str(">\n");
+        self.depth += 1;
+    }
+
+    fn close(&mut self, name: &str) {
+        self.depth -= 1;
+        writeln!(self.out, "{}</{name}>", "  ".repeat(self.depth)).unwrap();
+    }
+
+    fn empty(&mut self, name: &str, attributes: &[(&str, &str)]) {
+        self.start(name, attributes);
+        self.out.push_str(" />\n");
+    }
+
+    fn text(&mut self, name: &str, attributes: &[(&str, &str)], text: &str) {
+        if text.is_empty() {
+            return self.empty(name, attributes);
+        }
+
+        self.start(name, attributes);
+        writeln!(self.out, ">{}</{name}>", escape(text, false)).unwrap();
+    }
+}
+
+fn escape(s: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_node(writer: &mut Writer, node: &Node) {
+    writer.open("Node", &[]);
+    writer.text("NodeName", &[], &node.node_name);
+    if let Some(path) = &node.path {
+        writer.text("Path", &[], path);
+    }
+    write_properties(writer, &node.properties);
+    for child in &node.children {
+        write_node(writer, child);
+    }
+    writer.close("Node");
+}
+
+fn write_properties(writer: &mut Writer, properties: &DFProperties) {
+    writer.open("DFProperties", &[]);
+    write_access_type(writer, &properties.access_type);
+    if let Some(default_value) = &properties.default_value {
+        writer.text("DefaultValue", &[], default_value);
+    }
+    if let Some(description) = &properties.description {
+        writer.text("Description", &[], description);
+    }
+
+    writer.open("DFFormat", &[]);
+    writer.empty(&properties.df_format.to_string(), &[]);
+    writer.close("DFFormat");
+
+    if let Some(occurrence) = &properties.occurrence {
+        writer.open("Occurrence", &[]);
+        match &**occurrence {
+            OccurrenceVariant::ZeroOrN(n) => writer.text("ZeroOrN", &[], &n.to_string()),
+            OccurrenceVariant::OneOrN(n) => writer.text("OneOrN", &[], &n.to_string()),
+            // The other variants are named as in the DDF
+            occurrence => writer.empty(&format!("{occurrence:?}"), &[]),
+        }
+        writer.close("Occurrence");
+    }
+    if let Some(scope) = &properties.scope {
+        writer.open("Scope", &[]);
+        writer.empty(&format!("{:?}", **scope), &[]);
+        writer.close("Scope");
+    }
+    if let Some(title) = &properties.df_title {
+        writer.text("DFTitle", &[], title);
+    }
+
+    writer.open("DFType", &[]);
+    if let Some(mime) = &properties.df_type.mime {
+        writer.text("MIME", &[], mime);
+    }
+    if let Some(ddf_name) = &properties.df_type.ddf_name {
+        writer.text("DDFName", &[], ddf_name);
+    }
+    writer.close("DFType");
+
+    if let Some(case_sense) = &properties.case_sense {
+        writer.open("CaseSense", &[]);
+        writer.empty(&format!("{:?}", **case_sense), &[]);
+        writer.close("CaseSense");
+    }
+    if let Some(applicability) = &properties.applicability {
+        write_applicability(writer, applicability);
+    }
+    if let Some(allowed_values) = &properties.allowed_values {
+        write_allowed_values(writer, allowed_values);
+    }
+    if let Some(deprecated) = &properties.deprecated {
+        let attributes = deprecated
+            .os_build_deprecated
+            .as_deref()
+            .map(|build| ("OsBuildDeprecated", build));
+        writer.empty("MSFT:Deprecated", attributes.as_slice());
+    }
+    if let Some(gp_mapping) = &properties.gp_mapping {
+        let mut attributes = vec![
+            ("GpEnglishName", gp_mapping.gp_english_name.as_str()),
+            ("GpAreaPath", gp_mapping.gp_area_path.as_str()),
+        ];
+        if let Some(gp_element) = &gp_mapping.gp_element {
+            attributes.push(("GpElement", gp_element));
+        }
+        writer.empty("MSFT:GpMapping", &attributes);
+    }
+    if let Some(conflict_resolution) = &properties.conflict_resolution {
+        // The variants are named as in the DDF
+        writer.text(
+            "MSFT:ConflictResolution",
+            &[],
+            &format!("{conflict_resolution:?}"),
+        );
+    }
+    writer.close("DFProperties");
+}
+
+fn write_access_type(writer: &mut Writer, access_type: &AccessType) {
+    writer.open("AccessType", &[]);
+    for (name, permitted) in [
+        ("Add", &access_type.add),
+        ("Copy", &access_type.copy),
+        ("Delete", &access_type.delete),
+        ("Exec", &access_type.exec),
+        ("Get", &access_type.get),
+        ("Replace", &access_type.replace),
+    ] {
+        if permitted.is_some() {
+            writer.empty(name, &[]);
+        }
+    }
+    writer.close("AccessType");
+}
+
+fn write_applicability(writer: &mut Writer, applicability: &Applicability) {
+    writer.open("MSFT:Applicability", &[]);
+    if let Some(version) = &applicability.os_build_version {
+        writer.text("MSFT:OsBuildVersion", &[], version);
+    }
+    if let Some(version) = &applicability.csp_version {
+        writer.text("MSFT:CspVersion", &[], version);
+    }
+    if let Some(list) = &applicability.edition_allow_list {
+        let ids = list
+            .ids
+            .iter()
+            .map(|id| format!("{id};"))
+            .collect::<String>();
+        writer.text("MSFT:EditionAllowList", &[], &ids);
+    }
+    if applicability.requires_azure_ad.is_some() {
+        writer.empty("MSFT:RequiresAzureAd", &[]);
+    }
+    writer.close("MSFT:Applicability");
+}
+
+fn write_allowed_values(writer: &mut Writer, allowed_values: &AllowedValues) {
+    let (values, list) = match allowed_values {
+        AllowedValues::List { delimiter, item } => (&**item, Some(*delimiter)),
+        values => (values, None),
+    };
+    let value_type = match values {
+        AllowedValues::Enum(_) => "ENUM",
+        AllowedValues::Flag(_) => "Flag",
+        AllowedValues::Range(_) => "Range",
+        AllowedValues::Admx(_) => "ADMX",
+        AllowedValues::Regex(_) => "RegEx",
+        AllowedValues::Xsd(_) => "XSD",
+        AllowedValues::Sddl(_) => "SDDL",
+        AllowedValues::Json(_) => "JSON",
+        AllowedValues::None | AllowedValues::List { .. } => "None",
+    };
+
+    writer.open("MSFT:AllowedValues", &[("ValueType", value_type)]);
+    if let Some(delimiter) = list {
+        let delimiter = match delimiter {
+            '\u{F000}' => "0xF000".to_string(),
+            delimiter => delimiter.to_string(),
+        };
+        writer.empty("MSFT:List", &[("Delimiter", &delimiter)]);
+    }
+    match values {
+        AllowedValues::Enum(values) | AllowedValues::Flag(values) => {
+            for value in values {
+                write_enum_value(writer, value);
+            }
+        }
+        AllowedValues::Range(range) => {
+            writer.text("MSFT:Value", &[], &range.to_string());
+            if let Some(description) = &range.description {
+                writer.text("MSFT:ValueDescription", &[], description);
+            }
+        }
+        AllowedValues::Admx(backed) => write_admx_backed(writer, backed),
+        AllowedValues::Regex(value) | AllowedValues::Xsd(value) | AllowedValues::Json(value) => {
+            writer.text("MSFT:Value", &[], value);
+        }
+        AllowedValues::Sddl(Some(value)) => writer.text("MSFT:Value", &[], value),
+        AllowedValues::Sddl(None) | AllowedValues::None | AllowedValues::List { .. } => {}
+    }
+    writer.close("MSFT:AllowedValues");
+}
+
+fn write_enum_value(writer: &mut Writer, value: &EnumValue) {
+    writer.open("MSFT:Enum", &[]);
+    writer.text("MSFT:Value", &[], &value.value);
+    if let Some(description) = &value.description {
+        writer.text("MSFT:ValueDescription", &[], description);
+    }
+    writer.close("MSFT:Enum");
+}
+
+fn write_admx_backed(writer: &mut Writer, backed: &AdmxBacked) {
+    writer.empty(
+        "MSFT:AdmxBacked",
+        &[
+            ("Area", &backed.area),
+            ("Name", &backed.name),
+            ("File", &backed.file),
+        ],
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use super::*;
+    use crate::{Command, DFFormatVariant, ScopeVariant};
+
+    #[test]
+    fn test_builder() {
+        let tree = MgmtTree::new().node(
+            Node::new("Mattrax", DFFormatVariant::Node)
+                .path("./Device/Vendor")
+                .child(
+                    Node::dynamic("ProfileName").child(
+                        Node::new("SyncInterval", DFFormatVariant::Int)
+                            .access([Command::Get, Command::Replace])
+                            .default_value("60")
+                            .description("Minutes between syncs & retries")
+                            .scope(ScopeVariant::Dynamic)
+                            .allowed_values(AllowedValues::Range("[5-1440]".parse().unwrap())),
+                    ),
+                ),
+        );
+
+        let xml = to_string(&tree);
+        assert!(xml.starts_with(HEADER));
+        let expected = r#"<MgmtTree xmlns:MSFT="http://schemas.microsoft.com/MobileDevice/DM">
+  <VerDTD>1.2</VerDTD>
+  <Node>
+    <NodeName>Mattrax</NodeName>
+    <Path>./Device/Vendor</Path>
+    <DFProperties>
+      <AccessType>
+        <Get />
+      </AccessType>
+      <DFFormat>
+        <node />
+      </DFFormat>
+      <Occurrence>
+        <One />
+      </Occurrence>
+      <Scope>
+        <Permanent />
+      </Scope>
+      <DFType>
+        <DDFName />
+      </DFType>
+    </DFProperties>
+    <Node>
+      <NodeName />
+      <DFProperties>
+        <AccessType>
+          <Add />
+          <Delete />
+          <Get />
+        </AccessType>
+        <DFFormat>
+          <node />
+        </DFFormat>
+        <Occurrence>
+          <ZeroOrMore />
+        </Occurrence>
+        <Scope>
+          <Dynamic />
+        </Scope>
+        <DFTitle>ProfileName</DFTitle>
+        <DFType>
+          <DDFName />
+        </DFType>
+      </DFProperties>
+      <Node>
+        <NodeName>SyncInterval</NodeName>
+        <DFProperties>
+          <AccessType>
+            <Get />
+            <Replace />
+          </AccessType>
+          <DefaultValue>60</DefaultValue>
+          <Description>Minutes between syncs &amp; retries</Description>
+          <DFFormat>
+            <int />
+          </DFFormat>
+          <Occurrence>
+            <ZeroOrOne />
+          </Occurrence>
+          <Scope>
+            <Dynamic />
+          </Scope>
+          <DFType>
+            <MIME>text/plain</MIME>
+          </DFType>
+          <MSFT:AllowedValues ValueType="Range">
+            <MSFT:Value>[5-1440]</MSFT:Value>
+          </MSFT:AllowedValues>
+        </DFProperties>
+      </Node>
+    </Node>
+  </Node>
+</MgmtTree>
+"#;
+        assert_eq!(&xml[HEADER.len()..], expected);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape(r#"<a href="x">&</a>"#, false),
+            r#"&lt;a href="x"&gt;&amp;&lt;/a&gt;"#
+        );
+        assert_eq!(escape(r#""quoted""#, true), "&quot;quoted&quot;");
+    }
+
+    /// Writing a parsed file and parsing it again results in the same tree.
+    #[test]
+    fn test_round_trip() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../apps/ingest/ddf");
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "xml") {
+                continue;
+            }
+
+            let tree = crate::from_bytes(&fs::read(&path).unwrap()).unwrap();
+            let xml = to_string(&tree);
+            let written = crate::from_str(&xml)
+                .unwrap_or_else(|_| panic!("failed to parse {path:?} after writing it"));
+            assert!(written == tree, "{path:?} changed after writing it");
+        }
+    }
+}
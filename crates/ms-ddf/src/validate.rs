@@ -0,0 +1,451 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{LazyLock, Mutex},
+};
+
+use regex::Regex;
+
+use crate::{AllowedValues, DFFormatVariant, DdfIndex, OccurrenceVariant, Range, ResolvedNode};
+
+/// A SyncML command which operates on a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    Add,
+    Replace,
+    Delete,
+    Get,
+    Exec,
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Why a command can't be applied to a node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    UnknownNode(String),
+    NotPermitted {
+        command: Command,
+        allowed: Vec<Command>,
+    },
+    /// The node has an occurrence of `One`, so it can't be deleted.
+    Required,
+    MissingValue,
+    /// Interior nodes and `null` nodes don't have a value.
+    UnexpectedValue,
+    FormatMismatch {
+        expected: String,
+        got: String,
+    },
+    InvalidValue {
+        format: String,
+        value: String,
+    },
+    OutOfRange {
+        value: i64,
+        range: Range,
+    },
+    NotAllowed {
+        value: String,
+        allowed: Vec<String>,
+    },
+    NoMatch {
+        value: String,
+        pattern: String,
+    },
+    /// The node's pattern can't be compiled, such as one using .NET only syntax, so the value can't be checked.
+    InvalidPattern {
+        pattern: String,
+        error: String,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownNode(uri) => write!(f, "unknown node {uri:?}"),
+            Self::NotPermitted { command, allowed } => {
+                write!(f, "{command} not permitted")?;
+                match allowed.as_slice() {
+                    [] => Ok(()),
+                    allowed => {
+                        let allowed = allowed.iter().map(Command::to_string).collect::<Vec<_>>();
+                        write!(f, ", expected one of {}", allowed.join(", "))
+                    }
+                }
+            }
+            Self::Required => write!(f, "Delete not permitted as the node must always exist"),
+            Self::MissingValue => write!(f, "missing value"),
+            Self::UnexpectedValue => write!(f, "the node doesn't have a value"),
+            Self::FormatMismatch { expected, got } => write!(f, "expected {expected}, got {got}"),
+            Self::InvalidValue { format, value } => write!(f, "{value:?} is not a valid {format}"),
+            Self::OutOfRange { value, range } => write!(f, "value {value} outside {range}"),
+            Self::NotAllowed { value, allowed } => {
+                write!(f, "value {value:?} not one of {}", allowed.join(", "))
+            }
+            Self::NoMatch { value, pattern } => {
+                write!(f, "value {value:?} doesn't match {pattern:?}")
+            }
+            Self::InvalidPattern { pattern, error } => {
+                write!(f, "can't check against pattern {pattern:?}: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl<'a> DdfIndex<'a> {
+    /// Check a command against the definition of the node at `uri`, returning the node if it's valid.
+    ///
+    /// `format` is the format from the command's `Meta`. It's only checked when it's set, as the default of `chr`
+    /// is often left out.
+    pub fn validate(
+        &self,
+        uri: &str,
+        command: Command,
+        format: Option<&str>,
+        value: Option<&str>,
+    ) -> Result<ResolvedNode<'a>, ValidationError> {
+        let node = self
+            .lookup(uri)
+            .ok_or_else(|| ValidationError::UnknownNode(uri.to_string()))?;
+        node.validate(command, format, value)?;
+        Ok(node)
+    }
+}
+
+impl ResolvedNode<'_> {
+    /// Check a command against the definition of the node. See [`DdfIndex::validate`].
+    pub fn validate(
+        &self,
+        command: Command,
+        format: Option<&str>,
+        value: Option<&str>,
+    ) -> Result<(), ValidationError> {
+        let access_type = self.access_type();
+        let allowed = [
+            (Command::Add, access_type.add.is_some()),
+            (Command::Replace, access_type.replace.is_some()),
+            (Command::Delete, access_type.delete.is_some()),
+            (Command::Get, access_type.get.is_some()),
+            (Command::Exec, access_type.exec.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(command, allowed)| allowed.then_some(command))
+        .collect::<Vec<_>>();
+        if !allowed.contains(&command) {
+            return Err(ValidationError::NotPermitted { command, allowed });
+        }
+
+        let occurrence = self.node.properties.occurrence.as_deref();
+        if command == Command::Delete && matches!(occurrence, Some(OccurrenceVariant::One)) {
+            return Err(ValidationError::Required);
+        }
+
+        // Only commands which set the node have a value to check
+        if !matches!(command, Command::Add | Command::Replace) {
+            return Ok(());
+        }
+
+        let expected = self.format().to_string();
+        if let Some(got) = format.map(str::trim).filter(|got| *got != expected) {
+            return Err(ValidationError::FormatMismatch {
+                expected,
+                got: got.to_string(),
+            });
+        }
+
+        let value = match (self.format(), value) {
+            (DFFormatVariant::Node | DFFormatVariant::Null, None) => return Ok(()),
+            (DFFormatVariant::Node | DFFormatVariant::Null, Some("")) => return Ok(()),
+            (DFFormatVariant::Node | DFFormatVariant::Null, Some(_)) => {
+                return Err(ValidationError::UnexpectedValue)
+            }
+            (_, None) => return Err(ValidationError::MissingValue),
+            (_, Some(value)) => value,
+        };
+
+        let invalid = || ValidationError::InvalidValue {
+            format: expected.clone(),
+            value: value.to_string(),
+        };
+        match self.format() {
+            DFFormatVariant::Int => {
+                parse_int(value).ok_or_else(invalid)?;
+            }
+            DFFormatVariant::Bool
+                if !value.eq_ignore_ascii_case("true") && !value.eq_ignore_ascii_case("false") =>
+            {
+                return Err(invalid());
+            }
+            DFFormatVariant::Float => {
+                value.trim().parse::<f64>().map_err(|_| invalid())?;
+            }
+            DFFormatVariant::Base64 if !is_base64(value) => return Err(invalid()),
+            _ => {}
+        }
+
+        match self.allowed_values() {
+            Some(allowed_values) => check_allowed(allowed_values, value),
+            None => Ok(()),
+        }
+    }
+}
+
+fn check_allowed(allowed_values: &AllowedValues, value: &str) -> Result<(), ValidationError> {
+    let not_allowed = |values: &[crate::EnumValue]| ValidationError::NotAllowed {
+        value: value.to_string(),
+        allowed: values.iter().map(|v| v.value.clone()).collect(),
+    };
+    let invalid_int = || ValidationError::InvalidValue {
+        format: "int".into(),
+        value: value.to_string(),
+    };
+
+    match allowed_values {
+        AllowedValues::Enum(values) => {
+            if !values.iter().any(|v| v.value == value) {
+                return Err(not_allowed(values));
+            }
+        }
+        AllowedValues::Flag(values) => {
+            let mask = values
+                .iter()
+                .filter_map(|v| parse_int(&v.value))
+                .fold(0, |mask, flag| mask | flag);
+            let flags = parse_int(value).ok_or_else(invalid_int)?;
+            if flags & !mask != 0 {
+                return Err(not_allowed(values));
+            }
+        }
+        AllowedValues::Range(range) => {
+            let value = parse_int(value).ok_or_else(invalid_int)?;
+            if !range.contains(value) {
+                return Err(ValidationError::OutOfRange {
+                    value,
+                    range: range.clone(),
+                });
+            }
+        }
+        AllowedValues::List { delimiter, item } => {
+            for value in value.split(*delimiter).filter(|value| !value.is_empty()) {
+                check_allowed(item, value)?;
+            }
+        }
+        AllowedValues::Admx(_) => {
+            let value = value.trim_start();
+            if !value.starts_with("<enabled") && !value.starts_with("<disabled") {
+                return Err(ValidationError::InvalidValue {
+                    format: "ADMX policy".into(),
+                    value: value.to_string(),
+                });
+            }
+        }
+        AllowedValues::Regex(pattern) => {
+            // Some patterns are written like JavaScript literals as `/.../`
+            let source = pattern
+                .strip_prefix('/')
+                .and_then(|p| p.strip_suffix('/'))
+                .unwrap_or(pattern);
+            let regex = compile(source).map_err(|error| ValidationError::InvalidPattern {
+                pattern: pattern.clone(),
+                error,
+            })?;
+            if !regex.is_match(value) {
+                return Err(ValidationError::NoMatch {
+                    value: value.to_string(),
+                    pattern: pattern.clone(),
+                });
+            }
+        }
+        AllowedValues::Xsd(_)
+        | AllowedValues::Sddl(_)
+        | AllowedValues::Json(_)
+        | AllowedValues::None => {}
+    }
+    Ok(())
+}
+
+/// Compile a pattern, reusing it if it has been compiled before. A DDF only has a few hundred patterns so they are kept forever.
+fn compile(pattern: &str) -> Result<Regex, String> {
+    static CACHE: LazyLock<Mutex<HashMap<String, Result<Regex, String>>>> =
+        LazyLock::new(Default::default);
+
+    CACHE
+        .lock()
+        .unwrap()
+        .entry(pattern.to_string())
+        .or_insert_with(|| Regex::new(pattern).map_err(|err| err.to_string()))
+        .clone()
+}
+
+/// Ints are 32 bits, although some nodes treat them as unsigned.
+fn parse_int(value: &str) -> Option<i64> {
+    value
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|value| (i32::MIN as i64..=u32::MAX as i64).contains(value))
+}
+
+fn is_base64(value: &str) -> bool {
+    let value = value.trim();
+    value.len().is_multiple_of(4)
+        && value.trim_end_matches('=').len() + 2 >= value.len()
+        && value
+            .trim_end_matches('=')
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'+' || b == b'/')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::EnumValue;
+
+    #[test]
+    fn test_allowed_values() {
+        let range = AllowedValues::Range("[0-2]".parse().unwrap());
+        assert_eq!(check_allowed(&range, "2"), Ok(()));
+        assert_eq!(
+            check_allowed(&range, "5").unwrap_err().to_string(),
+            "value 5 outside [0-2]"
+        );
+        assert!(matches!(
+            check_allowed(&range, "abc"),
+            Err(ValidationError::InvalidValue { .. })
+        ));
+
+        let values = vec![
+            EnumValue {
+                value: "1".into(),
+                description: None,
+            },
+            EnumValue {
+                value: "4".into(),
+                description: None,
+            },
+        ];
+        let list = AllowedValues::List {
+            delimiter: ';',
+            item: Box::new(AllowedValues::Enum(values.clone())),
+        };
+        assert_eq!(check_allowed(&list, "1;4;"), Ok(()));
+        assert_eq!(
+            check_allowed(&list, "1;2").unwrap_err().to_string(),
+            "value \"2\" not one of 1, 4"
+        );
+
+        let flags = AllowedValues::Flag(values);
+        assert_eq!(check_allowed(&flags, "5"), Ok(()));
+        assert!(check_allowed(&flags, "2").is_err());
+
+        let regex = AllowedValues::Regex("^[\\d]*$".into());
+        assert_eq!(check_allowed(&regex, "123"), Ok(()));
+        assert!(check_allowed(&regex, "12a").is_err());
+        // .NET patterns with syntax Rust doesn't support can't be checked
+        let regex = AllowedValues::Regex("(?<=a)b".into());
+        assert!(matches!(
+            check_allowed(&regex, "anything"),
+            Err(ValidationError::InvalidPattern { .. })
+        ));
+        assert!(compile("^[\\d]*$").unwrap().is_match("123"));
+
+        let admx = AllowedValues::Admx(crate::AdmxBacked {
+            area: "ActiveXInstallService~AT~WindowsComponents~AxInstSv".into(),
+            name: "AxISURLZonePolicies".into(),
+            file: "ActiveXInstallService.admx".into(),
+        });
+        assert_eq!(check_allowed(&admx, "<enabled/>"), Ok(()));
+        assert!(check_allowed(&admx, "1").is_err());
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(parse_int("-1"), Some(-1));
+        assert_eq!(parse_int("4294967295"), Some(4294967295));
+        assert_eq!(parse_int("4294967296"), None);
+        assert!(is_base64("AQIDBA=="));
+        assert!(!is_base64("AQIDBA="));
+        assert!(!is_base64("AQ!DBA=="));
+    }
+
+    const DDF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<MgmtTree xmlns:MSFT="http://schemas.microsoft.com/MobileDevice/DM">
+  <VerDTD>1.2</VerDTD>
+  <Node>
+    <NodeName>Education</NodeName>
+    <Path>./Device/Vendor/MSFT/Policy/Config</Path>
+    <DFProperties>
+      <AccessType><Get /></AccessType>
+      <DFFormat><node /></DFFormat>
+      <Occurrence><One /></Occurrence>
+      <Scope><Permanent /></Scope>
+      <DFType><DDFName /></DFType>
+    </DFProperties>
+    <Node>
+      <NodeName>EnableEduThemes</NodeName>
+      <DFProperties>
+        <AccessType><Add /><Delete /><Get /><Replace /></AccessType>
+        <DefaultValue>0</DefaultValue>
+        <DFFormat><int /></DFFormat>
+        <Occurrence><ZeroOrOne /></Occurrence>
+        <Scope><Dynamic /></Scope>
+        <DFType><MIME>text/plain</MIME></DFType>
+        <MSFT:AllowedValues ValueType="ENUM">
+          <MSFT:Enum><MSFT:Value>0</MSFT:Value></MSFT:Enum>
+          <MSFT:Enum><MSFT:Value>1</MSFT:Value></MSFT:Enum>
+        </MSFT:AllowedValues>
+      </DFProperties>
+    </Node>
+  </Node>
+</MgmtTree>"#;
+
+    #[test]
+    fn test_validate() {
+        let tree = crate::from_str(DDF).unwrap();
+        let index = DdfIndex::new([&tree]);
+        let uri = "./Device/Vendor/MSFT/Policy/Config/Education/EnableEduThemes";
+
+        assert!(index
+            .validate(uri, Command::Replace, Some("int"), Some("1"))
+            .is_ok());
+        let error = |uri, command, format, value| {
+            index
+                .validate(uri, command, format, value)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error(uri, Command::Replace, Some("chr"), Some("1")),
+            "expected int, got chr"
+        );
+        assert_eq!(
+            error(uri, Command::Exec, None, None),
+            "Exec not permitted, expected one of Add, Replace, Delete, Get"
+        );
+        assert_eq!(
+            error(uri, Command::Add, None, Some("2")),
+            "value \"2\" not one of 0, 1"
+        );
+        assert_eq!(error(uri, Command::Add, None, None), "missing value");
+        assert_eq!(
+            error(
+                "./Device/Vendor/MSFT/Policy/Config/Education",
+                Command::Replace,
+                None,
+                None
+            ),
+            "Replace not permitted, expected one of Get"
+        );
+        assert!(matches!(
+            index.validate("./Device/Vendor/MSFT/Missing", Command::Get, None, None),
+            Err(ValidationError::UnknownNode(_))
+        ));
+    }
+}
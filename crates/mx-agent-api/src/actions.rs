@@ -0,0 +1,60 @@
+use mx_policy::Script;
+use serde::{Deserialize, Serialize};
+
+use crate::ScriptResult;
+
+/// The kinds of `device_actions`. The names match the enum in the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    /// Check in with Mattrax now.
+    Sync,
+    /// Run the script in [`Action::script`] once.
+    Script,
+    /// Collect a diagnostics bundle and upload it.
+    Logs,
+    /// Reboot the device.
+    Restart,
+    Shutdown,
+    Lost,
+    Wipe,
+    Retire,
+}
+
+/// An action requested by an administrator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Action {
+    pub id: String,
+    pub kind: ActionKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<Script>,
+}
+
+/// A message sent by Mattrax over the actions WebSocket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ServerMessage {
+    Action(Action),
+}
+
+/// A message sent by the agent over the actions WebSocket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AgentMessage {
+    /// The action was received and won't be redelivered over the WebSocket.
+    Ack { id: String },
+}
+
+/// The outcome of an [`Action`], sent to `POST /agent/actions/result`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionResult {
+    pub id: String,
+    pub success: bool,
+    /// A human readable message, or the id of the diagnostics bundle for [`ActionKind::Logs`].
+    #[serde(default)]
+    pub output: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<ScriptResult>,
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// The size of each chunk of a diagnostics bundle. The last chunk may be smaller.
+pub const DIAGNOSTICS_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The largest diagnostics bundle Mattrax will accept.
+pub const MAX_DIAGNOSTICS_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Start uploading a diagnostics bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsUpload {
+    /// The size of the bundle in bytes.
+    pub size: u64,
+    /// Hex encoded SHA-256 of the bundle, checked once every chunk is received.
+    pub sha256: String,
+    /// The action which requested the bundle, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsStatus {
+    pub id: String,
+    /// The number of bytes received, which is the offset of the next chunk.
+    pub received: u64,
+    pub complete: bool,
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+/// The version of the [`Inventory`] document. This must be bumped when a breaking change is made to it's format.
+pub const INVENTORY_VERSION: u32 = 1;
+
+/// Hardware and software facts collected from the device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Inventory {
+    pub version: u32,
+    /// Unix timestamp in seconds
+    pub collected_at: u64,
+    pub hostname: String,
+    pub os: OperatingSystem,
+    pub hardware: Hardware,
+    pub cpu: Cpu,
+    pub memory: Memory,
+    pub disks: Vec<Disk>,
+    pub network_interfaces: Vec<NetworkInterface>,
+    pub packages: Vec<Package>,
+}
+
+impl Inventory {
+    /// The disk mounted at `/`.
+    pub fn root_disk(&self) -> Option<&Disk> {
+        self.disks.iter().find(|disk| disk.mount_point == "/")
+    }
+}
+
+/// From `/etc/os-release` and the running kernel.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatingSystem {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub version_id: Option<String>,
+    pub pretty_name: Option<String>,
+    pub kernel: Option<String>,
+    pub arch: String,
+}
+
+/// From the DMI tables.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hardware {
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub serial_number: Option<String>,
+    pub uuid: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cpu {
+    pub model: Option<String>,
+    pub logical_cores: u32,
+    pub physical_cores: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Memory {
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+    pub swap_total_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Disk {
+    pub device: String,
+    pub mount_point: String,
+    pub filesystem: String,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkInterface {
+    pub name: String,
+    pub mac_address: Option<String>,
+    /// The operational state. Eg. `up`, `down`
+    pub state: Option<String>,
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    pub arch: Option<String>,
+    pub manager: PackageManager,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PackageManager {
+    Dpkg,
+    Rpm,
+}
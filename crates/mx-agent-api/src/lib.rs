@@ -0,0 +1,72 @@
+//! Types for the API between the Mattrax agent (`mattraxd`) and Mattrax Cloud.
+//!
+//! All routes are mounted under `/agent`. Everything except [`EnrollRequest`] requires the device's client certificate.
+//!
+//! - `POST /agent/enroll` - [`EnrollRequest`] -> [`EnrollResponse`]
+//! - `POST /agent/checkin` - [`CheckinResponse`]
+//! - `POST /agent/inventory` - [`Inventory`]
+//! - `POST /agent/policies/status` - [`PolicyStatusReport`]s
+//! - `POST /agent/update` - [`UpdateRequest`] -> [`SignedManifest`] (or `null` if the channel has no release)
+//! - `GET /agent/ws` - WebSocket carrying [`ServerMessage`]s and [`AgentMessage`]s
+//! - `POST /agent/actions/result` - [`ActionResult`]
+//! - `POST /agent/diagnostics` - [`DiagnosticsUpload`] -> [`DiagnosticsStatus`]
+//! - `GET /agent/diagnostics/:id` - [`DiagnosticsStatus`]
+//! - `PUT /agent/diagnostics/:id/:offset` - a chunk of the bundle -> [`DiagnosticsStatus`]
+
+use serde::{Deserialize, Serialize};
+
+mod actions;
+mod diagnostics;
+mod inventory;
+mod policy;
+mod update;
+
+pub use actions::*;
+pub use diagnostics::*;
+pub use inventory::*;
+pub use policy::*;
+pub use update::*;
+
+/// The header the mTLS proxy uses to forward the client certificate (base64 encoded DER).
+pub const CLIENT_CERT_HEADER: &str = "x-client-cert";
+
+/// The header the mTLS proxy uses to prove to Mattrax Cloud that [`CLIENT_CERT_HEADER`] was set by it.
+pub const PROXY_SECRET_HEADER: &str = "x-mtls-proxy-secret";
+
+/// The header the agent uses to identify a request from its outbox, so a retried request isn't applied twice.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrollRequest {
+    /// The enrollment token generated by an administrator.
+    pub token: String,
+    /// PEM encoded certificate signing request for the device's identity keypair.
+    pub csr: String,
+    /// The hostname of the device.
+    pub name: String,
+    /// A unique hardware identifier for the device.
+    pub serial_number: String,
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub os_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrollResponse {
+    pub device_id: String,
+    /// PEM encoded client certificate issued by the device authority.
+    pub certificate: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckinResponse {
+    pub device_id: String,
+    #[serde(default)]
+    pub policies: Vec<DeployedPolicy>,
+    /// Actions which haven't been delivered over the WebSocket, so the agent still receives them if it isn't connected.
+    #[serde(default)]
+    pub actions: Vec<Action>,
+}
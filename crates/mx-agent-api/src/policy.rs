@@ -0,0 +1,102 @@
+use mx_policy::{LinuxConfig, Script};
+use serde::{Deserialize, Serialize};
+
+/// The latest deploy of a policy which is assigned to the device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeployedPolicy {
+    pub deploy_id: String,
+    pub policy_id: String,
+    pub name: String,
+    pub scripts: Vec<Script>,
+    #[serde(default)]
+    pub linux: Option<LinuxConfig>,
+}
+
+/// The outcome of applying a policy deploy on the device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyStatusReport {
+    pub deploy_id: String,
+    /// The scripts which were run. Scripts which weren't triggered are omitted.
+    pub scripts: Vec<ScriptResult>,
+    /// The compliance of every item in the policy's Linux configuration.
+    #[serde(default)]
+    pub linux: Vec<ItemCompliance>,
+}
+
+impl PolicyStatusReport {
+    pub fn is_success(&self) -> bool {
+        self.scripts.iter().all(ScriptResult::is_success)
+            && self.linux.iter().all(ItemCompliance::is_compliant)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptResult {
+    /// SHA-256 of the script's shell and contents.
+    pub hash: String,
+    /// `None` if the script was terminated by a signal or never started.
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Whether the output exceeded the capture limit and was cut off.
+    pub truncated: bool,
+    pub timed_out: bool,
+    /// Set if the agent was unable to run the script.
+    pub error: Option<String>,
+    /// Unix timestamp (in seconds) of when the script started.
+    pub started_at: u64,
+    pub duration_ms: u64,
+}
+
+impl ScriptResult {
+    pub fn is_success(&self) -> bool {
+        self.error.is_none() && !self.timed_out && self.exit_code == Some(0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ItemKind {
+    File,
+    Service,
+    Sysctl,
+    Package,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ComplianceStatus {
+    /// The item already matched the configuration.
+    Compliant,
+    /// The item had drifted and was corrected.
+    Remediated,
+    /// The item has drifted but wasn't corrected because it was a dry run.
+    Drifted,
+    /// The item has drifted and correcting it failed.
+    Failed,
+}
+
+/// The state of a single item from a [`LinuxConfig`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemCompliance {
+    pub kind: ItemKind,
+    /// The file path, unit, sysctl key or package name.
+    pub id: String,
+    pub status: ComplianceStatus,
+    /// Human readable description of each difference that was found.
+    pub drift: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl ItemCompliance {
+    pub fn is_compliant(&self) -> bool {
+        matches!(
+            self.status,
+            ComplianceStatus::Compliant | ComplianceStatus::Remediated
+        )
+    }
+}
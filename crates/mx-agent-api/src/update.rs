@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateRequest {
+    /// The release channel the device is subscribed to. Eg. `stable`
+    pub channel: String,
+}
+
+/// A [`ReleaseManifest`] signed by the Mattrax release key.
+///
+/// The manifest is kept as a string as the signature is over its exact bytes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedManifest {
+    pub manifest: String,
+    /// Base64 encoded Ed25519 signature.
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseManifest {
+    /// Semver version of the release.
+    pub version: String,
+    pub channel: String,
+    /// Percentage of devices on the channel which should install the release, for staged rollouts.
+    pub rollout: u8,
+    pub artifacts: Vec<Artifact>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Artifact {
+    /// `<arch>-<os>`. Eg. `x86_64-linux`
+    pub target: String,
+    pub url: String,
+    pub size: u64,
+    /// Hex encoded SHA-256 of the binary.
+    pub sha256: String,
+}
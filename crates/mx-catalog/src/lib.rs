@@ -0,0 +1,336 @@
+//! Full-text search over the settings of every supported platform.
+//!
+//! The index is built by `ingest` and shipped as JSON, so the web UI can search it without a server and the server
+//! can load the same file. Terms are stemmed with the English Snowball stemmer, which
+//! `packages/configuration-schemas/src/stem.ts` ports so the web UI stems queries the same way.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
+
+/// How much more a match in each field counts than a match in the description.
+const TITLE_WEIGHT: u32 = 3;
+const PATH_WEIGHT: u32 = 2;
+const KEYWORD_WEIGHT: u32 = 2;
+const DESCRIPTION_WEIGHT: u32 = 1;
+
+// BM25 parameters
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+/// Words which are too common to be worth indexing.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "be", "by", "for", "if", "in", "is", "it", "of", "on", "or",
+    "the", "this", "to", "when", "which", "will", "with",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Platform {
+    Windows,
+    Apple,
+}
+
+/// A setting which can be searched for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Document {
+    pub platform: Platform,
+    /// The OMA-URI of a CSP node, or the payload domain and key of an Apple setting such as `com.apple.screensaver/idleTime`.
+    pub path: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Other text to match, such as the English name of the Group Policy or the descriptions of the allowed values.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+}
+
+/// The part of a [`Document`] the index keeps to show it in the results.
+///
+/// The description and keywords are only indexed, as they'd make up over a third of `search.json`.
+/// They can be looked up by `path` in the platform's schema.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    pub platform: Platform,
+    pub path: String,
+    pub title: String,
+}
+
+/// An inverted index of [`Document`]s, ranked with BM25.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    documents: Vec<Summary>,
+    /// The documents each stemmed term appears in, with its weighted frequency in each.
+    terms: BTreeMap<String, Vec<(u32, u32)>>,
+    /// The weighted number of terms in each document.
+    lengths: Vec<u32>,
+}
+
+/// A document which matched a search.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SearchResult<'a> {
+    pub document: &'a Summary,
+    pub score: f32,
+}
+
+impl SearchIndex {
+    pub fn new(documents: Vec<Document>) -> Self {
+        let mut index = Self::default();
+        for document in documents {
+            index.insert(document);
+        }
+        index
+    }
+
+    pub fn insert(&mut self, document: Document) {
+        let id = self.documents.len() as u32;
+
+        let mut frequencies = HashMap::<String, u32>::new();
+        let fields = [
+            (Some(document.title.as_str()), TITLE_WEIGHT),
+            (Some(document.path.as_str()), PATH_WEIGHT),
+            (document.description.as_deref(), DESCRIPTION_WEIGHT),
+        ];
+        let keywords = document
+            .keywords
+            .iter()
+            .map(|k| (Some(k.as_str()), KEYWORD_WEIGHT));
+        for (text, weight) in fields.into_iter().chain(keywords) {
+            for term in tokenize(text.unwrap_or_default()) {
+                *frequencies.entry(term).or_default() += weight;
+            }
+        }
+
+        self.lengths.push(frequencies.values().sum());
+        for (term, frequency) in frequencies {
+            self.terms.entry(term).or_default().push((id, frequency));
+        }
+        self.documents.push(Summary {
+            platform: document.platform,
+            path: document.path,
+            title: document.title,
+        });
+    }
+
+    pub fn documents(&self) -> &[Summary] {
+        &self.documents
+    }
+
+    /// Find the documents which best match a query, with the best match first.
+    ///
+    /// Documents only need to match one word of the query. The last word also matches any term it's a prefix of,
+    /// so results can be shown as the query is typed.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchResult<'_>> {
+        let mut words = tokenize(query).collect::<Vec<_>>();
+        let last = query
+            .split(|c: char| !c.is_alphanumeric())
+            .rfind(|word| !word.is_empty())
+            .map(str::to_lowercase);
+        if let Some(last) = last.filter(|last| !query.ends_with(' ') && last.chars().count() >= 3) {
+            let expanded = self
+                .terms
+                .range(last.clone()..)
+                .take_while(|(term, _)| term.starts_with(&last))
+                .map(|(term, _)| term.clone());
+            words.extend(expanded);
+        }
+        words.sort();
+        words.dedup();
+
+        let count = self.documents.len() as f32;
+        let average = self.lengths.iter().sum::<u32>() as f32 / count.max(1.0);
+        let mut scores = HashMap::<u32, f32>::new();
+        for word in &words {
+            let Some(postings) = self.terms.get(word) else {
+                continue;
+            };
+
+            let frequency = postings.len() as f32;
+            let idf = (1.0 + (count - frequency + 0.5) / (frequency + 0.5)).ln();
+            for &(id, tf) in postings {
+                let tf = tf as f32;
+                let length = self.lengths[id as usize] as f32;
+                let norm = K1 * (1.0 - B + B * length / average);
+                *scores.entry(id).or_default() += idf * tf * (K1 + 1.0) / (tf + norm);
+            }
+        }
+
+        let mut results = scores
+            .into_iter()
+            .map(|(id, score)| SearchResult {
+                document: &self.documents[id as usize],
+                score,
+            })
+            .collect::<Vec<_>>();
+        results.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.document.path.cmp(&b.document.path))
+        });
+        results.truncate(limit);
+        results
+    }
+}
+
+/// Split text into stemmed, lowercase terms.
+///
+/// Identifiers such as `AllowCameraAccess` are split into their words.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    let stemmer = Stemmer::create(Algorithm::English);
+    text.split(|c: char| !c.is_alphanumeric())
+        .flat_map(split_identifier)
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .map(move |word| stemmer.stem(&word).into_owned())
+}
+
+/// Split a camel case identifier into words, keeping acronyms together such as `HTTP` and `Proxy` in `HTTPProxy`.
+fn split_identifier(word: &str) -> Vec<&str> {
+    let chars = word.char_indices().collect::<Vec<_>>();
+    let mut words = Vec::new();
+    let mut start = 0;
+    for i in 1..chars.len() {
+        let (index, c) = chars[i];
+        let prev = chars[i - 1].1;
+        // The last capital of an acronym starts a new word, unless it's a suffix like the `v2` in `VPNv2`
+        let next_word = chars[i + 1..]
+            .iter()
+            .take(2)
+            .filter(|(_, c)| c.is_lowercase())
+            .count()
+            == 2;
+        let boundary = (prev.is_lowercase() && c.is_uppercase())
+            || (prev.is_uppercase() && c.is_uppercase() && next_word);
+        if boundary {
+            words.push(&word[start..index]);
+            start = index;
+        }
+    }
+    if start < word.len() {
+        words.push(&word[start..]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn document(path: &str, title: &str, description: &str) -> Document {
+        Document {
+            platform: Platform::Windows,
+            path: path.into(),
+            title: title.into(),
+            description: Some(description.into()),
+            keywords: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("AllowCameraAccess").collect::<Vec<_>>(),
+            ["allow", "camera", "access"]
+        );
+        assert_eq!(
+            tokenize("./Device/Vendor/MSFT/HTTPProxy").collect::<Vec<_>>(),
+            ["devic", "vendor", "msft", "http", "proxi"]
+        );
+        assert_eq!(
+            tokenize("Disables the cameras").collect::<Vec<_>>(),
+            ["disabl", "camera"]
+        );
+        assert_eq!(split_identifier("VPNv2"), ["VPNv2"]);
+    }
+
+    #[test]
+    fn test_search() {
+        let index = SearchIndex::new(vec![
+            document(
+                "./Device/Vendor/MSFT/Policy/Config/Camera/AllowCamera",
+                "Allow Camera",
+                "Disables or enables the camera.",
+            ),
+            document(
+                "./Device/Vendor/MSFT/Policy/Config/Privacy/LetAppsAccessCamera",
+                "Let Apps Access Camera",
+                "Specifies whether Windows apps can access the camera.",
+            ),
+            document(
+                "./Device/Vendor/MSFT/Policy/Config/Bluetooth/AllowDiscoverableMode",
+                "Allow Discoverable Mode",
+                "Disables or enables whether other Bluetooth devices can discover the device.",
+            ),
+        ]);
+
+        let paths = |query| {
+            index
+                .search(query, 10)
+                .into_iter()
+                .map(|result| result.document.path.rsplit('/').next().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            paths("disable camera"),
+            [
+                "AllowCamera",
+                "LetAppsAccessCamera",
+                "AllowDiscoverableMode"
+            ]
+        );
+        assert_eq!(paths("bluetooth"), ["AllowDiscoverableMode"]);
+        // The last word is a prefix while typing
+        assert_eq!(paths("blueto"), ["AllowDiscoverableMode"]);
+        assert!(paths("blueto ").is_empty());
+        assert!(paths("printer").is_empty());
+
+        let json = serde_json::to_string(&index).unwrap();
+        let index = serde_json::from_str::<SearchIndex>(&json).unwrap();
+        assert_eq!(index.search("camera", 1)[0].document.title, "Allow Camera");
+    }
+
+    /// The fixture which `packages/configuration-schemas/tests/search.test.ts` is also tested against.
+    #[derive(Deserialize)]
+    struct Fixture {
+        documents: Vec<Document>,
+        index: SearchIndex,
+        stems: BTreeMap<String, String>,
+        queries: Vec<Query>,
+    }
+
+    #[derive(Deserialize)]
+    struct Query {
+        query: String,
+        paths: Vec<String>,
+    }
+
+    #[test]
+    fn test_fixture() {
+        let fixture =
+            serde_json::from_str::<Fixture>(include_str!("../tests/search.json")).unwrap();
+
+        // The TypeScript search only reads the index, so it must be the one built from these documents
+        let index = SearchIndex::new(fixture.documents);
+        assert_eq!(
+            serde_json::to_value(&index).unwrap(),
+            serde_json::to_value(&fixture.index).unwrap()
+        );
+
+        let stemmer = Stemmer::create(Algorithm::English);
+        for (word, stem) in fixture.stems {
+            assert_eq!(stemmer.stem(&word), stem, "{word:?}");
+        }
+
+        for Query { query, paths } in fixture.queries {
+            let results = index.search(&query, 20);
+            let results = results
+                .iter()
+                .map(|result| result.document.path.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(results, paths, "{query:?}");
+        }
+    }
+}
@@ -0,0 +1 @@
+ALTER TABLE `devices` ADD `inventory` json;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE `policy_deploy_status` ADD `result` json;
\ No newline at end of file
@@ -0,0 +1,7 @@
+CREATE TABLE `agent_releases` (
+	`channel` varchar(32) NOT NULL,
+	`manifest` text NOT NULL,
+	`signature` varchar(128) NOT NULL,
+	`updated_at` timestamp NOT NULL DEFAULT (now()),
+	CONSTRAINT `agent_releases_channel` PRIMARY KEY(`channel`)
+);
\ No newline at end of file
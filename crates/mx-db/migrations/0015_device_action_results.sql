@@ -0,0 +1,8 @@
+ALTER TABLE `device_actions` MODIFY COLUMN `action` enum('restart','shutdown','lost','wipe','retire','sync','script','logs') NOT NULL;--> statement-breakpoint
+ALTER TABLE `device_actions` ADD `id` varchar(24) NOT NULL;--> statement-breakpoint
+ALTER TABLE `device_actions` ADD `payload` json;--> statement-breakpoint
+ALTER TABLE `device_actions` ADD `acked_at` timestamp;--> statement-breakpoint
+ALTER TABLE `device_actions` ADD `completed_at` timestamp;--> statement-breakpoint
+ALTER TABLE `device_actions` ADD `result` json;--> statement-breakpoint
+UPDATE `device_actions` SET `id` = LEFT(REPLACE(UUID(), '-', ''), 24);--> statement-breakpoint
+ALTER TABLE `device_actions` ADD CONSTRAINT `device_actions_id_unique` UNIQUE(`id`);
\ No newline at end of file
@@ -0,0 +1,23 @@
+CREATE TABLE `device_diagnostics` (
+	`pk` serial AUTO_INCREMENT NOT NULL,
+	`id` varchar(24) NOT NULL,
+	`device` bigint unsigned NOT NULL,
+	`action` varchar(24),
+	`size` bigint unsigned NOT NULL,
+	`sha256` varchar(64) NOT NULL,
+	`received` bigint unsigned NOT NULL DEFAULT 0,
+	`created_at` timestamp NOT NULL DEFAULT (now()),
+	`completed_at` timestamp,
+	CONSTRAINT `device_diagnostics_pk` PRIMARY KEY(`pk`),
+	CONSTRAINT `device_diagnostics_id_unique` UNIQUE(`id`)
+);
+--> statement-breakpoint
+CREATE TABLE `device_diagnostics_chunks` (
+	`diagnostics` bigint unsigned NOT NULL,
+	`offset` bigint unsigned NOT NULL,
+	`data` mediumblob NOT NULL,
+	CONSTRAINT `device_diagnostics_chunks_diagnostics_offset_pk` PRIMARY KEY(`diagnostics`,`offset`)
+);
+--> statement-breakpoint
+ALTER TABLE `device_diagnostics` ADD CONSTRAINT `device_diagnostics_device_devices_pk_fk` FOREIGN KEY (`device`) REFERENCES `devices`(`pk`) ON DELETE no action ON UPDATE no action;--> statement-breakpoint
+ALTER TABLE `device_diagnostics_chunks` ADD CONSTRAINT `device_diagnostics_chunks_diagnostics_device_diagnostics_pk_fk` FOREIGN KEY (`diagnostics`) REFERENCES `device_diagnostics`(`pk`) ON DELETE no action ON UPDATE no action;
\ No newline at end of file
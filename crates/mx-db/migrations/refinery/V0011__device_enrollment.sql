@@ -0,0 +1,21 @@
+CREATE TABLE `device_authority` (
+	`id` serial AUTO_INCREMENT NOT NULL,
+	`public` varchar(5048) NOT NULL,
+	`private` varchar(5048) NOT NULL,
+	`created_at` timestamp NOT NULL,
+	`expires_at` timestamp NOT NULL,
+	CONSTRAINT `device_authority_id` PRIMARY KEY(`id`)
+);
+
+CREATE TABLE `enrollment_tokens` (
+	`token` varchar(64) NOT NULL,
+	`tenant` bigint unsigned NOT NULL,
+	`created_by` bigint unsigned,
+	`created_at` timestamp NOT NULL DEFAULT (now()),
+	`expires_at` timestamp,
+	CONSTRAINT `enrollment_tokens_token` PRIMARY KEY(`token`)
+);
+
+ALTER TABLE `devices` MODIFY COLUMN `os` enum('Windows','iOS','macOS','tvOS','Android','ChromeOS','Linux') NOT NULL;
+ALTER TABLE `enrollment_tokens` ADD CONSTRAINT `enrollment_tokens_tenant_tenant_pk_fk` FOREIGN KEY (`tenant`) REFERENCES `tenant`(`pk`) ON DELETE no action ON UPDATE no action;
+ALTER TABLE `enrollment_tokens` ADD CONSTRAINT `enrollment_tokens_created_by_accounts_pk_fk` FOREIGN KEY (`created_by`) REFERENCES `accounts`(`pk`) ON DELETE no action ON UPDATE no action;
\ No newline at end of file
@@ -0,0 +1,8 @@
+ALTER TABLE `device_actions` MODIFY COLUMN `action` enum('restart','shutdown','lost','wipe','retire','sync','script','logs') NOT NULL;
+ALTER TABLE `device_actions` ADD `id` varchar(24) NOT NULL;
+ALTER TABLE `device_actions` ADD `payload` json;
+ALTER TABLE `device_actions` ADD `acked_at` timestamp;
+ALTER TABLE `device_actions` ADD `completed_at` timestamp;
+ALTER TABLE `device_actions` ADD `result` json;
+UPDATE `device_actions` SET `id` = LEFT(REPLACE(UUID(), '-', ''), 24);
+ALTER TABLE `device_actions` ADD CONSTRAINT `device_actions_id_unique` UNIQUE(`id`);
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE `device_requests` (
+	`device` bigint unsigned NOT NULL,
+	`idempotency_key` varchar(64) NOT NULL,
+	`created_at` timestamp NOT NULL DEFAULT (now()),
+	CONSTRAINT `device_requests_device_idempotency_key_pk` PRIMARY KEY(`device`,`idempotency_key`)
+);
+
+ALTER TABLE `device_requests` ADD CONSTRAINT `device_requests_device_devices_pk_fk` FOREIGN KEY (`device`) REFERENCES `devices`(`pk`) ON DELETE no action ON UPDATE no action;
\ No newline at end of file
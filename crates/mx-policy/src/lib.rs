@@ -0,0 +1,165 @@
+//! Mattrax's policy format.
+//!
+//! The TypeScript types in `packages/policy` are generated from this crate by the `export` test.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+mod linux;
+
+pub use linux::*;
+
+/// Define the platforms that are supported by Mattrax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+pub enum Platform {
+    #[serde(rename = "windows")]
+    Windows,
+    #[serde(rename = "macOS")]
+    MacOS,
+    #[serde(rename = "iOS")]
+    IOS,
+    #[serde(rename = "iPadOS")]
+    IPadOS,
+    #[serde(rename = "tvOS")]
+    TvOS,
+    #[serde(rename = "watchOS")]
+    WatchOS,
+    #[serde(rename = "linux")]
+    Linux,
+    #[serde(rename = "android")]
+    Android,
+    #[serde(rename = "ChromeOS")]
+    ChromeOS,
+}
+
+/// TODO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+pub struct Policy {
+    pub id: String,
+    pub name: String,
+    pub data: PolicyData,
+}
+
+/// TODO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+pub struct PolicyData {
+    /// SyncML nodes
+    pub windows: BTreeMap<String, BTreeMap<String, WindowsConfigValue>>,
+    /// inner part of the `.mobileconfig`
+    pub macos: BTreeMap<String, Vec<BTreeMap<String, AppleConfigValue>>>,
+    /// Android configuration
+    pub android: (),
+    /// Linux configuration
+    pub linux: Option<LinuxConfig>,
+    /// Scripts
+    pub scripts: Vec<Script>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+#[serde(untagged)]
+pub enum WindowsConfigValue {
+    Integer(i64),
+    String(String),
+    Boolean(bool),
+    Dictionary(BTreeMap<String, BTreeMap<String, WindowsConfigValue>>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+#[serde(untagged)]
+pub enum AppleConfigValue {
+    Integer(i64),
+    String(String),
+    Boolean(bool),
+}
+
+/// TODO
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+pub struct Script {
+    pub shell: Shell,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, specta(optional))]
+    pub supported: Option<Vec<Platform>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, specta(optional))]
+    pub trigger: Option<Trigger>,
+    pub run: String,
+}
+
+/// TODO
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+#[serde(rename_all = "lowercase")]
+pub enum Shell {
+    Powershell,
+    Bash,
+    Zsh,
+}
+
+/// TODO
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Trigger {
+    /// Only run the script once. If you modify the script it will run again.
+    #[default]
+    Once,
+    /// Trigger anytime a user logs in
+    Login,
+    /// Trigger anytime a user logs out
+    Logout,
+    /// Trigger anytime a device starts up
+    Startup,
+    /// Trigger anytime the network state changes
+    NetworkStateChange,
+    /// Trigger after the device has been enrolled.
+    EnrollmentComplete,
+    /// Trigger every time the device talks with Mattrax.
+    Checkin,
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use specta::NamedType;
+    use specta_typescript::Typescript;
+
+    use super::*;
+
+    #[test]
+    fn export() {
+        let mut types = String::from("//! This file is generated by the 'export' unit test in 'mx-policy'! Do not modify it manually!\n\n");
+        let type_map = &mut Default::default();
+
+        let policy = Policy::definition_named_data_type(type_map);
+        types.push_str(
+            &specta_typescript::export_named_datatype(&Typescript::default(), &policy, type_map)
+                .unwrap(),
+        );
+        types.push('\n');
+
+        type_map
+            .iter()
+            .filter(|(_, ty)| ty.name() != policy.name())
+            .for_each(|(_, ty)| {
+                types.push_str(
+                    &specta_typescript::export_named_datatype(&Typescript::default(), ty, type_map)
+                        .unwrap(),
+                );
+                types.push('\n');
+            });
+
+        fs::write(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("../../packages/policy/src/types.ts"),
+            types,
+        )
+        .unwrap();
+    }
+}
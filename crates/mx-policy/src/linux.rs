@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Declarative configuration for Linux devices, enforced by the Mattrax agent.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+pub struct LinuxConfig {
+    /// Files managed by Mattrax
+    #[serde(default)]
+    pub files: Vec<LinuxFile>,
+    /// systemd units which should be enabled or disabled
+    #[serde(default)]
+    pub services: Vec<LinuxService>,
+    /// Kernel parameters. Eg. `net.ipv4.ip_forward` -> `1`
+    #[serde(default)]
+    pub sysctl: BTreeMap<String, String>,
+    /// Packages which should be installed or removed
+    #[serde(default)]
+    pub packages: Vec<LinuxPackage>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+#[serde(rename_all = "lowercase")]
+pub enum Ensure {
+    #[default]
+    Present,
+    Absent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+pub struct LinuxFile {
+    /// Absolute path of the file
+    pub path: String,
+    #[serde(default)]
+    pub ensure: Ensure,
+    /// The file's contents. If unset the contents of an existing file are left alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, specta(optional))]
+    pub content: Option<String>,
+    /// User name of the owner
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, specta(optional))]
+    pub owner: Option<String>,
+    /// Group name of the owner
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, specta(optional))]
+    pub group: Option<String>,
+    /// Octal permissions. Eg. `0644`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(test, specta(optional))]
+    pub mode: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+pub struct LinuxService {
+    /// The systemd unit. Eg. `sshd.service`
+    pub unit: String,
+    /// Enabled units are also started and disabled units are also stopped.
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(specta::Type))]
+pub struct LinuxPackage {
+    pub name: String,
+    #[serde(default)]
+    pub ensure: Ensure,
+}
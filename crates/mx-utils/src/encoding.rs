@@ -0,0 +1,262 @@
+//! Serde adapters for binary data encoded as strings, for use with `#[serde(with = "...")]`.
+//!
+//! They work with any byte container such as `Vec<u8>` or a fixed-size array like `[u8; 20]` for a SHA-1 thumbprint,
+//! and each has an `option` module for `Option<...>` fields. Decoding accepts owned strings so escaped JSON strings work.
+//!
+//! ```ignore
+//! #[derive(Serialize, Deserialize)]
+//! struct Certificate {
+//!     #[serde(with = "mx_utils::encoding::hex_upper")]
+//!     thumbprint: [u8; 20],
+//!     #[serde(with = "mx_utils::encoding::base64::option")]
+//!     hardware_hash: Option<Vec<u8>>,
+//! }
+//! ```
+
+use std::{fmt, marker::PhantomData};
+
+// The adapter modules shadow the crates of the same name
+use ::base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serializer,
+};
+
+trait Encoding {
+    const EXPECTING: &'static str;
+
+    fn encode(bytes: &[u8]) -> String;
+
+    fn decode(s: &str) -> Result<Vec<u8>, String>;
+}
+
+// Padding is optional when decoding as not every implementation includes it
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+const BASE64_URL: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+struct Hex;
+
+impl Encoding for Hex {
+    const EXPECTING: &'static str = "a hex string";
+
+    fn encode(bytes: &[u8]) -> String {
+        ::hex::encode(bytes)
+    }
+
+    fn decode(s: &str) -> Result<Vec<u8>, String> {
+        ::hex::decode(s).map_err(|err| err.to_string())
+    }
+}
+
+struct HexUpper;
+
+impl Encoding for HexUpper {
+    const EXPECTING: &'static str = "a hex string";
+
+    fn encode(bytes: &[u8]) -> String {
+        ::hex::encode_upper(bytes)
+    }
+
+    fn decode(s: &str) -> Result<Vec<u8>, String> {
+        ::hex::decode(s).map_err(|err| err.to_string())
+    }
+}
+
+struct Base64;
+
+impl Encoding for Base64 {
+    const EXPECTING: &'static str = "a base64 string";
+
+    fn encode(bytes: &[u8]) -> String {
+        BASE64.encode(bytes)
+    }
+
+    fn decode(s: &str) -> Result<Vec<u8>, String> {
+        BASE64.decode(s).map_err(|err| err.to_string())
+    }
+}
+
+struct Base64Url;
+
+impl Encoding for Base64Url {
+    const EXPECTING: &'static str = "a URL-safe base64 string";
+
+    fn encode(bytes: &[u8]) -> String {
+        BASE64_URL.encode(bytes)
+    }
+
+    fn decode(s: &str) -> Result<Vec<u8>, String> {
+        BASE64_URL.decode(s).map_err(|err| err.to_string())
+    }
+}
+
+struct BytesVisitor<E, T>(PhantomData<(E, T)>);
+
+impl<E: Encoding, T: TryFrom<Vec<u8>>> Visitor<'_> for BytesVisitor<E, T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(E::EXPECTING)
+    }
+
+    fn visit_str<Err: de::Error>(self, s: &str) -> Result<T, Err> {
+        let bytes = E::decode(s).map_err(Err::custom)?;
+        let len = bytes.len();
+        // Fixed-size arrays fail if the length is wrong
+        T::try_from(bytes).map_err(|_| Err::invalid_length(len, &self))
+    }
+}
+
+fn deserialize<'de, E: Encoding, T: TryFrom<Vec<u8>>, D: Deserializer<'de>>(
+    d: D,
+) -> Result<T, D::Error> {
+    d.deserialize_str(BytesVisitor::<E, T>(PhantomData))
+}
+
+/// Deserializes using `E` so it can be wrapped in an `Option`.
+struct Decoded<E, T>(T, PhantomData<E>);
+
+impl<'de, E: Encoding, T: TryFrom<Vec<u8>>> Deserialize<'de> for Decoded<E, T> {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        deserialize::<E, T, D>(d).map(|value| Self(value, PhantomData))
+    }
+}
+
+macro_rules! encoding {
+    ($(#[doc = $doc:literal])* $name:ident, $encoding:ty) => {
+        $(#[doc = $doc])*
+        pub mod $name {
+            use super::*;
+
+            pub fn serialize<T: AsRef<[u8]>, S: Serializer>(bytes: &T, s: S) -> Result<S::Ok, S::Error> {
+                s.serialize_str(&<$encoding>::encode(bytes.as_ref()))
+            }
+
+            pub fn deserialize<'de, T: TryFrom<Vec<u8>>, D: Deserializer<'de>>(d: D) -> Result<T, D::Error> {
+                super::deserialize::<$encoding, T, D>(d)
+            }
+
+            /// The same encoding for `Option<...>` fields, using `null` for `None`.
+            pub mod option {
+                use super::*;
+
+                pub fn serialize<T: AsRef<[u8]>, S: Serializer>(
+                    bytes: &Option<T>,
+                    s: S,
+                ) -> Result<S::Ok, S::Error> {
+                    match bytes {
+                        Some(bytes) => s.serialize_some(&<$encoding>::encode(bytes.as_ref())),
+                        None => s.serialize_none(),
+                    }
+                }
+
+                pub fn deserialize<'de, T: TryFrom<Vec<u8>>, D: Deserializer<'de>>(
+                    d: D,
+                ) -> Result<Option<T>, D::Error> {
+                    Option::<Decoded<$encoding, T>>::deserialize(d)
+                        .map(|value| value.map(|Decoded(value, _)| value))
+                }
+            }
+        }
+    };
+}
+
+encoding!(
+    /// Lowercase hex. Either case is accepted when decoding.
+    hex,
+    Hex
+);
+encoding!(
+    /// Uppercase hex, as used for certificate thumbprints. Either case is accepted when decoding.
+    hex_upper,
+    HexUpper
+);
+encoding!(
+    /// Standard base64 with padding.
+    base64,
+    Base64
+);
+encoding!(
+    /// URL-safe base64 without padding.
+    base64_url,
+    Base64Url
+);
+
+#[cfg(test)]
+mod test {
+    use proptest::prelude::*;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Encoded {
+        #[serde(with = "super::hex")]
+        hex: Vec<u8>,
+        #[serde(with = "super::hex_upper")]
+        thumbprint: [u8; 20],
+        #[serde(with = "super::base64")]
+        base64: Vec<u8>,
+        #[serde(with = "super::base64_url")]
+        base64_url: Vec<u8>,
+        #[serde(with = "super::base64::option")]
+        optional: Option<[u8; 4]>,
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(
+            hex: Vec<u8>,
+            thumbprint: [u8; 20],
+            base64: Vec<u8>,
+            base64_url: Vec<u8>,
+            optional: Option<[u8; 4]>,
+        ) {
+            let value = Encoded { hex, thumbprint, base64, base64_url, optional };
+            let json = serde_json::to_string(&value).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Encoded>(&json).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_encoding() {
+        let value = Encoded {
+            hex: vec![0xab, 0xcd],
+            thumbprint: [0xab; 20],
+            base64: vec![0xfb, 0xff],
+            base64_url: vec![0xfb, 0xff],
+            optional: None,
+        };
+        let json = serde_json::to_value(&value).unwrap();
+        assert_eq!(json["hex"], "abcd");
+        assert_eq!(json["thumbprint"], "AB".repeat(20));
+        assert_eq!(json["base64"], "+/8=");
+        assert_eq!(json["base64_url"], "-_8");
+        assert_eq!(json["optional"], serde_json::Value::Null);
+
+        // Escaped strings can't be borrowed from the input
+        let json = format!(
+            r#"{{"hex": "\u0061bcd", "thumbprint": "{}", "base64": "+/8", "base64_url": "-_8=", "optional": "AQIDBA=="}}"#,
+            "ab".repeat(20)
+        );
+        let decoded = serde_json::from_str::<Encoded>(&json).unwrap();
+        assert_eq!(decoded.hex, [0xab, 0xcd]);
+        assert_eq!(decoded.optional, Some([1, 2, 3, 4]));
+
+        let short = json.replace(&"ab".repeat(20), "abcd");
+        assert!(serde_json::from_str::<Encoded>(&short)
+            .unwrap_err()
+            .to_string()
+            .contains("invalid length 2"));
+    }
+}
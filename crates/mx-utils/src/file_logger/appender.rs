@@ -0,0 +1,250 @@
+//! A log file writer which rotates by time or size and prunes old files.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use time::OffsetDateTime;
+
+use super::Rotation;
+
+pub(super) struct Appender {
+    dir: PathBuf,
+    prefix: String,
+    rotation: Rotation,
+    max_files: Option<usize>,
+    max_total_bytes: Option<u64>,
+    file: Option<(File, PathBuf)>,
+    /// The time period of the current file when rotating by time.
+    period: Option<String>,
+    size: u64,
+}
+
+impl Appender {
+    pub fn new(
+        dir: &Path,
+        prefix: String,
+        rotation: Rotation,
+        max_files: Option<usize>,
+        max_total_bytes: Option<u64>,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut appender = Self {
+            dir: dir.to_path_buf(),
+            prefix,
+            rotation,
+            max_files,
+            max_total_bytes,
+            file: None,
+            period: None,
+            size: 0,
+        };
+        // Open the file now so permission errors are returned instead of logs being silently dropped
+        appender.roll(OffsetDateTime::now_utc())?;
+        Ok(appender)
+    }
+
+    fn should_roll(&self, now: OffsetDateTime, len: usize) -> bool {
+        match self.rotation {
+            _ if self.file.is_none() => true,
+            Rotation::Size(max) => self.size > 0 && self.size + len as u64 > max,
+            Rotation::Never => false,
+            _ => self.period.as_deref() != Some(&period(self.rotation, now)),
+        }
+    }
+
+    fn roll(&mut self, now: OffsetDateTime) -> io::Result<()> {
+        let path = match self.rotation {
+            Rotation::Never => self.dir.join(format!("{}.log", self.prefix)),
+            Rotation::Size(_) => {
+                // Files are named by when they were created so they sort in order.
+                // Files created within the same second are numbered after the newest one, as older ones may have been pruned.
+                let stamp = period(Rotation::Size(0), now);
+                let prefix = format!("{}.{stamp}.", self.prefix);
+                let n = fs::read_dir(&self.dir)?
+                    .filter_map(|entry| entry.ok())
+                    .filter_map(|entry| {
+                        entry
+                            .file_name()
+                            .to_str()?
+                            .strip_prefix(&prefix)?
+                            .strip_suffix(".log")?
+                            .parse::<u32>()
+                            .ok()
+                    })
+                    .max()
+                    .map_or(0, |n| n + 1);
+                self.dir.join(format!("{prefix}{n:03}.log"))
+            }
+            rotation => {
+                let period = period(rotation, now);
+                let path = self.dir.join(format!("{}.{period}.log", self.prefix));
+                self.period = Some(period);
+                path
+            }
+        };
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        self.size = file.metadata()?.len();
+        self.file = Some((file, path));
+        self.prune()
+    }
+
+    /// Remove the oldest log files until we are within the limits. The current file is never removed.
+    fn prune(&self) -> io::Result<()> {
+        if self.max_files.is_none() && self.max_total_bytes.is_none() {
+            return Ok(());
+        }
+
+        let mut files = fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with(&format!("{}.", self.prefix)) && name.ends_with(".log")
+            })
+            .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?.len())))
+            .collect::<Vec<_>>();
+        files.sort();
+
+        let current = self.file.as_ref().map(|(_, path)| path);
+        let mut count = files.len();
+        let mut total = files.iter().map(|(_, len)| len).sum::<u64>();
+        for (path, len) in files {
+            let over_count = self.max_files.is_some_and(|max| count > max);
+            let over_size = self.max_total_bytes.is_some_and(|max| total > max);
+            if !over_count && !over_size {
+                break;
+            }
+            if Some(&path) == current {
+                continue;
+            }
+
+            fs::remove_file(&path)?;
+            count -= 1;
+            total -= len;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for Appender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let now = OffsetDateTime::now_utc();
+        if self.should_roll(now, buf.len()) {
+            self.roll(now)?;
+        }
+
+        let (file, _) = self.file.as_mut().expect("a file was opened");
+        let written = file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some((file, _)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The part of the file name identifying the time period. This matches the naming of `tracing_appender`.
+fn period(rotation: Rotation, now: OffsetDateTime) -> String {
+    let date = format!(
+        "{:04}-{:02}-{:02}",
+        now.year(),
+        now.month() as u8,
+        now.day()
+    );
+    match rotation {
+        Rotation::Minutely => format!("{date}-{:02}-{:02}", now.hour(), now.minute()),
+        Rotation::Hourly => format!("{date}-{:02}", now.hour()),
+        Rotation::Daily | Rotation::Never => date,
+        Rotation::Size(_) => format!(
+            "{date}-{:02}-{:02}-{:02}",
+            now.hour(),
+            now.minute(),
+            now.second()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn files(dir: &Path) -> Vec<(String, u64)> {
+        let mut files = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .map(|entry| {
+                (
+                    entry.file_name().to_string_lossy().to_string(),
+                    entry.metadata().unwrap().len(),
+                )
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_size_rotation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let mut appender =
+            Appender::new(dir, "test".into(), Rotation::Size(100), Some(3), None).unwrap();
+        for _ in 0..10 {
+            appender.write_all(&[b'a'; 40]).unwrap();
+        }
+
+        // 400 bytes in 5 files of 80 bytes, of which only the newest 3 are kept
+        let files = files(dir);
+        assert_eq!(files.len(), 3);
+        assert!(files.iter().all(|(_, len)| *len == 80));
+
+        // The total size limit removes files, even with no limit on the number
+        let mut appender =
+            Appender::new(dir, "test".into(), Rotation::Size(100), None, Some(150)).unwrap();
+        for _ in 0..10 {
+            appender.write_all(&[b'a'; 40]).unwrap();
+        }
+        let total = self::files(dir).iter().map(|(_, len)| len).sum::<u64>();
+        assert!(total <= 150 + 100, "{total} bytes of logs were kept");
+    }
+
+    #[test]
+    fn test_size_rotation_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+
+        let mut appender =
+            Appender::new(dir, "test".into(), Rotation::Size(100), Some(2), None).unwrap();
+        for c in b'a'..=b'j' {
+            appender.write_all(&[c; 60]).unwrap();
+        }
+
+        // Every write starts a new file, and the newest files sort last even after the oldest were pruned
+        let contents = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .map(|path| fs::read(path).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(contents, vec![vec![b'i'; 60], vec![b'j'; 60]]);
+    }
+
+    #[test]
+    fn test_period() {
+        let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        assert_eq!(period(Rotation::Daily, now), "2023-11-14");
+        assert_eq!(period(Rotation::Hourly, now), "2023-11-14-22");
+        assert_eq!(period(Rotation::Size(0), now), "2023-11-14-22-13-20");
+    }
+}
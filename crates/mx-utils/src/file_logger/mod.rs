@@ -0,0 +1,305 @@
+//! Logging to rotated files in the data directory, and optionally stdout.
+//!
+//! ```ignore
+//! let logger = FileLogger::new(data_dir.join("logs"))
+//!     .rotation(Rotation::Size(10 * 1024 * 1024))
+//!     .max_total_bytes(Some(100 * 1024 * 1024))
+//!     .target_level("mattraxd", LevelFilter::DEBUG)
+//!     .init()?;
+//! ```
+
+use std::path::PathBuf;
+
+use tracing_appender::non_blocking::{NonBlocking, WorkerGuard};
+use tracing_subscriber::{
+    filter::LevelFilter, layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Layer,
+    Registry,
+};
+
+use crate::telemetry::{Telemetry, TelemetryGuard};
+
+mod appender;
+mod json;
+
+/// When to start a new log file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Minutely,
+    Hourly,
+    Daily,
+    /// Start a new file once the current one would exceed this many bytes.
+    Size(u64),
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+pub struct FileLogger {
+    dir: PathBuf,
+    prefix: String,
+    rotation: Rotation,
+    max_files: Option<usize>,
+    max_total_bytes: Option<u64>,
+    format: Format,
+    stdout: bool,
+    level: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+    telemetry: Option<Telemetry>,
+}
+
+impl FileLogger {
+    /// Log to daily rotated `mttx.*.log` files in `dir`, keeping 7 files. Logs are also written to stdout.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            prefix: "mttx".into(),
+            rotation: Rotation::Daily,
+            max_files: Some(7),
+            max_total_bytes: None,
+            format: Format::Text,
+            stdout: true,
+            level: LevelFilter::INFO,
+            targets: Vec::new(),
+            telemetry: None,
+        }
+    }
+
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    pub fn rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// The maximum number of log files to keep, including the current one. `None` keeps every file.
+    pub fn max_files(mut self, max: Option<usize>) -> Self {
+        self.max_files = max;
+        self
+    }
+
+    /// The maximum size of all log files combined. `None` doesn't limit the size.
+    ///
+    /// This is checked when rotating, so use [`Rotation::Size`] for a strict limit.
+    pub fn max_total_bytes(mut self, max: Option<u64>) -> Self {
+        self.max_total_bytes = max;
+        self
+    }
+
+    /// The format of the log files. Stdout is always text.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn stdout(mut self, enabled: bool) -> Self {
+        self.stdout = enabled;
+        self
+    }
+
+    /// The level for targets without their own level.
+    pub fn level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn target_level(mut self, target: impl Into<String>, level: LevelFilter) -> Self {
+        self.targets.push((target.into(), level));
+        self
+    }
+
+    /// Also export spans and metrics to an OpenTelemetry collector, if it's enabled.
+    pub fn telemetry(mut self, telemetry: Telemetry) -> Self {
+        self.telemetry = Some(telemetry);
+        self
+    }
+
+    /// The filter directives for the configured levels. `RUST_LOG` takes precedence if it's set.
+    fn directives(&self) -> String {
+        std::env::var("RUST_LOG").unwrap_or_else(|_| {
+            std::iter::once(self.level.to_string().to_lowercase())
+                .chain(self.targets.iter().map(|(target, level)| {
+                    format!("{target}={}", level.to_string().to_lowercase())
+                }))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    }
+
+    /// Install the logger as the global subscriber.
+    pub fn init(self) -> Result<Logger, String> {
+        let filter = EnvFilter::try_new(self.directives())
+            .map_err(|err| format!("error parsing log filter: {err}"))?;
+        let (filter, handle) = reload::Layer::new(filter);
+
+        let appender = appender::Appender::new(
+            &self.dir,
+            self.prefix,
+            self.rotation,
+            self.max_files,
+            self.max_total_bytes,
+        )
+        .map_err(|err| format!("error opening log file in {:?}: {err}", self.dir))?;
+        let (logfile, guard) = NonBlocking::new(appender);
+
+        let file = tracing_subscriber::fmt::layer()
+            .with_target(false)
+            .with_file(true)
+            .with_line_number(true)
+            .with_ansi(false)
+            .with_writer(logfile);
+        let mut layers = vec![match self.format {
+            Format::Text => file.boxed(),
+            Format::Json => file.event_format(json::Json).boxed(),
+        }];
+        let telemetry = match &self.telemetry {
+            Some(telemetry) => {
+                let (telemetry_layers, guard) = telemetry.layers()?;
+                layers.extend(telemetry_layers);
+                Some(guard)
+            }
+            None => None,
+        };
+        if self.stdout {
+            layers.push(
+                tracing_subscriber::fmt::layer()
+                    .with_target(false)
+                    .with_file(true)
+                    .with_line_number(true)
+                    .with_writer(std::io::stdout)
+                    .boxed(),
+            );
+        }
+
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(layers)
+            .try_init()
+            .map_err(|err| format!("error setting up logging: {err}"))?;
+
+        Ok(Logger {
+            _guard: guard,
+            _telemetry: telemetry,
+            filter: LogFilter(handle),
+        })
+    }
+}
+
+/// Keeps the background writer and any telemetry exporters alive. Logs are flushed when this is dropped.
+pub struct Logger {
+    _guard: WorkerGuard,
+    _telemetry: Option<TelemetryGuard>,
+    filter: LogFilter,
+}
+
+impl Logger {
+    pub fn filter(&self) -> LogFilter {
+        self.filter.clone()
+    }
+}
+
+/// A handle for changing the log filter at runtime.
+#[derive(Clone)]
+pub struct LogFilter(reload::Handle<EnvFilter, Registry>);
+
+impl LogFilter {
+    /// Replace the filter with new directives, in the same format as `RUST_LOG`.
+    pub fn reload(&self, directives: &str) -> Result<(), String> {
+        let filter = EnvFilter::try_new(directives)
+            .map_err(|err| format!("invalid log filter {directives:?}: {err}"))?;
+        self.0
+            .reload(filter)
+            .map_err(|err| format!("error reloading log filter: {err}"))
+    }
+
+    pub fn current(&self) -> Option<String> {
+        self.0.with_current(|filter| filter.to_string()).ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use super::*;
+
+    /// Captures the output of a `fmt` layer.
+    #[derive(Clone, Default)]
+    pub(super) struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Buffer {
+        pub fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Self;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_directives() {
+        // `RUST_LOG` would take precedence
+        if std::env::var("RUST_LOG").is_ok() {
+            return;
+        }
+
+        let logger = FileLogger::new("logs")
+            .level(LevelFilter::WARN)
+            .target_level("mattraxd", LevelFilter::DEBUG);
+        assert_eq!(logger.directives(), "warn,mattraxd=debug");
+        assert!(EnvFilter::try_new(logger.directives()).is_ok());
+    }
+
+    #[test]
+    fn test_reload() {
+        let (layer, handle) = reload::Layer::new(EnvFilter::new("warn"));
+        let filter = LogFilter(handle);
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(layer).with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(buffer.clone()),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("before reload");
+            filter.reload("info").unwrap();
+            tracing::info!("after reload");
+
+            assert_eq!(filter.current().as_deref(), Some("info"));
+            // An invalid filter leaves the current one in place
+            assert!(filter.reload("mattraxd=loud").is_err());
+            assert_eq!(filter.current().as_deref(), Some("info"));
+        });
+        let logs = buffer.contents();
+        assert!(!logs.contains("before reload"), "{logs}");
+        assert!(logs.contains("after reload"), "{logs}");
+    }
+}
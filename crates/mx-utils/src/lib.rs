@@ -1,4 +1,21 @@
 //! Standard utilities for Mattrax crates.
 
+pub mod encoding;
 pub mod file_logger;
-pub mod serde_with_hex;
+pub mod telemetry;
+
+/// Use [`encoding::hex`] instead.
+#[deprecated(note = "use `mx_utils::encoding::hex` instead")]
+pub mod serde_with_hex {
+    use serde::{Deserializer, Serializer};
+
+    #[deprecated(note = "use `mx_utils::encoding::hex::deserialize` instead")]
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        super::encoding::hex::deserialize(d)
+    }
+
+    #[deprecated(note = "use `mx_utils::encoding::hex::serialize` instead")]
+    pub fn serialize<S: Serializer>(bytes: &Vec<u8>, s: S) -> Result<S::Ok, S::Error> {
+        super::encoding::hex::serialize(bytes, s)
+    }
+}
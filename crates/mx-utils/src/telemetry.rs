@@ -0,0 +1,313 @@
+//! Tracing setup shared by Mattrax services, with optional export of spans and metrics to an OpenTelemetry collector.
+//!
+//! Export is enabled when an OTLP endpoint is configured, using [`Telemetry::endpoint`] or the standard
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` environment variable. Data is sent as HTTP/protobuf (usually port `4318`).
+//!
+//! ```ignore
+//! let _telemetry = Telemetry::new(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+//!     .git_hash(env!("GIT_HASH"))
+//!     .init()?;
+//! ```
+//!
+//! The trace context is carried between services in the W3C `traceparent` header,
+//! using [`inject`] on outgoing requests and [`set_parent`] on incoming ones.
+
+use http::HeaderMap;
+use opentelemetry::{
+    global,
+    metrics::Meter,
+    propagation::TextMapPropagator,
+    trace::{TraceContextExt, TracerProvider as _},
+};
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
+use opentelemetry_otlp::{MetricExporter, Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    metrics::{PeriodicReader, SdkMeterProvider},
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::TracerProvider,
+    Resource,
+};
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{
+    layer::SubscriberExt, registry::LookupSpan, util::SubscriberInitExt, EnvFilter, Layer,
+};
+
+pub use opentelemetry::{metrics::Histogram, KeyValue};
+
+const ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+type Layers<S> = Vec<Box<dyn Layer<S> + Send + Sync>>;
+
+pub struct Telemetry {
+    service: &'static str,
+    version: &'static str,
+    attributes: Vec<KeyValue>,
+    endpoint: Option<String>,
+}
+
+impl Telemetry {
+    pub fn new(service: &'static str, version: &'static str) -> Self {
+        Self {
+            service,
+            version,
+            attributes: Vec::new(),
+            endpoint: None,
+        }
+    }
+
+    /// The commit the service was built from.
+    pub fn git_hash(self, hash: &str) -> Self {
+        self.attribute("vcs.ref.head.revision", hash.trim())
+    }
+
+    pub fn device_id(self, id: impl Into<String>) -> Self {
+        self.attribute("device.id", id.into())
+    }
+
+    /// Add an attribute to the resource which every span and metric is reported under.
+    pub fn attribute(mut self, key: &'static str, value: impl Into<String>) -> Self {
+        self.attributes.push(KeyValue::new(key, value.into()));
+        self
+    }
+
+    /// The base URL of the collector, eg. `http://localhost:4318`. `OTEL_EXPORTER_OTLP_ENDPOINT` takes precedence if it's set.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    fn resource(&self) -> Resource {
+        let attributes = [
+            KeyValue::new("service.name", self.service),
+            KeyValue::new("service.version", self.version),
+        ]
+        .into_iter()
+        .chain(self.attributes.iter().cloned());
+
+        // The default resource includes `OTEL_RESOURCE_ATTRIBUTES`
+        Resource::default().merge(&Resource::new(attributes))
+    }
+
+    /// The layers which export to the collector, or none if export isn't enabled.
+    ///
+    /// This must be called from within a Tokio runtime as the exporters run in the background.
+    pub(crate) fn layers<S>(&self) -> Result<(Layers<S>, TelemetryGuard), String>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+    {
+        let Some(endpoint) = std::env::var(ENDPOINT_ENV).ok().or(self.endpoint.clone()) else {
+            return Ok((Vec::new(), TelemetryGuard(None)));
+        };
+        let endpoint = endpoint.trim_end_matches('/');
+        let resource = self.resource();
+
+        let spans = SpanExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpBinary)
+            .with_endpoint(format!("{endpoint}/v1/traces"))
+            .build()
+            .map_err(|err| format!("error creating span exporter: {err}"))?;
+        let tracer_provider = TracerProvider::builder()
+            .with_batch_exporter(spans, runtime::Tokio)
+            .with_resource(resource.clone())
+            .build();
+
+        let metrics = MetricExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpBinary)
+            .with_endpoint(format!("{endpoint}/v1/metrics"))
+            .build()
+            .map_err(|err| format!("error creating metric exporter: {err}"))?;
+        let meter_provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(metrics, runtime::Tokio).build())
+            .with_resource(resource)
+            .build();
+        global::set_meter_provider(meter_provider.clone());
+
+        let layer = tracing_opentelemetry::layer()
+            .with_tracer(tracer_provider.tracer(self.service))
+            .boxed();
+        Ok((
+            vec![layer],
+            TelemetryGuard(Some((tracer_provider, meter_provider))),
+        ))
+    }
+
+    /// Install a global subscriber which logs to stdout and exports to the collector if enabled.
+    ///
+    /// `RUST_LOG` configures the filter, which defaults to `info`.
+    pub fn init(self) -> Result<TelemetryGuard, String> {
+        let filter = EnvFilter::try_from_default_env()
+            .or_else(|_| EnvFilter::try_new("info"))
+            .map_err(|err| format!("error parsing log filter: {err}"))?;
+        let (mut layers, guard) = self.layers()?;
+        layers.push(tracing_subscriber::fmt::layer().boxed());
+
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(layers)
+            .try_init()
+            .map_err(|err| format!("error setting up logging: {err}"))?;
+        Ok(guard)
+    }
+}
+
+/// Flushes any spans and metrics which haven't been exported when dropped.
+pub struct TelemetryGuard(Option<(TracerProvider, SdkMeterProvider)>);
+
+impl TelemetryGuard {
+    /// Whether spans and metrics are being exported.
+    pub fn is_enabled(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some((tracer_provider, meter_provider)) = self.0.take() {
+            let _ = tracer_provider.shutdown();
+            let _ = meter_provider.shutdown();
+        }
+    }
+}
+
+/// The meter for recording metrics. This does nothing if export isn't enabled.
+pub fn meter() -> Meter {
+    global::meter("mattrax")
+}
+
+/// Add the trace context of `span` to the headers of an outgoing request.
+pub fn inject(span: &Span, headers: &mut HeaderMap) {
+    TraceContextPropagator::new().inject_context(&span.context(), &mut HeaderInjector(headers));
+}
+
+/// Continue the trace from an incoming request in `span`, if the request has a trace context.
+pub fn set_parent(span: &Span, headers: &HeaderMap) {
+    let cx = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+    if cx.span().span_context().is_valid() {
+        span.set_parent(cx);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+        thread,
+        time::Duration,
+    };
+
+    use tracing::info_span;
+
+    use super::*;
+
+    /// A stand in for a collector which accepts every request, returning the path and body of each.
+    fn collector() -> (String, mpsc::Receiver<(String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut stream = stream;
+                    loop {
+                        let mut request_line = String::new();
+                        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        let path = request_line.split(' ').nth(1).unwrap_or("").to_string();
+
+                        let mut length = 0;
+                        loop {
+                            let mut line = String::new();
+                            reader.read_line(&mut line).unwrap();
+                            let line = line.trim_end();
+                            if line.is_empty() {
+                                break;
+                            }
+                            if let Some((name, value)) = line.split_once(':') {
+                                if name.eq_ignore_ascii_case("content-length") {
+                                    length = value.trim().parse().unwrap();
+                                }
+                            }
+                        }
+
+                        let mut body = vec![0; length];
+                        reader.read_exact(&mut body).unwrap();
+                        let _ = tx.send((path, body));
+                        stream
+                            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        (endpoint, rx)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_export() {
+        if std::env::var(ENDPOINT_ENV).is_ok() {
+            return;
+        }
+        let (endpoint, requests) = collector();
+
+        let telemetry = Telemetry::new("mx-utils-test", "0.0.0")
+            .device_id("device-123")
+            .endpoint(endpoint);
+        let (layers, guard) = telemetry.layers().unwrap();
+        assert!(guard.is_enabled());
+
+        let subscriber = tracing_subscriber::registry().with(layers);
+        let (agent, cloud) = tracing::subscriber::with_default(subscriber, || {
+            // The trace continues across the request
+            let sync = info_span!("device_sync");
+            let mut headers = HeaderMap::new();
+            inject(&sync, &mut headers);
+            assert!(headers.contains_key("traceparent"));
+
+            let request = info_span!("http_request");
+            set_parent(&request, &headers);
+            (
+                sync.context().span().span_context().trace_id(),
+                request.context().span().span_context().trace_id(),
+            )
+        });
+        assert_eq!(agent, cloud);
+        drop(guard);
+
+        let (_, body) = std::iter::from_fn(|| requests.recv_timeout(Duration::from_secs(5)).ok())
+            .find(|(path, _)| path == "/v1/traces")
+            .expect("spans were exported");
+        let contains = |needle: &[u8]| body.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"device_sync"));
+        assert!(contains(b"http_request"));
+        assert!(contains(b"mx-utils-test"));
+        assert!(contains(b"device-123"));
+    }
+
+    #[test]
+    fn test_disabled() {
+        if std::env::var(ENDPOINT_ENV).is_ok() {
+            return;
+        }
+
+        let (layers, guard) = Telemetry::new("mx-utils-test", "0.0.0")
+            .layers::<tracing_subscriber::Registry>()
+            .unwrap();
+        assert!(layers.is_empty());
+        assert!(!guard.is_enabled());
+
+        // Without a trace there is nothing to propagate
+        let mut headers = HeaderMap::new();
+        inject(&Span::none(), &mut headers);
+        assert!(headers.is_empty());
+    }
+}
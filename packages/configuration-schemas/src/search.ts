@@ -0,0 +1,145 @@
+// This file mirrors the search in `crates/mx-catalog` so the web UI can search without a server.
+//
+// Queries are stemmed with the same stemmer as the index (see `stem.ts`), so both return the same results. They're
+// tested against the same fixture in `crates/mx-catalog/tests/search.json`.
+
+import { stem } from "./stem";
+
+export type Platform = "windows" | "apple";
+
+/**
+ * What the index keeps of a setting to show it in the results.
+ *
+ * Look the setting up by `path` in the platform's schema for its description.
+ */
+export type Document = {
+	platform: Platform;
+	/**
+	 * The OMA-URI of a CSP node, or the payload domain and key of an Apple setting such as `com.apple.screensaver/idleTime`.
+	 */
+	path: string;
+	title: string;
+};
+
+export type SearchIndex = {
+	documents: Document[];
+	/**
+	 * The documents each stemmed term appears in, as `[document, weighted frequency]`.
+	 */
+	terms: { [term in string]: [number, number][] };
+	/**
+	 * The weighted number of terms in each document.
+	 */
+	lengths: number[];
+};
+
+export type SearchResult = { document: Document; score: number };
+
+// BM25 parameters
+const K1 = 1.2;
+const B = 0.75;
+
+// Anything but the letters and numbers of a word
+const NON_WORD = /[^\p{Alphabetic}\p{N}]+/u;
+
+const STOP_WORDS = new Set([
+	"a",
+	"an",
+	"and",
+	"are",
+	"as",
+	"be",
+	"by",
+	"for",
+	"if",
+	"in",
+	"is",
+	"it",
+	"of",
+	"on",
+	"or",
+	"the",
+	"this",
+	"to",
+	"when",
+	"which",
+	"will",
+	"with",
+]);
+
+/**
+ * Find the documents which best match a query, with the best match first.
+ *
+ * Documents only need to match one word of the query. The last word also matches any term it's a prefix of,
+ * so results can be shown as the query is typed.
+ */
+export function search(
+	index: SearchIndex,
+	query: string,
+	limit = 20,
+): SearchResult[] {
+	const words = new Set(tokenize(query));
+	const last = query
+		.split(NON_WORD)
+		.filter((word) => word !== "")
+		.pop()
+		?.toLowerCase();
+	if (last && !query.endsWith(" ") && [...last].length >= 3) {
+		for (const term of Object.keys(index.terms))
+			if (term.startsWith(last)) words.add(term);
+	}
+
+	const count = index.documents.length;
+	const average =
+		index.lengths.reduce((sum, length) => sum + length, 0) / Math.max(count, 1);
+	const scores = new Map<number, number>();
+	for (const word of words) {
+		// `terms` is a plain object, so a word such as `constructor` would otherwise find its prototype
+		if (!Object.hasOwn(index.terms, word)) continue;
+		const postings = index.terms[word]!;
+
+		const frequency = postings.length;
+		const idf = Math.log(1 + (count - frequency + 0.5) / (frequency + 0.5));
+		for (const [id, tf] of postings) {
+			const norm = K1 * (1 - B + (B * index.lengths[id]!) / average);
+			const score = (idf * tf * (K1 + 1)) / (tf + norm);
+			scores.set(id, (scores.get(id) ?? 0) + score);
+		}
+	}
+
+	return [...scores]
+		.map(([id, score]) => ({ document: index.documents[id]!, score }))
+		.sort(
+			(a, b) =>
+				b.score - a.score ||
+				(a.document.path < b.document.path
+					? -1
+					: a.document.path > b.document.path
+						? 1
+						: 0),
+		)
+		.slice(0, limit);
+}
+
+/**
+ * Split text into stemmed, lowercase terms.
+ *
+ * Identifiers such as `AllowCameraAccess` are split into their words.
+ */
+export function tokenize(text: string): string[] {
+	return text
+		.split(NON_WORD)
+		.flatMap(splitIdentifier)
+		.map((word) => word.toLowerCase())
+		.filter((word) => word !== "" && !STOP_WORDS.has(word))
+		.map(stem);
+}
+
+/**
+ * Split a camel case identifier into words, keeping acronyms together such as `HTTP` and `Proxy` in `HTTPProxy`.
+ */
+function splitIdentifier(word: string): string[] {
+	return word.split(
+		/(?<=\p{Lowercase})(?=\p{Uppercase})|(?<=\p{Uppercase})(?=\p{Uppercase}\p{Lowercase}{2})/u,
+	);
+}
@@ -0,0 +1,232 @@
+// The English (Porter2) stemmer from Snowball, which `crates/mx-catalog` stems the index with.
+//
+// This follows `english.sbl` as compiled into the `rust-stemmers` crate, so a query is stemmed to the same terms as the
+// index. See https://snowballstem.org/algorithms/english/stemmer.html for a description of each step.
+
+const EXCEPTIONS = new Map([
+	["skis", "ski"],
+	["skies", "sky"],
+	["dying", "die"],
+	["lying", "lie"],
+	["tying", "tie"],
+	["idly", "idl"],
+	["gently", "gentl"],
+	["ugly", "ugli"],
+	["early", "earli"],
+	["only", "onli"],
+	["singly", "singl"],
+	["sky", "sky"],
+	["news", "news"],
+	["howe", "howe"],
+	["atlas", "atlas"],
+	["cosmos", "cosmos"],
+	["bias", "bias"],
+	["andes", "andes"],
+]);
+
+// Words which are left alone once their plural is removed
+const INVARIANTS = new Set([
+	"inning",
+	"outing",
+	"canning",
+	"herring",
+	"earring",
+	"proceed",
+	"exceed",
+	"succeed",
+]);
+
+// R1 of a word starting with one of these is the rest of the word
+const R1_PREFIXES = ["gener", "commun", "arsen"];
+
+const STEP_2: { [suffix in string]: string } = {
+	tional: "tion",
+	enci: "ence",
+	anci: "ance",
+	abli: "able",
+	entli: "ent",
+	izer: "ize",
+	ization: "ize",
+	ational: "ate",
+	ation: "ate",
+	ator: "ate",
+	alism: "al",
+	aliti: "al",
+	alli: "al",
+	fulness: "ful",
+	ousli: "ous",
+	ousness: "ous",
+	iveness: "ive",
+	iviti: "ive",
+	biliti: "ble",
+	bli: "ble",
+	// Only after an `l`
+	ogi: "og",
+	fulli: "ful",
+	lessli: "less",
+	// Only after one of `cdeghkmnrt`
+	li: "",
+};
+
+const STEP_3: { [suffix in string]: string } = {
+	tional: "tion",
+	ational: "ate",
+	alize: "al",
+	icate: "ic",
+	iciti: "ic",
+	ical: "ic",
+	ful: "",
+	ness: "",
+	// Only in R2
+	ative: "",
+};
+
+const STEP_4 = [
+	"al",
+	"ance",
+	"ence",
+	"er",
+	"ic",
+	"able",
+	"ible",
+	"ant",
+	"ement",
+	"ment",
+	"ent",
+	"ism",
+	"ate",
+	"iti",
+	"ous",
+	"ive",
+	"ize",
+	// Only after an `s` or `t`
+	"ion",
+];
+
+function isVowel(c: string | undefined) {
+	return c !== undefined && "aeiouy".includes(c);
+}
+
+/**
+ * Whether the word ends in a short syllable before `end`, such as `hop` in `hoping`.
+ */
+function isShortSyllable(word: string, end: number) {
+	if (end === 2) return isVowel(word[0]) && !isVowel(word[1]);
+	return (
+		end >= 3 &&
+		!isVowel(word[end - 3]) &&
+		isVowel(word[end - 2]) &&
+		!"aeiouywxY".includes(word[end - 1]!)
+	);
+}
+
+/**
+ * The longest of the suffixes which the word ends with.
+ */
+function longestSuffix(word: string, suffixes: string[]) {
+	return suffixes
+		.filter((suffix) => word.endsWith(suffix))
+		.sort((a, b) => b.length - a.length)[0];
+}
+
+/**
+ * Stem a lowercase word, such as `devices` to `devic`.
+ */
+export function stem(word: string): string {
+	const exception = EXCEPTIONS.get(word);
+	if (exception !== undefined) return exception;
+	if (word.length < 3) return word;
+
+	// A `y` which is a consonant is marked as `Y` until the end
+	let w = word.startsWith("'") ? word.slice(1) : word;
+	let yFound = false;
+	for (let i = 0; i < w.length; i++) {
+		if (w[i] === "y" && (i === 0 || isVowel(w[i - 1]))) {
+			w = `${w.slice(0, i)}Y${w.slice(i + 1)}`;
+			yFound = true;
+		}
+	}
+
+	// R1 starts after the first non-vowel which follows a vowel, and R2 after the next one in R1
+	const afterSyllable = (from: number) => {
+		let i = from;
+		while (i < w.length && !isVowel(w[i])) i++;
+		while (i < w.length && isVowel(w[i])) i++;
+		return Math.min(i + 1, w.length);
+	};
+	const prefix = R1_PREFIXES.find((prefix) => w.startsWith(prefix));
+	const p1 = prefix?.length ?? afterSyllable(0);
+	const p2 = afterSyllable(p1);
+	const inR1 = (suffix: string) => w.length - suffix.length >= p1;
+	const inR2 = (suffix: string) => w.length - suffix.length >= p2;
+	const replace = (suffix: string, replacement: string) => {
+		w = w.slice(0, w.length - suffix.length) + replacement;
+	};
+
+	// Step 1a
+	const apostrophe = longestSuffix(w, ["'", "'s'", "'s"]);
+	if (apostrophe) replace(apostrophe, "");
+	const plural = longestSuffix(w, ["sses", "ied", "ies", "s", "ss", "us"]);
+	if (plural === "sses") replace(plural, "ss");
+	else if (plural === "ied" || plural === "ies")
+		replace(plural, w.length > 4 ? "i" : "ie");
+	// The letter before the `s` doesn't count, so `gas` is kept but `gaps` isn't
+	else if (plural === "s" && [...w.slice(0, -2)].some(isVowel))
+		replace(plural, "");
+
+	if (!INVARIANTS.has(w)) {
+		// Step 1b
+		const suffix = longestSuffix(w, [
+			"eed",
+			"eedly",
+			"ed",
+			"edly",
+			"ing",
+			"ingly",
+		]);
+		if (suffix === "eed" || suffix === "eedly") {
+			if (inR1(suffix)) replace(suffix, "ee");
+		} else if (suffix && [...w.slice(0, -suffix.length)].some(isVowel)) {
+			replace(suffix, "");
+			if (longestSuffix(w, ["at", "bl", "iz"])) w += "e";
+			else if (/(bb|dd|ff|gg|mm|nn|pp|rr|tt)$/.test(w)) w = w.slice(0, -1);
+			else if (w.length === p1 && isShortSyllable(w, w.length)) w += "e";
+		}
+
+		// Step 1c
+		if (/[yY]$/.test(w) && w.length > 2 && !isVowel(w[w.length - 2]))
+			replace("y", "i");
+
+		// Step 2
+		const step2 = longestSuffix(w, Object.keys(STEP_2));
+		if (step2 && inR1(step2)) {
+			const before = w[w.length - step2.length - 1] ?? "";
+			if (step2 === "ogi") {
+				if (before === "l") replace(step2, STEP_2[step2]!);
+			} else if (step2 === "li") {
+				if (before !== "" && "cdeghkmnrt".includes(before)) replace(step2, "");
+			} else replace(step2, STEP_2[step2]!);
+		}
+
+		// Step 3
+		const step3 = longestSuffix(w, Object.keys(STEP_3));
+		if (step3 && inR1(step3) && (step3 !== "ative" || inR2(step3)))
+			replace(step3, STEP_3[step3]!);
+
+		// Step 4
+		const step4 = longestSuffix(w, STEP_4);
+		if (step4 && inR2(step4)) {
+			const before = w[w.length - step4.length - 1];
+			if (step4 !== "ion" || before === "s" || before === "t")
+				replace(step4, "");
+		}
+
+		// Step 5
+		if (w.endsWith("e")) {
+			if (inR2("e") || (inR1("e") && !isShortSyllable(w, w.length - 1)))
+				replace("e", "");
+		} else if (w.endsWith("ll") && inR2("l")) replace("l", "");
+	}
+
+	return yFound ? w.replace(/Y/g, "y") : w;
+}
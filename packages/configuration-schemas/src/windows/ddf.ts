@@ -1,31 +1,127 @@
+/**
+ * What a device requires for the node to apply, inherited from the closest ancestor which has one.
+ */
+export type Applicability = {
+	osBuildVersion?: string | null;
+	cspVersion?: string | null;
+	/**
+	 * The ids of the Windows editions the node applies to, or every edition when unset.
+	 * An id such as `0x88*` includes every edition whose id starts with those hex digits.
+	 */
+	editions?: string[] | null;
+	requiresAzureAd: boolean;
+};
 export type EnumContent = { description: string | null };
-export type IntAllowedValues =
+/**
+ * A Group Policy and the nodes which configure it.
+ */
+export type GroupPolicy = {
+	/**
+	 * The category path of the policy, such as `Printing~AT~ControlPanel~CplPrinters`.
+	 */
+	area: string;
+	/**
+	 * The name of the policy in its ADMX file, such as `NoAddPrinter`.
+	 */
+	name: string;
+	/**
+	 * The ADMX file the policy is defined in, if the node is backed by it.
+	 */
+	file?: string | null;
+} & {
+	/**
+	 * The name of the policy shown in the Group Policy editor and in GPO reports, if its ADMX file was provided.
+	 */
+	displayName?: string | null;
+	nodes: GroupPolicyNode[];
+};
+export type GroupPolicyNode = {
+	path: string;
+	/**
+	 * The element of the policy the node maps to, if it only maps to part of it.
+	 */
+	element?: string | null;
+};
+export type NodeAllowedValues =
 	| { valueType: "range"; min: number; max: number }
-	| { valueType: "enum"; enum: { [key in string]: EnumContent } };
+	| { valueType: "enum"; enum: { [key in string]: EnumContent } }
+	/**
+	 * Any combination of the values, added together.
+	 */
+	| { valueType: "flag"; flags: { [key in string]: EnumContent } }
+	| { valueType: "regex"; pattern: string }
+	/**
+	 * An ADMX-backed policy, whose value is the `<enabled/>` or `<disabled/>` XML.
+	 */
+	| { valueType: "admx"; file: string; name: string }
+	| { valueType: "xsd" }
+	| { valueType: "sddl" }
+	| { valueType: "json" };
+/**
+ * A Group Policy which a node configures.
+ */
+export type NodeGroupPolicy = {
+	/**
+	 * The category path of the policy, such as `Printing~AT~ControlPanel~CplPrinters`.
+	 */
+	area: string;
+	/**
+	 * The name of the policy in its ADMX file, such as `NoAddPrinter`.
+	 */
+	name: string;
+	/**
+	 * The ADMX file the policy is defined in, if the node is backed by it.
+	 */
+	file?: string | null;
+} & { element?: string | null };
+export type Occurrence =
+	| "one"
+	| "zeroOrOne"
+	| "zeroOrMore"
+	| "oneOrMore"
+	| { zeroOrN: number }
+	| { oneOrN: number };
 export type Scope = "user" | "device";
 export type WindowsCSP = {
 	name: string;
 	nodes: { [key in string]: WindowsDDFNode };
 };
 export type WindowsDDFNode = (
-	| {
-			format: "int";
-			defaultValue: number;
-			allowedValues?: IntAllowedValues | null;
-	  }
-	| { format: "bool" }
-	| { format: "string" }
+	| { format: "int"; defaultValue?: number | null }
+	| { format: "bool"; defaultValue?: boolean | null }
+	| { format: "string"; defaultValue?: string | null }
 	| { format: "node"; nodes: { [key in string]: WindowsDDFNode } }
 	| { format: "null" }
-	| { format: "base64" }
+	| { format: "base64"; defaultValue?: string | null }
+	| { format: "date" }
 	| { format: "time" }
-	| { format: "float" }
+	| { format: "float"; defaultValue?: number | null }
 	| { format: "xml" }
 	| { format: "bin" }
 ) & {
 	name: string;
 	title?: string | null;
 	description?: string | null;
+	allowedValues?: NodeAllowedValues | null;
+	/**
+	 * Set when the value is a list of items separated by this delimiter, which each match `allowed_values`.
+	 */
+	listDelimiter?: string | null;
+	occurrence: Occurrence;
+	applicability?: Applicability | null;
 	scope: Scope;
 	dynamic: string | null;
 };
+/**
+ * The Group Policies which can be configured with a CSP, looked up by policy or by node.
+ */
+export type GpMappingTable = {
+	/**
+	 * Sorted by the area and name of the policy.
+	 */
+	policies: GroupPolicy[];
+	/**
+	 * The policies each node maps to, keyed by OMA-URI.
+	 */
+	nodes: { [key in string]: NodeGroupPolicy[] };
+};
@@ -0,0 +1,14 @@
+import { expect, test } from "vitest";
+import fixture from "../../../crates/mx-catalog/tests/search.json";
+import { type SearchIndex, search } from "../src/search";
+import { stem } from "../src/stem";
+
+// `crates/mx-catalog` is tested against the same fixture, so both stem and search the same way
+test.each(Object.entries(fixture.stems))("stem(%s)", (word, expected) => {
+	expect(stem(word)).toBe(expected);
+});
+
+test.each(fixture.queries)("search($query)", ({ query, paths }) => {
+	const results = search(fixture.index as SearchIndex, query);
+	expect(results.map((result) => result.document.path)).toEqual(paths);
+});
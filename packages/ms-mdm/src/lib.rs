@@ -19,7 +19,6 @@ mod item;
 mod meta;
 mod mode;
 mod msg_ref;
-mod os_edition;
 mod replace;
 mod results;
 mod routing;
@@ -31,6 +30,8 @@ mod sync_ml;
 
 pub mod util;
 
+pub use ms_ddf::OsEdition;
+
 pub use add::Add;
 pub use alert::{Alert, AlertType};
 pub use atomic::Atomic;
@@ -45,7 +46,6 @@ pub use item::Item;
 pub use meta::{Format, Meta, MAX_REQUEST_BODY_SIZE};
 pub use mode::Mode;
 pub use msg_ref::MsgRef;
-pub use os_edition::OsEdition;
 pub use r#final::Final;
 pub use replace::Replace;
 pub use results::Results;
@@ -311,12 +311,22 @@ function NodeEditor(props: {
 						class="w-2 h-2 bg-brand rounded-full absolute -left-4 top-1.5 ring-brand ring-offset-2 focus-visible:ring-2 transition-shadow outline-none"
 					/>
 				)}
-				<Show when={node().format === "bool"}>
-					<Checkbox
-						id={id}
-						checked={(!!props.data as boolean) || false}
-						onChange={(checked) => props.setData(props.path, checked)}
-					/>
+				<Show
+					when={(() => {
+						const n = node();
+						return n.format === "bool" && n;
+					})()}
+					keyed
+				>
+					{(node) => (
+						<Checkbox
+							id={id}
+							checked={
+								(props.data as boolean | undefined) ?? node.defaultValue ?? false
+							}
+							onChange={(checked) => props.setData(props.path, checked)}
+						/>
+					)}
 				</Show>
 				<label
 					class="font-medium text-sm"
@@ -356,8 +366,8 @@ function NodeEditor(props: {
 							fallback={
 								<NumberInput
 									class="mt-2"
-									defaultValue={policy.defaultValue}
-									value={(props.data as number) ?? policy.defaultValue}
+									defaultValue={policy.defaultValue ?? undefined}
+									value={(props.data as number) ?? policy.defaultValue ?? undefined}
 									onChange={(value) => props.setData(props.path, value)}
 								>
 									<div class="relative">
@@ -434,8 +444,8 @@ function NodeEditor(props: {
 											class="mt-2"
 											minValue={allowedValues().min}
 											maxValue={allowedValues().max}
-											defaultValue={policy.defaultValue}
-											value={(props.data as number) ?? policy.defaultValue}
+											defaultValue={policy.defaultValue ?? undefined}
+											value={(props.data as number) ?? policy.defaultValue ?? undefined}
 											onChange={(value) => props.setData(props.path, value)}
 										>
 											<div class="relative">
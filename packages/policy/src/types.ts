@@ -1,6 +1,62 @@
 //! This file is generated by the 'export' unit test in 'mx-policy'! Do not modify it manually!
 
 export type AppleConfigValue = number | string | boolean;
+export type Ensure = "present" | "absent";
+/**
+ * Declarative configuration for Linux devices, enforced by the Mattrax agent.
+ */
+export type LinuxConfig = {
+	/**
+	 * Files managed by Mattrax
+	 */
+	files: LinuxFile[];
+	/**
+	 * systemd units which should be enabled or disabled
+	 */
+	services: LinuxService[];
+	/**
+	 * Kernel parameters. Eg. `net.ipv4.ip_forward` -> `1`
+	 */
+	sysctl: { [key in string]: string };
+	/**
+	 * Packages which should be installed or removed
+	 */
+	packages: LinuxPackage[];
+};
+export type LinuxFile = {
+	/**
+	 * Absolute path of the file
+	 */
+	path: string;
+	ensure: Ensure;
+	/**
+	 * The file's contents. If unset the contents of an existing file are left alone.
+	 */
+	content?: string;
+	/**
+	 * User name of the owner
+	 */
+	owner?: string;
+	/**
+	 * Group name of the owner
+	 */
+	group?: string;
+	/**
+	 * Octal permissions. Eg. `0644`
+	 */
+	mode?: string;
+};
+export type LinuxPackage = { name: string; ensure: Ensure };
+export type LinuxService = {
+	/**
+	 * The systemd unit. Eg. `sshd.service`
+	 */
+	unit: string;
+	/**
+	 * Enabled units are also started and disabled units are also stopped.
+	 */
+	enabled: boolean;
+};
 /**
  * Define the platforms that are supported by Mattrax.
  */
@@ -37,7 +93,7 @@ export type PolicyData = {
 	/**
 	 * Linux configuration
 	 */
-	linux: null;
+	linux: LinuxConfig | null;
 	/**
 	 * Scripts
 	 */